@@ -0,0 +1,7 @@
+/* STM32F405RG, in place of the embassy-stm32 memory-x feature so sectors 10 and 11 are kept
+   out of the image, they hold the config store (crates/ner-config-store/src/stm32.rs) */
+MEMORY
+{
+    FLASH : ORIGIN = 0x08000000, LENGTH = 768K
+    RAM : ORIGIN = 0x20000000, LENGTH = 128K
+}
@@ -10,11 +10,16 @@ use embassy_sync::{
     channel::Receiver,
     signal::Signal,
 };
+use ner_can_messages::{
+    external::{BmsCurrentLimits, DtiErpm},
+    CanMessage,
+};
 
 const CAN_BITRATE: u32 = 500_000;
 
-pub const DTI_RPM_MSG_ID: StandardId = StandardId::new(0x416).expect("Cannot parse ID");
-const BMS_DCL_MSG_ID: StandardId = StandardId::new(0x156).expect("Cannot parse ID");
+pub const DTI_RPM_MSG_ID: StandardId = StandardId::new(DtiErpm::DEF.id).expect("Cannot parse ID");
+const BMS_DCL_MSG_ID: StandardId =
+    StandardId::new(BmsCurrentLimits::DEF.id).expect("Cannot parse ID");
 
 #[embassy_executor::task]
 /// Handles CAN, giving messages to DTI and BMS as they match via ID
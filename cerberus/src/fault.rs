@@ -1,16 +1,15 @@
-use defmt::{debug, unwrap, warn};
+use defmt::{debug, warn};
 use embassy_futures::select::select3;
-use embassy_stm32::can::{Frame, StandardId};
+use embassy_stm32::can::Frame;
 use embassy_sync::{
     blocking_mutex::raw::{CriticalSectionRawMutex, ThreadModeRawMutex},
     channel::Sender,
     signal::Signal,
 };
 use embassy_time::{Duration, Ticker};
+use ner_can_messages::cerberus::CerberusStatus;
 
-use crate::{FaultCode, FunctionalType, StateTransition};
-
-const STATUS_MSG_ID: StandardId = StandardId::new(0x502).expect("Cannot parse ID");
+use crate::{message_frame, FaultCode, FunctionalType, StateTransition};
 
 /// time at which to unfault the car if the faulting condition has cleared
 const UNFAULT_TIME: Duration = Duration::from_secs(5);
@@ -28,8 +27,6 @@ pub async fn fault_handler(
 ) {
     let mut last_fault = FaultCode::FaultsClear;
 
-    let mut fault_bits: [u8; 5] = [0u8; 5];
-
     let mut fault_cansend_ticker = Ticker::every(SEND_STATUS_MSG_TIME);
 
     let mut unfault_ticker = Ticker::every(UNFAULT_TIME);
@@ -69,11 +66,10 @@ pub async fn fault_handler(
             }
         };
 
-        fault_bits[3..4].copy_from_slice(&(last_fault.get_severity() as u8).to_be_bytes());
-        fault_bits[0..3].copy_from_slice(&(last_fault as u32).to_be_bytes());
-
-        can_send
-            .send(unwrap!(Frame::new_data(STATUS_MSG_ID, &fault_bits)))
-            .await;
+        let status = CerberusStatus {
+            fault_code: last_fault as u32,
+            severity: last_fault.get_severity() as u8,
+        };
+        can_send.send(message_frame(&status)).await;
     }
 }
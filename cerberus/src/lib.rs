@@ -14,6 +14,78 @@ pub type SharedI2c = embassy_sync::mutex::Mutex<
     embassy_stm32::i2c::I2c<'static, embassy_stm32::mode::Async>,
 >;
 
+/// Build a frame for a message using the ID of its definition
+pub fn message_frame<M: ner_can_messages::CanMessage>(msg: &M) -> embassy_stm32::can::Frame {
+    defmt::unwrap!(embassy_stm32::can::Frame::new_data(
+        defmt::unwrap!(embassy_stm32::can::StandardId::new(M::DEF.id)),
+        msg.encode().as_bytes()
+    ))
+}
+
+/// Per car constants that used to be hard coded, kept in flash so they can be tuned per car
+#[derive(Copy, Clone, PartialEq, defmt::Format)]
+pub struct Calibration {
+    /// Ratio of the LV sense resistor divider
+    pub lv_sense_divider: f32,
+    /// Tire diameter in inches
+    pub tire_diameter: f32,
+    /// Motor turns per wheel turn
+    pub gear_ratio: f32,
+    /// Motor pole pairs, to turn ERPM into RPM
+    pub pole_pairs: u16,
+}
+
+impl Calibration {
+    pub const DEFAULT: Calibration = Calibration {
+        // nobody remembers the exact resistor config
+        lv_sense_divider: 8.967,
+        tire_diameter: 16.0,
+        gear_ratio: 47.0 / 13.0,
+        pole_pairs: 10,
+    };
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl ner_config_store::Record for Calibration {
+    const VERSION: u16 = 1;
+    const SIZE: usize = 14;
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.lv_sense_divider.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.tire_diameter.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.gear_ratio.to_le_bytes());
+        buf[12..14].copy_from_slice(&self.pole_pairs.to_le_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let f32_at = |i: usize| f32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+        let calibration = Calibration {
+            lv_sense_divider: f32_at(0),
+            tire_diameter: f32_at(4),
+            gear_ratio: f32_at(8),
+            pole_pairs: u16::from_le_bytes([buf[12], buf[13]]),
+        };
+        // a zero would divide by zero or read 0 V, and NaN compares false
+        let positive = [
+            calibration.lv_sense_divider,
+            calibration.tire_diameter,
+            calibration.gear_ratio,
+        ]
+        .iter()
+        .all(|value| *value > 0.0 && value.is_finite());
+        (positive && calibration.pole_pairs > 0).then_some(calibration)
+    }
+}
+
+/// Flash storage of the [`Calibration`], in the last two sectors
+pub type CerberusConfigStore =
+    ner_config_store::ConfigStore<ner_config_store::stm32::Stm32Flash, Calibration>;
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum FunctionalType {
     READY,
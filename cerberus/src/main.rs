@@ -8,8 +8,8 @@ use core::{
 };
 
 use cerberus::{
-    bms, can_handler, dti, fault, monitor, state_machine, FaultCode, PduCommand, SharedI2c,
-    StateTransition,
+    bms, can_handler, dti, fault, monitor, state_machine, Calibration, FaultCode, PduCommand,
+    SharedI2c, StateTransition,
 };
 use cortex_m::{peripheral::SCB, singleton};
 use cortex_m_rt::{exception, ExceptionFrame};
@@ -26,6 +26,7 @@ use embassy_stm32::{
 };
 use embassy_stm32::{
     can::Frame,
+    flash::Flash,
     gpio::{Level, Output, Speed},
     peripherals,
     usart::{self, Uart},
@@ -40,6 +41,7 @@ use embassy_sync::{
 };
 use embassy_time::Timer;
 use heapless::String;
+use ner_config_store::{stm32::Stm32Flash, ConfigStore};
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
@@ -92,6 +94,25 @@ async fn main(spawner: Spawner) -> ! {
 
     let mut p = embassy_stm32::init(Config::default());
 
+    // before the watchdog, opening the store may erase a flash sector
+    let calibration = match ConfigStore::new(Stm32Flash::new(Flash::new_blocking(p.FLASH))) {
+        Ok(mut store) => match store.load() {
+            Ok(Some(calibration)) => calibration,
+            // save the defaults so there is a record to tune from a debugger
+            _ => {
+                if let Err(err) = store.store(&Calibration::DEFAULT) {
+                    warn!("Could not save default calibration: {}", err);
+                }
+                Calibration::DEFAULT
+            }
+        },
+        Err(err) => {
+            warn!("Could not open config storage, using defaults: {}", err);
+            Calibration::DEFAULT
+        }
+    };
+    info!("Calibration: {}", calibration);
+
     let can = Can::new(p.CAN1, p.PA11, p.PA12, IrqsCAN);
     if let Err(err) = spawner.spawn(can_handler::can_handler(
         can,
@@ -105,7 +126,7 @@ async fn main(spawner: Spawner) -> ! {
     if let Err(err) = spawner.spawn(bms::bms_handler(&BMS_CALLBACK, &FAULT)) {
         warn!("Could not spawn BMS task: {}", err);
     }
-    if let Err(err) = spawner.spawn(dti::dti_handler(&DTI_CALLBACK, &DTI_MPH)) {
+    if let Err(err) = spawner.spawn(dti::dti_handler(&DTI_CALLBACK, &DTI_MPH, calibration)) {
         warn!("Could not spawn DTI task: {}", err);
     }
 
@@ -157,7 +178,11 @@ async fn main(spawner: Spawner) -> ! {
         .expect("Could not init adc buffer");
     let mut adc1 = adc1.into_ring_buffered(p.DMA2_CH4, adc_data_1);
     adc1.set_sample_sequence(Sequence::One, &mut p.PB0, SampleTime::CYCLES112); // LV sense
-    if let Err(err) = spawner.spawn(monitor::lv_sense_handler(adc1, CAN_CHANNEL.sender())) {
+    if let Err(err) = spawner.spawn(monitor::lv_sense_handler(
+        adc1,
+        CAN_CHANNEL.sender(),
+        calibration,
+    )) {
         warn!("Could not spawn LV sense task: {}", err);
     }
 
@@ -4,22 +4,17 @@ use bitfield::Bit;
 use defmt::{unwrap, warn};
 use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
 use embassy_futures::select::{self, select3, select_array};
-use embassy_stm32::{
-    adc::RingBufferedAdc,
-    can::{Frame, StandardId},
-    exti::ExtiInput,
-    peripherals::ADC1,
-};
+use embassy_stm32::{adc::RingBufferedAdc, can::Frame, exti::ExtiInput, peripherals::ADC1};
 use embassy_sync::{
     blocking_mutex::raw::ThreadModeRawMutex,
     channel::{Receiver, Sender},
 };
 use embassy_time::{Duration, Instant, Ticker, Timer};
+use ner_can_messages::cerberus::{FuseStatus, LvSense};
 use pca9539_ner::{Pca9539, Pin};
 
-use crate::{PduCommand, SharedI2c};
+use crate::{message_frame, Calibration, PduCommand, SharedI2c};
 
-const LV_SENSE_MSG_ID: StandardId = StandardId::new(0x503).expect("Cannot parse ID");
 const LV_SENSE_REFRESH_TIME: Duration = Duration::from_millis(750);
 
 #[embassy_executor::task]
@@ -27,6 +22,7 @@ const LV_SENSE_REFRESH_TIME: Duration = Duration::from_millis(750);
 pub async fn lv_sense_handler(
     mut adc1: RingBufferedAdc<'static, ADC1>,
     can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    calibration: Calibration,
 ) {
     let mut measurements: [u16; 20] = [0u16; 40 / 2];
 
@@ -34,14 +30,10 @@ pub async fn lv_sense_handler(
         match adc1.read(&mut measurements).await {
             Ok(_) => {
                 adc1.teardown_adc();
-                // 8.97 is mahic no. bc nobody remembers the exact resistor config
-                let v_in = (measurements[0] as f32 * 8.967 * 10f32) as u32;
+                let v_in = (measurements[0] as f32 * calibration.lv_sense_divider * 10f32) as u32;
                 // TODO transform measurements
                 can_send
-                    .send(unwrap!(Frame::new_data(
-                        LV_SENSE_MSG_ID,
-                        &v_in.to_be_bytes()
-                    )))
+                    .send(message_frame(&LvSense { lv_sense: v_in }))
                     .await;
             }
             Err(_) => {
@@ -69,7 +61,16 @@ pub async fn ctrl_expander_handler(
     ctrl_expand_i2c: &'static SharedI2c,
     ts_state_send: &'static AtomicBool,
 ) {
-    let i2c_dev = I2cDevice::new(ctrl_expand_i2c);
+    let mut i2c_dev = I2cDevice::new(ctrl_expand_i2c);
+    // the setup below panics without the expander, so say why first
+    match ner_i2c_probe::probe(&mut i2c_dev, CTRL_EXPANDER_I2C_ADDR).await {
+        Ok(true) => (),
+        Ok(false) => warn!(
+            "Nothing answers at the ctrl expander address {:#x}",
+            CTRL_EXPANDER_I2C_ADDR
+        ),
+        Err(err) => warn!("Could not probe the ctrl expander: {}", err),
+    }
     let mut pca9539 = Pca9539::new(i2c_dev, CTRL_EXPANDER_I2C_ADDR).unwrap();
 
     // initial setup
@@ -175,34 +176,19 @@ pub async fn ctrl_expander_handler(
                         .await
                 );
 
-                let mut send_data_1: u8 = 0;
-                let mut send_data_2: u8 = 0;
-
-                send_data_1.set_bit(0, data_0.bit(4));
-                send_data_1.set_bit(1, data_0.bit(5));
-                send_data_1.set_bit(2, data_0.bit(6));
-                send_data_1.set_bit(3, data_0.bit(7));
-                send_data_1.set_bit(4, data_1.bit(0));
-                send_data_1.set_bit(5, data_1.bit(1));
-                send_data_1.set_bit(6, data_1.bit(2));
-                send_data_1.set_bit(7, data_1.bit(3));
-                send_data_2.set_bit(0, data_1.bit(4));
-
-                // reverse bits
-                send_data_1 = send_data_1.reverse_bits();
-                send_data_2 = send_data_2.reverse_bits();
+                let fuses = FuseStatus {
+                    battbox: data_0.bit(4),
+                    lvbox: data_0.bit(5),
+                    fan_radiator: data_0.bit(6),
+                    mc: data_0.bit(7),
+                    fan_battbox: data_1.bit(0),
+                    pump: data_1.bit(1),
+                    dashboard: data_1.bit(2),
+                    brakelight: data_1.bit(3),
+                    brb: data_1.bit(4),
+                };
 
-                let mut send_data_bits: [u8; 2] = [0u8; 2];
-
-                send_data_bits[0..1].copy_from_slice(&send_data_1.to_be_bytes());
-                send_data_bits[1..2].copy_from_slice(&send_data_2.to_be_bytes());
-
-                can_send
-                    .send(unwrap!(Frame::new_data(
-                        unwrap!(StandardId::new(0x111)),
-                        &send_data_bits
-                    )))
-                    .await;
+                can_send.send(message_frame(&fuses)).await;
             }
             select::Either3::Third(cmd) => match cmd {
                 PduCommand::WritePump(state) => {
@@ -6,9 +6,14 @@ use core::convert::TryFrom;
 
 use regs::*;
 
-pub use regs::{AccelerometerOutput, AccelerometerScale, GyroscopeFullScale, GyroscopeOutput};
+pub use regs::{
+    AccelerometerBatchRate, AccelerometerFilterBandwidth, AccelerometerOutput, AccelerometerScale,
+    FifoMode, FreeFallThreshold, GyroscopeBatchRate, GyroscopeFullScale, GyroscopeHighPassCutoff,
+    GyroscopeLpf1Bandwidth, GyroscopeOutput, OrientationThreshold, Rounding, TemperatureBatchRate,
+    TimestampDecimation,
+};
 
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
 /// Enum containing all possible types of errors when interacting with the IMU
 #[derive(Debug)]
@@ -16,6 +21,8 @@ pub enum Error<E> {
     CommunicationError(E),
     ChipDetectFailed,
     RegisterReadFailed,
+    /// No new sample arrived while calibrating or self-testing, the output rate is probably off
+    DataNotReady,
 }
 
 // Value of the WHO_AM_I register
@@ -24,11 +31,445 @@ const CHIP_ID: u8 = 0x6C;
 // Earth gravity constant for acceleration conversion
 const EARTH_GRAVITY: f32 = 9.80665;
 
+// Largest FIFO watermark, FIFO_CTRL1 and one bit of FIFO_CTRL2
+const FIFO_MAX_WATERMARK: u16 = 0x1FF;
+
+// Bits of INT1_CTRL/INT2_CTRL and MD1_CFG/MD2_CFG that `route_interrupts` sets
+const INT_CTRL_ROUTED: u8 = 0b0011_1011;
+const MD_CFG_ROUTED: u8 = 0b0111_1110;
+
+// Largest tap, wake-up and free-fall settings
+const TAP_MAX_THRESHOLD: u8 = 0x1F;
+const WAKE_UP_MAX_THRESHOLD: u8 = 0x3F;
+const FREE_FALL_MAX_DURATION: u8 = 0x3F;
+
+// Weights of the X/Y/Z_OFS_USR registers in mg per bit, 2^-10 g and 2^-6 g
+const ACCEL_OFFSET_FINE_MG: f32 = 1000.0 / 1024.0;
+const ACCEL_OFFSET_COARSE_MG: f32 = 1000.0 / 64.0;
+
+// Polls of the status register for a new sample before giving up, 1 ms apart
+const DATA_READY_MAX_POLLS: u16 = 100;
+
+// Self-test pass limits from the datasheet, accelerometer at +-4 g in mg and gyroscope at
+// 2000 dps in dps
+const ACCEL_SELF_TEST_MG: (f32, f32) = (50.0, 1700.0);
+const GYRO_SELF_TEST_DPS: (f32, f32) = (150.0, 700.0);
+
+// Samples averaged with and without the self-test actuation, after the first is discarded
+const SELF_TEST_SAMPLES: u8 = 5;
+
+// Time for the output to settle after a self-test step, in ms
+const SELF_TEST_SETTLE_MS: u32 = 100;
+
+// Written to TIMESTAMP2 to reset the timestamp counter
+const TIMESTAMP_RESET: u8 = 0xAA;
+
+/// Time of one timestamp counter tick in ns, before any INTERNAL_FREQ_FINE trim
+pub const TIMESTAMP_TICK_NS: u32 = 25_000;
+
+/// What is batched into the FIFO, and how it behaves once full
+#[derive(Debug, Clone, Copy)]
+pub struct FifoConfig {
+    pub mode: FifoMode,
+    /// Unread words that set the watermark flag, clamped to 511
+    pub watermark: u16,
+    /// Stop batching at the watermark instead of when the FIFO is full
+    pub stop_on_watermark: bool,
+    pub accelerometer_rate: AccelerometerBatchRate,
+    pub gyroscope_rate: GyroscopeBatchRate,
+    pub temperature_rate: TemperatureBatchRate,
+    /// Anything but `NotBatched` also starts the timestamp counter
+    pub timestamp_decimation: TimestampDecimation,
+}
+
+/// Direction the self-test pushes the sensor in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestSign {
+    Positive,
+    Negative,
+}
+
+/// How much one self-test moved the output of each axis, in mg for the accelerometer and dps for
+/// the gyro
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTestAxes {
+    pub change: (f32, f32, f32),
+    /// Every axis moved by an amount within the datasheet limits
+    pub passed: bool,
+}
+
+/// Results of the datasheet self-test of both sensors in both directions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTest {
+    pub accelerometer_positive: SelfTestAxes,
+    pub accelerometer_negative: SelfTestAxes,
+    pub gyroscope_positive: SelfTestAxes,
+    pub gyroscope_negative: SelfTestAxes,
+}
+
+impl SelfTest {
+    pub fn accelerometer_passed(&self) -> bool {
+        self.accelerometer_positive.passed && self.accelerometer_negative.passed
+    }
+
+    pub fn gyroscope_passed(&self) -> bool {
+        self.gyroscope_positive.passed && self.gyroscope_negative.passed
+    }
+
+    pub fn passed(&self) -> bool {
+        self.accelerometer_passed() && self.gyroscope_passed()
+    }
+}
+
+/// Output rate and full scale of both sensors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SensorState {
+    pub accelerometer_output: AccelerometerOutput,
+    pub accelerometer_scale: AccelerometerScale,
+    pub gyroscope_output: GyroscopeOutput,
+    pub gyroscope_scale: GyroscopeFullScale,
+}
+
+/// Digital filtering of the accelerometer output, after the LPF1 at half the output rate that
+/// always runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelerometerFilter {
+    /// LPF1 only
+    Lpf1,
+    /// LPF2 after LPF1
+    LowPass(AccelerometerFilterBandwidth),
+    /// High pass after LPF1
+    HighPass(AccelerometerFilterBandwidth),
+}
+
+/// Digital filtering of the gyroscope output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GyroscopeFilter {
+    /// LPF1, otherwise only the LPF2 set by the output rate runs
+    pub lpf1: Option<GyroscopeLpf1Bandwidth>,
+    pub high_pass: Option<GyroscopeHighPassCutoff>,
+}
+
+/// Everything the control registers set, applied together by [`Lsm6dso::configure`].
+/// The default is the reset state, both sensors off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lsm6dsoConfig {
+    pub accelerometer_output: AccelerometerOutput,
+    pub accelerometer_scale: AccelerometerScale,
+    pub accelerometer_filter: AccelerometerFilter,
+    pub gyroscope_output: GyroscopeOutput,
+    pub gyroscope_scale: GyroscopeFullScale,
+    pub gyroscope_filter: GyroscopeFilter,
+    /// Don't update the output registers until both bytes of the last sample are read
+    pub block_data_update: bool,
+    /// Low power instead of high performance, below 208 Hz for the accelerometer
+    pub low_power: bool,
+    /// Output registers a burst read wraps around in
+    pub rounding: Rounding,
+    pub timestamp: bool,
+}
+
+impl Lsm6dsoConfig {
+    pub const RESET: Lsm6dsoConfig = Lsm6dsoConfig {
+        accelerometer_output: AccelerometerOutput::PowerDown,
+        accelerometer_scale: AccelerometerScale::G02,
+        accelerometer_filter: AccelerometerFilter::Lpf1,
+        gyroscope_output: GyroscopeOutput::PowerDown,
+        gyroscope_scale: GyroscopeFullScale::Dps250,
+        gyroscope_filter: GyroscopeFilter {
+            lpf1: None,
+            high_pass: None,
+        },
+        block_data_update: false,
+        low_power: false,
+        rounding: Rounding::None,
+        timestamp: false,
+    };
+}
+
+impl Default for Lsm6dsoConfig {
+    fn default() -> Self {
+        Self::RESET
+    }
+}
+
+/// Fill level and flags of the FIFO
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FifoStatus {
+    /// Words waiting to be read
+    pub unread: u16,
+    pub watermark: bool,
+    /// Data was lost since the status was last read
+    pub overrun: bool,
+    pub full: bool,
+}
+
+/// Data of one FIFO word, in the same units as the direct reads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FifoData {
+    /// RAD/s
+    Gyroscope((f32, f32, f32)),
+    /// m/s^2
+    Accelerometer((f32, f32, f32)),
+    /// degC
+    Temperature(f32),
+    /// Timestamp counter, in ticks of [`TIMESTAMP_TICK_NS`]
+    Timestamp(u32),
+    /// A tag this driver doesn't decode, such as compressed or sensor hub data
+    Other(u8),
+}
+
+/// One word read from the FIFO
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FifoRecord {
+    /// Two bit counter that steps on every batch event, words batched together share it
+    pub counter: u8,
+    pub data: FifoData,
+}
+
+/// One of the two interrupt pins
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptPin {
+    Int1,
+    Int2,
+}
+
+/// Events that drive an interrupt pin, the pin is the OR of every event set
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterruptRoute {
+    pub accelerometer_ready: bool,
+    pub gyroscope_ready: bool,
+    /// Held while the FIFO is at or above its watermark
+    pub fifo_watermark: bool,
+    pub fifo_overrun: bool,
+    pub fifo_full: bool,
+    pub wake_up: bool,
+    pub free_fall: bool,
+    /// 6D orientation change
+    pub orientation: bool,
+    pub single_tap: bool,
+    pub double_tap: bool,
+    pub significant_motion: bool,
+}
+
+/// Events that fired, read from ALL_INT_SRC. Reading clears them when latched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InterruptSources {
+    pub wake_up: bool,
+    pub free_fall: bool,
+    /// 6D orientation change
+    pub orientation: bool,
+    pub single_tap: bool,
+    pub double_tap: bool,
+    /// Changed between activity and inactivity
+    pub sleep_change: bool,
+}
+
+/// Any combination of the three axes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Axes {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl Axes {
+    pub const ALL: Axes = Axes {
+        x: true,
+        y: true,
+        z: true,
+    };
+
+    // x, y and z in bits 2, 1 and 0, the layout of the event source and enable registers
+    fn from_bits(bits: u8) -> Self {
+        Axes {
+            x: bits & 0b100 != 0,
+            y: bits & 0b010 != 0,
+            z: bits & 0b001 != 0,
+        }
+    }
+
+    fn bits(&self) -> u8 {
+        ((self.x as u8) << 2) | ((self.y as u8) << 1) | self.z as u8
+    }
+}
+
+/// Tap (shock) detection, with times in samples at the accelerometer output rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapConfig {
+    pub axes: Axes,
+    /// Acceleration on an enabled axis that counts as a tap, in 1/32 of the full scale, up to 31
+    pub threshold: u8,
+    /// Longest a tap may stay over the threshold, 0 is 4 samples otherwise 8 each, up to 3
+    pub shock: u8,
+    /// Time after a tap that must be quiet, 0 is 2 samples otherwise 4 each, up to 3
+    pub quiet: u8,
+    /// Longest time between the taps of a double tap, 0 is 16 samples otherwise 32 each, up to 15
+    pub duration: u8,
+    /// Detect double taps as well as single taps
+    pub double_tap: bool,
+}
+
+/// Wake-up (activity) detection on the high-pass filtered acceleration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WakeUpConfig {
+    /// Acceleration on any axis that wakes, in 1/64 of the full scale, up to 63
+    pub threshold: u8,
+    /// Samples the acceleration must stay over the threshold, up to 3
+    pub duration: u8,
+}
+
+/// Free-fall detection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeFallConfig {
+    pub threshold: FreeFallThreshold,
+    /// Samples every axis must stay under the threshold, up to 63
+    pub duration: u8,
+}
+
+/// Tap detection status, from TAP_SRC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TapSource {
+    pub single: bool,
+    pub double: bool,
+    /// Axes the tap was detected on
+    pub axes: Axes,
+    /// The tap accelerated in the negative direction
+    pub negative: bool,
+}
+
+/// Wake-up, free-fall and activity status, from WAKE_UP_SRC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WakeUpSource {
+    pub wake_up: bool,
+    pub free_fall: bool,
+    /// Changed between activity and inactivity
+    pub sleep_change: bool,
+    /// Currently inactive
+    pub sleeping: bool,
+    /// Axes the wake-up was detected on
+    pub axes: Axes,
+}
+
+/// 6D orientation status, from D6D_SRC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OrientationSource {
+    pub changed: bool,
+    /// Axes pointing up past the threshold
+    pub high: Axes,
+    /// Axes pointing down past the threshold
+    pub low: Axes,
+}
+
+/// Status of every event detector
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EventSources {
+    pub wake_up: WakeUpSource,
+    pub tap: TapSource,
+    pub orientation: OrientationSource,
+    pub significant_motion: bool,
+}
+
+/// Biases measured by [`Lsm6dso::calibrate_bias`] while the sensor was still
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ImuBias {
+    /// Written to the user offset registers and subtracted from every output by the sensor, m/s^2
+    pub accelerometer: (f32, f32, f32),
+    /// The gyro has no offset registers, subtract it with a [`GyroBiasEstimator`], rad/s
+    pub gyroscope: (f32, f32, f32),
+}
+
+/// Tracks the gyro bias by averaging the readings taken while the sensor is not turning, so the
+/// bias can follow temperature drift after a calibration.
+///
+/// A reading counts as still when every axis is within `still_rate` of the current bias. The
+/// first readings are averaged evenly, after that each still reading moves the bias by `weight`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GyroBiasEstimator {
+    bias: (f32, f32, f32),
+    still_rate: f32,
+    weight: f32,
+    samples: u32,
+}
+
+impl GyroBiasEstimator {
+    /// Start from no bias, `still_rate` in rad/s and `weight` between 0 and 1
+    pub const fn new(still_rate: f32, weight: f32) -> Self {
+        Self {
+            bias: (0.0, 0.0, 0.0),
+            still_rate,
+            weight,
+            samples: 0,
+        }
+    }
+
+    /// Start from a bias measured by [`Lsm6dso::calibrate_bias`], which is trusted like a long
+    /// average
+    pub fn with_bias(mut self, bias: (f32, f32, f32)) -> Self {
+        self.bias = bias;
+        self.samples = u32::MAX;
+        self
+    }
+
+    /// The current estimate in rad/s
+    pub fn bias(&self) -> (f32, f32, f32) {
+        self.bias
+    }
+
+    /// Add a reading in rad/s to the estimate if the sensor was still, returns whether it was
+    pub fn update(&mut self, gyro: (f32, f32, f32)) -> bool {
+        let still = |rate: f32, bias: f32| abs(rate - bias) < self.still_rate;
+        // with no bias yet anything slower than the threshold counts, a large bias would never
+        // settle otherwise
+        let reference = if self.samples == 0 {
+            (0.0, 0.0, 0.0)
+        } else {
+            self.bias
+        };
+        if !(still(gyro.0, reference.0) && still(gyro.1, reference.1) && still(gyro.2, reference.2))
+        {
+            return false;
+        }
+
+        self.samples = self.samples.saturating_add(1);
+        let weight = self.weight.max(1.0 / self.samples as f32);
+        let mix = |bias: f32, rate: f32| bias + (rate - bias) * weight;
+        self.bias = (
+            mix(self.bias.0, gyro.0),
+            mix(self.bias.1, gyro.1),
+            mix(self.bias.2, gyro.2),
+        );
+        true
+    }
+
+    /// A reading in rad/s with the bias removed
+    pub fn correct(&self, gyro: (f32, f32, f32)) -> (f32, f32, f32) {
+        (
+            gyro.0 - self.bias.0,
+            gyro.1 - self.bias.1,
+            gyro.2 - self.bias.2,
+        )
+    }
+}
+
+// f32::abs is not in core
+fn abs(value: f32) -> f32 {
+    if value < 0.0 {
+        -value
+    } else {
+        value
+    }
+}
+
 /// 6-DoF IMU accelerometer + gyro
 pub struct Lsm6dso<I2C> {
     i2c: I2C,
     addr: u8,
+    // what the sensor is known to be set to, `None` until set or read back, or after a write that
+    // failed and may or may not have happened
+    accelerometer_output: Option<AccelerometerOutput>,
     accelerometer_scale: Option<AccelerometerScale>,
+    gyroscope_output: Option<GyroscopeOutput>,
     gyroscope_scale: Option<GyroscopeFullScale>,
 }
 
@@ -42,7 +483,9 @@ where
         let mut lsm = Lsm6dso {
             i2c,
             addr,
+            accelerometer_output: None,
             accelerometer_scale: None,
+            gyroscope_output: None,
             gyroscope_scale: None,
         };
 
@@ -56,12 +499,44 @@ where
         }
     }
 
+    /// Create an instance of the Lsm6dso driver and apply `config`
+    pub async fn new_with_config(
+        i2c: I2C,
+        addr: u8,
+        config: &Lsm6dsoConfig,
+    ) -> Result<Self, Error<E>> {
+        let mut lsm = Self::new(i2c, addr).await?;
+        lsm.configure(config).await?;
+        Ok(lsm)
+    }
+
+    /// Apply every setting of `config`. The control register bits it does not cover, like the
+    /// interrupt pin settings, are left alone.
+    pub async fn configure(&mut self, config: &Lsm6dsoConfig) -> Result<(), Error<E>> {
+        self.set_accelerometer_output(config.accelerometer_output)
+            .await?;
+        self.set_accelerometer_scale(config.accelerometer_scale)
+            .await?;
+        self.set_accelerometer_filter(config.accelerometer_filter)
+            .await?;
+        self.set_gyroscope_output(config.gyroscope_output).await?;
+        self.set_gyroscope_scale(config.gyroscope_scale).await?;
+        self.set_gyroscope_filter(config.gyroscope_filter).await?;
+        self.set_block_data_update(config.block_data_update).await?;
+        self.set_low_power_mode(config.low_power).await?;
+        self.write_register_option(Register::Ctrl5C, config.rounding)
+            .await?;
+        self.set_timestamp(config.timestamp).await
+    }
+
     /// Set the accelerometer output rate
     pub async fn set_accelerometer_output(
         &mut self,
         output: AccelerometerOutput,
     ) -> Result<(), Error<E>> {
-        self.write_register_option(Register::Ctrl1XL, output).await
+        let result = self.write_register_option(Register::Ctrl1XL, output).await;
+        self.accelerometer_output = result.is_ok().then_some(output);
+        result
     }
 
     /// Set the accelerometer operating range
@@ -69,35 +544,81 @@ where
         &mut self,
         scale: AccelerometerScale,
     ) -> Result<(), Error<E>> {
-        match self.write_register_option(Register::Ctrl1XL, scale).await {
-            Ok(()) => {
-                self.accelerometer_scale = Some(scale);
-                Ok(())
-            }
-            Err(e) => {
-                self.accelerometer_scale = None;
-                Err(e)
-            }
-        }
+        let result = self.write_register_option(Register::Ctrl1XL, scale).await;
+        self.accelerometer_scale = result.is_ok().then_some(scale);
+        result
     }
 
     /// Set the gyroscope output rate
     pub async fn set_gyroscope_output(&mut self, output: GyroscopeOutput) -> Result<(), Error<E>> {
-        self.write_register_option(Register::Ctrl2G, output).await
+        let result = self.write_register_option(Register::Ctrl2G, output).await;
+        self.gyroscope_output = result.is_ok().then_some(output);
+        result
     }
 
     /// Set the gyroscope operating range
     pub async fn set_gyroscope_scale(&mut self, scale: GyroscopeFullScale) -> Result<(), Error<E>> {
-        match self.write_register_option(Register::Ctrl2G, scale).await {
-            Ok(()) => {
-                self.gyroscope_scale = Some(scale);
-                Ok(())
-            }
-            Err(e) => {
-                self.accelerometer_scale = None;
-                Err(e)
-            }
+        let result = self.write_register_option(Register::Ctrl2G, scale).await;
+        self.gyroscope_scale = result.is_ok().then_some(scale);
+        result
+    }
+
+    /// Set the accelerometer filter chain after LPF1
+    pub async fn set_accelerometer_filter(
+        &mut self,
+        filter: AccelerometerFilter,
+    ) -> Result<(), Error<E>> {
+        let (low_pass, high_pass, bandwidth) = match filter {
+            AccelerometerFilter::Lpf1 => (false, false, None),
+            AccelerometerFilter::LowPass(bandwidth) => (true, false, Some(bandwidth)),
+            AccelerometerFilter::HighPass(bandwidth) => (false, true, Some(bandwidth)),
+        };
+        if let Some(bandwidth) = bandwidth {
+            self.write_register_option(Register::Ctrl8Xl, bandwidth)
+                .await?;
         }
+        self.write_bit(
+            Register::Ctrl8Xl,
+            high_pass as u8,
+            Ctrl8Xl::HighPassSlope as u8,
+        )
+        .await?;
+        self.write_bit(Register::Ctrl1XL, low_pass as u8, Ctrl1Xl::Lpf2Enable as u8)
+            .await
+    }
+
+    /// Set the gyroscope LPF1 and high pass filter
+    pub async fn set_gyroscope_filter(&mut self, filter: GyroscopeFilter) -> Result<(), Error<E>> {
+        if let Some(bandwidth) = filter.lpf1 {
+            self.write_register_option(Register::Ctrl6C, bandwidth)
+                .await?;
+        }
+        self.write_bit(
+            Register::Ctrl4C,
+            filter.lpf1.is_some() as u8,
+            Ctrl4C::GyroLpf1Enable as u8,
+        )
+        .await?;
+        if let Some(cutoff) = filter.high_pass {
+            self.write_register_option(Register::Ctrl7G, cutoff).await?;
+        }
+        self.write_bit(
+            Register::Ctrl7G,
+            filter.high_pass.is_some() as u8,
+            Ctrl7G::GyroHighPassFilter as u8,
+        )
+        .await
+    }
+
+    /// Hold the output registers until both bytes of the last sample are read, so a read never
+    /// mixes two samples
+    pub async fn set_block_data_update(&mut self, enabled: bool) -> Result<(), Error<E>> {
+        self.write_bit(
+            Register::Ctrl3C,
+            enabled as u8,
+            Ctrl3C::BlockDataUpdate as u8,
+        )
+        .await
     }
 
     /// Set the low power mode
@@ -119,8 +640,8 @@ where
 
     /// Read all three sensors in one transaction. Returns temperature, gyro, accelerometer.
     pub async fn read_all(&mut self) -> Result<(f32, (f32, f32, f32), (f32, f32, f32)), Error<E>> {
-        let gyro_scale = self.read_gyroscope_scale().await?;
-        let accel_scale = self.read_accelerometer_scale().await?;
+        let state = self.sensor_state().await?;
+        let (gyro_scale, accel_scale) = (state.gyroscope_scale, state.accelerometer_scale);
         let data = self.read_registers::<14>(Register::OutTempL).await?;
         let (temp, gyro, accel) = array_refs!(&data, 2, 6, 6);
         Ok((
@@ -188,6 +709,614 @@ where
         (temperature / 16.0) + 25.0
     }
 
+    /// Configure what is batched into the FIFO, then start it in `config.mode`.
+    /// The FIFO is emptied first so no words batched with the old settings are left.
+    pub async fn configure_fifo(&mut self, config: &FifoConfig) -> Result<(), Error<E>> {
+        self.set_fifo_mode(FifoMode::Bypass).await?;
+        self.set_fifo_watermark(config.watermark).await?;
+        self.write_bit(
+            Register::FifoCtrl2,
+            config.stop_on_watermark as u8,
+            FifoCtrl2::StopOnWatermark as u8,
+        )
+        .await?;
+        self.write_register_option(Register::FifoCtrl3, config.accelerometer_rate)
+            .await?;
+        self.write_register_option(Register::FifoCtrl3, config.gyroscope_rate)
+            .await?;
+        self.write_register_option(Register::FifoCtrl4, config.temperature_rate)
+            .await?;
+        self.write_register_option(Register::FifoCtrl4, config.timestamp_decimation)
+            .await?;
+        // batched timestamps need the counter running, but it may be read without them
+        if !matches!(config.timestamp_decimation, TimestampDecimation::NotBatched) {
+            self.set_timestamp(true).await?;
+        }
+        self.set_fifo_mode(config.mode).await
+    }
+
+    /// Set the FIFO mode, `Bypass` empties it
+    pub async fn set_fifo_mode(&mut self, mode: FifoMode) -> Result<(), Error<E>> {
+        self.write_register_option(Register::FifoCtrl4, mode).await
+    }
+
+    /// Set the unread words that set the watermark flag, clamped to 511
+    pub async fn set_fifo_watermark(&mut self, watermark: u16) -> Result<(), Error<E>> {
+        let watermark = watermark.min(FIFO_MAX_WATERMARK);
+        self.write_register(Register::FifoCtrl1, watermark as u8)
+            .await?;
+        self.write_bit(
+            Register::FifoCtrl2,
+            (watermark >> 8) as u8,
+            FifoCtrl2::Watermark8 as u8,
+        )
+        .await
+    }
+
+    /// Read the fill level and flags of the FIFO, this clears the latched overrun flag
+    pub async fn fifo_status(&mut self) -> Result<FifoStatus, Error<E>> {
+        let [status1, status2] = self.read_registers::<2>(Register::FifoStatus1).await?;
+        let flag = |bit: FifoStatus2| status2 & (1 << bit as u8) != 0;
+        Ok(FifoStatus {
+            unread: ((status2 as u16 & 0b11) << 8) | status1 as u16,
+            watermark: flag(FifoStatus2::Watermark),
+            overrun: flag(FifoStatus2::Overrun) || flag(FifoStatus2::OverrunLatched),
+            full: flag(FifoStatus2::Full),
+        })
+    }
+
+    /// Read the oldest word in the FIFO
+    pub async fn read_fifo(&mut self) -> Result<FifoRecord, Error<E>> {
+        let gyro_scale = self.read_gyroscope_scale().await?;
+        let accel_scale = self.read_accelerometer_scale().await?;
+        let word = self.read_registers::<7>(Register::FifoDataOutTag).await?;
+        Ok(Self::convert_fifo_word(&word, gyro_scale, accel_scale))
+    }
+
+    /// Read the words waiting in the FIFO, oldest first, until `records` is full.
+    /// Returns how many were read, anything left over stays in the FIFO for the next call.
+    pub async fn drain_fifo(&mut self, records: &mut [FifoRecord]) -> Result<usize, Error<E>> {
+        let unread = self.fifo_status().await?.unread as usize;
+        let count = unread.min(records.len());
+        for record in records[..count].iter_mut() {
+            *record = self.read_fifo().await?;
+        }
+        Ok(count)
+    }
+
+    fn convert_fifo_word(
+        word: &[u8; 7],
+        gyro_scale: GyroscopeFullScale,
+        accel_scale: AccelerometerScale,
+    ) -> FifoRecord {
+        let (tag, data) = array_refs!(word, 1, 6);
+        let counter = (tag[0] >> 1) & 0b11;
+        let data = match tag[0] >> 3 {
+            t if t == FifoTag::GyroscopeNc as u8 => {
+                FifoData::Gyroscope(Self::convert_gyro_data(data, gyro_scale))
+            }
+            t if t == FifoTag::AccelerometerNc as u8 => {
+                FifoData::Accelerometer(Self::convert_accel_data(data, accel_scale))
+            }
+            t if t == FifoTag::Temperature as u8 => {
+                FifoData::Temperature(Self::convert_temp_data(&[data[0], data[1]]))
+            }
+            t if t == FifoTag::Timestamp as u8 => {
+                FifoData::Timestamp(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            t => FifoData::Other(t),
+        };
+        FifoRecord { counter, data }
+    }
+
+    /// Route events to an interrupt pin, replacing the events routed to it before. Tap, wake-up,
+    /// free-fall and 6D events also need [`Self::set_event_detection`].
+    pub async fn route_interrupts(
+        &mut self,
+        pin: InterruptPin,
+        route: InterruptRoute,
+    ) -> Result<(), Error<E>> {
+        let (ctrl, md) = match pin {
+            InterruptPin::Int1 => (Register::Int1Ctrl, Register::Md1Cfg),
+            InterruptPin::Int2 => (Register::Int2Ctrl, Register::Md2Cfg),
+        };
+        let int = |set: bool, field: IntCtrl| (set as u8) << field as u8;
+        let md_cfg = |set: bool, field: MdCfg| (set as u8) << field as u8;
+
+        let ctrl_bits = int(route.accelerometer_ready, IntCtrl::DataReadyAccelerometer)
+            | int(route.gyroscope_ready, IntCtrl::DataReadyGyroscope)
+            | int(route.fifo_watermark, IntCtrl::FifoThreshold)
+            | int(route.fifo_overrun, IntCtrl::FifoOverrun)
+            | int(route.fifo_full, IntCtrl::FifoFull);
+        self.write_bits(ctrl, ctrl_bits, INT_CTRL_ROUTED, 0).await?;
+
+        let md_bits = md_cfg(route.wake_up, MdCfg::WakeUp)
+            | md_cfg(route.free_fall, MdCfg::FreeFall)
+            | md_cfg(route.orientation, MdCfg::Orientation6d)
+            | md_cfg(route.single_tap, MdCfg::SingleTap)
+            | md_cfg(route.double_tap, MdCfg::DoubleTap)
+            | md_cfg(route.significant_motion, MdCfg::EmbeddedFunction);
+        self.write_bits(md, md_bits, MD_CFG_ROUTED, 0).await?;
+
+        let emb_func_int = match pin {
+            InterruptPin::Int1 => EmbeddedRegister::EmbFuncInt1,
+            InterruptPin::Int2 => EmbeddedRegister::EmbFuncInt2,
+        };
+        self.write_embedded_bit(
+            emb_func_int,
+            route.significant_motion as u8,
+            SIGNIFICANT_MOTION_BIT,
+        )
+        .await
+    }
+
+    /// Run the tap, wake-up, free-fall and 6D detectors, whether or not they are routed to a pin
+    pub async fn set_event_detection(&mut self, enabled: bool) -> Result<(), Error<E>> {
+        self.write_bit(
+            Register::TapCfg2,
+            enabled as u8,
+            TapCfg2::InterruptsEnable as u8,
+        )
+        .await
+    }
+
+    /// Set the interrupt pins active low instead of high, and open drain instead of push-pull
+    pub async fn set_interrupt_output(
+        &mut self,
+        active_low: bool,
+        open_drain: bool,
+    ) -> Result<(), Error<E>> {
+        self.write_bit(
+            Register::Ctrl3C,
+            active_low as u8,
+            Ctrl3C::InterruptActivationLevel as u8,
+        )
+        .await?;
+        self.write_bit(
+            Register::Ctrl3C,
+            open_drain as u8,
+            Ctrl3C::InterruptPadOutput as u8,
+        )
+        .await
+    }
+
+    /// Latch the event interrupts until [`Self::read_interrupt_sources`] instead of only holding
+    /// them while the event lasts
+    pub async fn set_latched_interrupts(&mut self, latched: bool) -> Result<(), Error<E>> {
+        self.write_bit(
+            Register::TapCfg0,
+            latched as u8,
+            TapCfg0::LatchedInterrupt as u8,
+        )
+        .await
+    }
+
+    /// Pulse the data ready interrupts for 75 us instead of holding them until the data is read
+    pub async fn set_data_ready_pulsed(&mut self, pulsed: bool) -> Result<(), Error<E>> {
+        self.write_bit(
+            Register::CounterBdrReg1,
+            pulsed as u8,
+            CounterBdrReg1::DataReadyPulsed as u8,
+        )
+        .await
+    }
+
+    /// Read which events fired, clearing latched interrupts
+    pub async fn read_interrupt_sources(&mut self) -> Result<InterruptSources, Error<E>> {
+        let src = self.read_register(Register::AllIntSrc).await?;
+        let flag = |bit: AllIntSrc| src & (1 << bit as u8) != 0;
+        Ok(InterruptSources {
+            wake_up: flag(AllIntSrc::WakeUp),
+            free_fall: flag(AllIntSrc::FreeFall),
+            orientation: flag(AllIntSrc::Orientation6d),
+            single_tap: flag(AllIntSrc::SingleTap),
+            double_tap: flag(AllIntSrc::DoubleTap),
+            sleep_change: flag(AllIntSrc::SleepChange),
+        })
+    }
+
+    /// Configure tap detection, thresholds and times over their range are clamped
+    pub async fn configure_tap(&mut self, config: &TapConfig) -> Result<(), Error<E>> {
+        self.write_bits(
+            Register::TapCfg0,
+            config.axes.bits(),
+            0b111,
+            TapCfg0::TapZEnable as u8,
+        )
+        .await?;
+        // each axis has its own threshold, spread over three registers
+        let threshold = config.threshold.min(TAP_MAX_THRESHOLD);
+        for register in [Register::TapCfg1, Register::TapCfg2, Register::TapThs6d] {
+            self.write_bits(register, threshold, TAP_MAX_THRESHOLD, 0)
+                .await?;
+        }
+        let int_dur2 =
+            (config.duration.min(15) << 4) | (config.quiet.min(3) << 2) | config.shock.min(3);
+        self.write_register(Register::IntDur2, int_dur2).await?;
+        self.write_bit(
+            Register::WakeUpThs,
+            config.double_tap as u8,
+            WakeUpThs::SingleDoubleTap as u8,
+        )
+        .await
+    }
+
+    /// Configure wake-up detection, thresholds and times over their range are clamped
+    pub async fn configure_wake_up(&mut self, config: &WakeUpConfig) -> Result<(), Error<E>> {
+        self.write_bits(
+            Register::WakeUpThs,
+            config.threshold.min(WAKE_UP_MAX_THRESHOLD),
+            WAKE_UP_MAX_THRESHOLD,
+            0,
+        )
+        .await?;
+        // threshold in 1/64 rather than 1/256 of the full scale
+        self.write_bit(
+            Register::WakeUpDur,
+            0,
+            WakeUpDur::WakeUpThresholdWeight as u8,
+        )
+        .await?;
+        self.write_bits(Register::WakeUpDur, config.duration.min(3), 0b11, 5)
+            .await
+    }
+
+    /// Configure free-fall detection, a duration over its range is clamped
+    pub async fn configure_free_fall(&mut self, config: &FreeFallConfig) -> Result<(), Error<E>> {
+        let duration = config.duration.min(FREE_FALL_MAX_DURATION);
+        self.write_register(
+            Register::FreeFall,
+            ((duration & 0x1F) << 3) | config.threshold.value(),
+        )
+        .await?;
+        self.write_bit(
+            Register::WakeUpDur,
+            duration >> 5,
+            WakeUpDur::FreeFallDuration5 as u8,
+        )
+        .await
+    }
+
+    /// Set the tilt that counts as a 6D orientation change
+    pub async fn set_orientation_threshold(
+        &mut self,
+        threshold: OrientationThreshold,
+    ) -> Result<(), Error<E>> {
+        self.write_register_option(Register::TapThs6d, threshold)
+            .await
+    }
+
+    /// Enable significant motion detection, which needs the accelerometer at 26 Hz or faster
+    pub async fn set_significant_motion(&mut self, enabled: bool) -> Result<(), Error<E>> {
+        self.write_embedded_bit(
+            EmbeddedRegister::EmbFuncEnA,
+            enabled as u8,
+            SIGNIFICANT_MOTION_BIT,
+        )
+        .await
+    }
+
+    /// Read the status of every event detector, clearing latched interrupts
+    pub async fn read_event_sources(&mut self) -> Result<EventSources, Error<E>> {
+        let [wake_up, tap, d6d] = self.read_registers::<3>(Register::WakeUpSrc).await?;
+        let emb_func = self.read_register(Register::EmbFuncStatusMainpage).await?;
+        let bit = |reg: u8, shift: u8| reg & (1 << shift) != 0;
+        Ok(EventSources {
+            wake_up: WakeUpSource {
+                wake_up: bit(wake_up, WakeUpSrc::WakeUp as u8),
+                free_fall: bit(wake_up, WakeUpSrc::FreeFall as u8),
+                sleep_change: bit(wake_up, WakeUpSrc::SleepChange as u8),
+                sleeping: bit(wake_up, WakeUpSrc::SleepState as u8),
+                axes: Axes::from_bits(wake_up),
+            },
+            tap: TapSource {
+                single: bit(tap, TapSrc::SingleTap as u8),
+                double: bit(tap, TapSrc::DoubleTap as u8),
+                axes: Axes::from_bits(tap),
+                negative: bit(tap, TapSrc::Negative as u8),
+            },
+            orientation: OrientationSource {
+                changed: bit(d6d, D6dSrc::Orientation6d as u8),
+                high: Axes {
+                    x: bit(d6d, D6dSrc::XHigh as u8),
+                    y: bit(d6d, D6dSrc::YHigh as u8),
+                    z: bit(d6d, D6dSrc::ZHigh as u8),
+                },
+                low: Axes {
+                    x: bit(d6d, D6dSrc::XLow as u8),
+                    y: bit(d6d, D6dSrc::YLow as u8),
+                    z: bit(d6d, D6dSrc::ZLow as u8),
+                },
+            },
+            significant_motion: bit(emb_func, SIGNIFICANT_MOTION_BIT),
+        })
+    }
+
+    /// Start or stop the timestamp counter
+    pub async fn set_timestamp(&mut self, enabled: bool) -> Result<(), Error<E>> {
+        self.write_bit(
+            Register::Ctrl10C,
+            enabled as u8,
+            Ctrl10C::TimestampEnable as u8,
+        )
+        .await
+    }
+
+    /// Restart the timestamp counter from zero
+    pub async fn reset_timestamp(&mut self) -> Result<(), Error<E>> {
+        self.write_register(Register::Timestamp2, TIMESTAMP_RESET)
+            .await
+    }
+
+    /// Length of a timestamp tick in ns on this part, [`TIMESTAMP_TICK_NS`] trimmed by the
+    /// INTERNAL_FREQ_FINE factory setting, which is off by up to a few percent otherwise
+    pub async fn read_timestamp_tick_ns(&mut self) -> Result<f32, Error<E>> {
+        let fine = self.read_register(Register::InternalFreqFine).await? as i8;
+        Ok(TIMESTAMP_TICK_NS as f32 / (1.0 + 0.0015 * fine as f32))
+    }
+
+    /// Read the timestamp counter, in ticks of [`TIMESTAMP_TICK_NS`]
+    pub async fn read_timestamp(&mut self) -> Result<u32, Error<E>> {
+        self.read_registers::<4>(Register::Timestamp0)
+            .await
+            .map(u32::from_le_bytes)
+    }
+
+    /// Set the offset the sensor subtracts from every accelerometer output, in m/s^2, and turn
+    /// it on. Offsets up to 124 mg are set in steps of about 1 mg, larger ones in steps of 16 mg
+    /// up to 1.98 g, anything beyond that is clamped.
+    pub async fn set_accelerometer_offset(
+        &mut self,
+        offset: (f32, f32, f32),
+    ) -> Result<(), Error<E>> {
+        let to_mg = |value: f32| value / EARTH_GRAVITY * 1000.0;
+        let offset_mg = (to_mg(offset.0), to_mg(offset.1), to_mg(offset.2));
+        let largest = abs(offset_mg.0).max(abs(offset_mg.1)).max(abs(offset_mg.2));
+        let coarse = largest > 127.0 * ACCEL_OFFSET_FINE_MG;
+        let weight = if coarse {
+            ACCEL_OFFSET_COARSE_MG
+        } else {
+            ACCEL_OFFSET_FINE_MG
+        };
+        // rounded to the nearest step, the cast truncates towards zero
+        let to_bits = |mg: f32| {
+            let steps = (mg / weight).clamp(-127.0, 127.0);
+            (if steps < 0.0 {
+                steps - 0.5
+            } else {
+                steps + 0.5
+            }) as i8 as u8
+        };
+
+        self.write_bit(
+            Register::Ctrl6C,
+            coarse as u8,
+            Ctrl6C::AccelOffsetWeight as u8,
+        )
+        .await?;
+        self.i2c
+            .write(
+                self.addr,
+                &[
+                    Register::XOfsUsr.into(),
+                    to_bits(offset_mg.0),
+                    to_bits(offset_mg.1),
+                    to_bits(offset_mg.2),
+                ],
+            )
+            .await
+            .map_err(Error::CommunicationError)?;
+        self.write_bit(Register::Ctrl7G, 1, Ctrl7G::EnableAccelOffset as u8)
+            .await
+    }
+
+    /// Stop correcting the accelerometer output and zero the offsets
+    pub async fn clear_accelerometer_offset(&mut self) -> Result<(), Error<E>> {
+        self.write_bit(Register::Ctrl7G, 0, Ctrl7G::EnableAccelOffset as u8)
+            .await?;
+        self.i2c
+            .write(self.addr, &[Register::XOfsUsr.into(), 0, 0, 0])
+            .await
+            .map_err(Error::CommunicationError)
+    }
+
+    /// Measure the accelerometer and gyro biases by averaging `samples` readings with the sensor
+    /// still, then correct the accelerometer with the offset registers. `gravity` is what the
+    /// accelerometer should read while still, in m/s^2, which depends on how it is mounted.
+    /// Both sensors must already be running, a sample is waited for with `delay`.
+    pub async fn calibrate_bias<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        samples: u16,
+        gravity: (f32, f32, f32),
+    ) -> Result<ImuBias, Error<E>> {
+        self.clear_accelerometer_offset().await?;
+
+        let mut accel_sum = (0.0, 0.0, 0.0);
+        let mut gyro_sum = (0.0, 0.0, 0.0);
+        for _ in 0..samples {
+            self.wait_data_ready(delay, 0b11).await?;
+            let (_, gyro, accel) = self.read_all().await?;
+            accel_sum = (
+                accel_sum.0 + accel.0,
+                accel_sum.1 + accel.1,
+                accel_sum.2 + accel.2,
+            );
+            gyro_sum = (
+                gyro_sum.0 + gyro.0,
+                gyro_sum.1 + gyro.1,
+                gyro_sum.2 + gyro.2,
+            );
+        }
+
+        let count = samples.max(1) as f32;
+        let bias = ImuBias {
+            accelerometer: (
+                accel_sum.0 / count - gravity.0,
+                accel_sum.1 / count - gravity.1,
+                accel_sum.2 / count - gravity.2,
+            ),
+            gyroscope: (gyro_sum.0 / count, gyro_sum.1 / count, gyro_sum.2 / count),
+        };
+        self.set_accelerometer_offset(bias.accelerometer).await?;
+        Ok(bias)
+    }
+
+    /// Run the datasheet self-test of both sensors in both directions, which takes about a second.
+    /// The sensor must be still, and the FIFO should not be running as the test samples would be
+    /// batched. The control registers are put back afterwards.
+    pub async fn self_test<D: DelayNs>(&mut self, delay: &mut D) -> Result<SelfTest, Error<E>> {
+        let saved = self.read_registers::<10>(Register::Ctrl1XL).await?;
+        let result = async {
+            Ok(SelfTest {
+                accelerometer_positive: self
+                    .self_test_accelerometer(delay, SelfTestSign::Positive)
+                    .await?,
+                accelerometer_negative: self
+                    .self_test_accelerometer(delay, SelfTestSign::Negative)
+                    .await?,
+                gyroscope_positive: self
+                    .self_test_gyroscope(delay, SelfTestSign::Positive)
+                    .await?,
+                gyroscope_negative: self
+                    .self_test_gyroscope(delay, SelfTestSign::Negative)
+                    .await?,
+            })
+        }
+        .await;
+        // put the control registers back even if a step failed
+        let restored = self.write_registers(Register::Ctrl1XL, &saved).await;
+        self.forget_state();
+        let result = result?;
+        restored?;
+        Ok(result)
+    }
+
+    /// Run one accelerometer self-test, leaving both sensors off and the control registers in
+    /// the test setup
+    pub async fn self_test_accelerometer<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        sign: SelfTestSign,
+    ) -> Result<SelfTestAxes, Error<E>> {
+        // 52 Hz at +-4 g with block data update, everything else off
+        let setup = [0x38, 0x00, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let test = match sign {
+            SelfTestSign::Positive => AccelerometerSelfTest::Positive,
+            SelfTestSign::Negative => AccelerometerSelfTest::Negative,
+        };
+        let change = self
+            .run_self_test(
+                delay,
+                &setup,
+                Register::OutXLA,
+                0b01,
+                test,
+                AccelerometerSelfTest::Normal,
+            )
+            .await?;
+        let scale = AccelerometerScale::G04.scale();
+        Ok(Self::self_test_axes(change, scale, ACCEL_SELF_TEST_MG))
+    }
+
+    /// Run one gyroscope self-test, leaving both sensors off and the control registers in the
+    /// test setup
+    pub async fn self_test_gyroscope<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        sign: SelfTestSign,
+    ) -> Result<SelfTestAxes, Error<E>> {
+        // 208 Hz at 2000 dps with block data update, everything else off
+        let setup = [0x00, 0x5C, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let test = match sign {
+            SelfTestSign::Positive => GyroscopeSelfTest::Positive,
+            SelfTestSign::Negative => GyroscopeSelfTest::Negative,
+        };
+        let change = self
+            .run_self_test(
+                delay,
+                &setup,
+                Register::OutXLG,
+                0b10,
+                test,
+                GyroscopeSelfTest::Normal,
+            )
+            .await?;
+        // mdps per bit
+        let scale = GyroscopeFullScale::Dps2000.scale() / 1000.0;
+        Ok(Self::self_test_axes(change, scale, GYRO_SELF_TEST_DPS))
+    }
+
+    // The datasheet procedure: set up, average the output, turn the self-test on, average again
+    // and turn everything off. Returns the raw change of each axis.
+    async fn run_self_test<D: DelayNs, RO: RegisterOption>(
+        &mut self,
+        delay: &mut D,
+        setup: &[u8; 10],
+        output: Register,
+        ready: u8,
+        test: RO,
+        normal: RO,
+    ) -> Result<(f32, f32, f32), Error<E>> {
+        self.forget_state();
+        self.write_registers(Register::Ctrl1XL, setup).await?;
+        delay.delay_ms(SELF_TEST_SETTLE_MS).await;
+        let before = self.average_output(delay, output, ready).await?;
+
+        self.write_register_option(Register::Ctrl5C, test).await?;
+        delay.delay_ms(SELF_TEST_SETTLE_MS).await;
+        let after = self.average_output(delay, output, ready).await?;
+
+        self.write_register_option(Register::Ctrl5C, normal).await?;
+        self.write_registers(Register::Ctrl1XL, &[0x00, 0x00])
+            .await?;
+        Ok((after.0 - before.0, after.1 - before.1, after.2 - before.2))
+    }
+
+    // Average of the raw output over the self-test samples, after discarding the first
+    async fn average_output<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        output: Register,
+        ready: u8,
+    ) -> Result<(f32, f32, f32), Error<E>> {
+        self.wait_data_ready(delay, ready).await?;
+        self.read_registers::<6>(output).await?;
+
+        let mut sum = (0.0, 0.0, 0.0);
+        for _ in 0..SELF_TEST_SAMPLES {
+            self.wait_data_ready(delay, ready).await?;
+            let (x, y, z) = Self::u8_to_f32(&self.read_registers(output).await?);
+            sum = (sum.0 + x, sum.1 + y, sum.2 + z);
+        }
+        let count = SELF_TEST_SAMPLES as f32;
+        Ok((sum.0 / count, sum.1 / count, sum.2 / count))
+    }
+
+    fn self_test_axes(change: (f32, f32, f32), scale: f32, limits: (f32, f32)) -> SelfTestAxes {
+        let change = (change.0 * scale, change.1 * scale, change.2 * scale);
+        let within = |value: f32| (limits.0..=limits.1).contains(&abs(value));
+        SelfTestAxes {
+            change,
+            passed: within(change.0) && within(change.1) && within(change.2),
+        }
+    }
+
+    // Poll the status register until all the `ready` bits are set
+    async fn wait_data_ready<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+        ready: u8,
+    ) -> Result<(), Error<E>> {
+        for _ in 0..DATA_READY_MAX_POLLS {
+            if self.read_status().await? & ready == ready {
+                return Ok(());
+            }
+            delay.delay_ms(1).await;
+        }
+        Err(Error::DataNotReady)
+    }
+
     /// Check if there is new accelerometer data
     pub async fn accel_data_available(&mut self) -> Result<bool, Error<E>> {
         self.read_status().await.map(|status| status & 0b1 != 0)
@@ -198,28 +1327,60 @@ where
         self.read_status().await.map(|status| status & 0b10 != 0)
     }
 
+    /// The output rates and full scales of both sensors, read back from CTRL1_XL and CTRL2_G in
+    /// one go unless all are already known
+    pub async fn sensor_state(&mut self) -> Result<SensorState, Error<E>> {
+        if let (
+            Some(accelerometer_output),
+            Some(accelerometer_scale),
+            Some(gyroscope_output),
+            Some(gyroscope_scale),
+        ) = (
+            self.accelerometer_output,
+            self.accelerometer_scale,
+            self.gyroscope_output,
+            self.gyroscope_scale,
+        ) {
+            return Ok(SensorState {
+                accelerometer_output,
+                accelerometer_scale,
+                gyroscope_output,
+                gyroscope_scale,
+            });
+        }
+
+        let [ctrl1_xl, ctrl2_g] = self.read_registers::<2>(Register::Ctrl1XL).await?;
+        let state = SensorState {
+            accelerometer_output: Self::convert_option(ctrl1_xl)?,
+            accelerometer_scale: Self::convert_option(ctrl1_xl)?,
+            gyroscope_output: Self::convert_option(ctrl2_g)?,
+            gyroscope_scale: Self::convert_option(ctrl2_g)?,
+        };
+        self.accelerometer_output = Some(state.accelerometer_output);
+        self.accelerometer_scale = Some(state.accelerometer_scale);
+        self.gyroscope_output = Some(state.gyroscope_output);
+        self.gyroscope_scale = Some(state.gyroscope_scale);
+        Ok(state)
+    }
+
     /// Read the accelerometer scale value from the configuration register
     pub async fn read_accelerometer_scale(&mut self) -> Result<AccelerometerScale, Error<E>> {
-        match self.accelerometer_scale {
-            Some(v) => Ok(v),
-            None => {
-                let scale = self.read_register_option(Register::Ctrl1XL).await?;
-                self.accelerometer_scale = Some(scale);
-                Ok(scale)
-            }
-        }
+        self.sensor_state()
+            .await
+            .map(|state| state.accelerometer_scale)
     }
 
     /// Read the gyroscope scale value from the configuration register
     pub async fn read_gyroscope_scale(&mut self) -> Result<GyroscopeFullScale, Error<E>> {
-        match self.gyroscope_scale {
-            Some(v) => Ok(v),
-            None => {
-                let scale = self.read_register_option(Register::Ctrl2G).await?;
-                self.gyroscope_scale = Some(scale);
-                Ok(scale)
-            }
-        }
+        self.sensor_state().await.map(|state| state.gyroscope_scale)
+    }
+
+    // Forget what the sensors are set to, for after the control registers were written directly
+    fn forget_state(&mut self) {
+        self.accelerometer_output = None;
+        self.accelerometer_scale = None;
+        self.gyroscope_output = None;
+        self.gyroscope_scale = None;
     }
 
     async fn check(&mut self) -> Result<bool, Error<E>> {
@@ -246,11 +1407,7 @@ where
             .await
     }
 
-    async fn read_register_option<RO: RegisterOption + TryFrom<u8>>(
-        &mut self,
-        register: Register,
-    ) -> Result<RO, Error<E>> {
-        let value = self.read_register(register).await?;
+    fn convert_option<RO: RegisterOption + TryFrom<u8>>(value: u8) -> Result<RO, Error<E>> {
         RO::try_from(value).map_err(|_| Error::RegisterReadFailed)
     }
 
@@ -275,6 +1432,33 @@ where
         self.write_register(register, modified_value).await
     }
 
+    // Read-modify-write a bit in the embedded functions page, then switch back to the main page
+    async fn write_embedded_bit(
+        &mut self,
+        register: EmbeddedRegister,
+        value: u8,
+        shift: u8,
+    ) -> Result<(), Error<E>> {
+        let access = FuncCfgAccess::EmbeddedFunctions as u8;
+        self.write_bit(Register::FuncCfgAccess, 1, access).await?;
+        let result = async {
+            let mut current = [0u8];
+            self.i2c
+                .write_read(self.addr, &[register.into()], &mut current)
+                .await
+                .map_err(Error::CommunicationError)?;
+            let modified = (current[0] & !(1 << shift)) | ((value & 0x01) << shift);
+            self.i2c
+                .write(self.addr, &[register.into(), modified])
+                .await
+                .map_err(Error::CommunicationError)
+        }
+        .await;
+        // leave the embedded page even if the access failed, or every later access goes astray
+        self.write_bit(Register::FuncCfgAccess, 0, access).await?;
+        result
+    }
+
     fn u8_to_f32(res: &[u8; 6]) -> (f32, f32, f32) {
         let (x, y, z) = (
             (res[0] as i16) | ((res[1] as i16) << 8),
@@ -306,6 +1490,21 @@ where
         Ok(res)
     }
 
+    // Write consecutive registers from `start_reg`, at most 15
+    async fn write_registers(
+        &mut self,
+        start_reg: Register,
+        values: &[u8],
+    ) -> Result<(), Error<E>> {
+        let mut buf = [0u8; 16];
+        buf[0] = start_reg.into();
+        buf[1..=values.len()].copy_from_slice(values);
+        self.i2c
+            .write(self.addr, &buf[..=values.len()])
+            .await
+            .map_err(Error::CommunicationError)
+    }
+
     // Write the specified value to the given register
     async fn write_register(&mut self, register: Register, value: u8) -> Result<(), Error<E>> {
         self.i2c
@@ -126,7 +126,7 @@ pub trait RegisterOption {
 // --- CTRL1_XL ------------------------------------------------------------------------------------
 // -------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccelerometerOutput {
     PowerDown = 0b0000,
     /// Low power only. Is 12.5 Hz in high performance mode.
@@ -155,8 +155,31 @@ impl RegisterOption for AccelerometerOutput {
     }
 }
 
+impl TryFrom<u8> for AccelerometerOutput {
+    type Error = RegisterError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let value = (value >> Self::bit_offset()) & Self::mask();
+        match value {
+            0b0000 => Ok(Self::PowerDown),
+            0b1011 => Ok(Self::Rate1_6),
+            0b0001 => Ok(Self::Rate12_5),
+            0b0010 => Ok(Self::Rate26),
+            0b0011 => Ok(Self::Rate52),
+            0b0100 => Ok(Self::Rate104),
+            0b0101 => Ok(Self::Rate208),
+            0b0110 => Ok(Self::Rate416),
+            0b0111 => Ok(Self::Rate833),
+            0b1000 => Ok(Self::Rate1_66k),
+            0b1001 => Ok(Self::Rate3_33k),
+            0b1010 => Ok(Self::Rate6_66k),
+            _ => Err(RegisterError::ConversionError),
+        }
+    }
+}
+
 /// Accelerometer full-scale selection
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AccelerometerScale {
     G02 = 0b00,
     /// Old full-scale mode only. Is 2g with new full-scale mode.
@@ -207,7 +230,7 @@ impl TryFrom<u8> for AccelerometerScale {
 // --- CTRL2_G -------------------------------------------------------------------------------------
 // -------------------------------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GyroscopeOutput {
     PowerDown = 0b0000,
     Rate12_5 = 0b0001,
@@ -234,7 +257,29 @@ impl RegisterOption for GyroscopeOutput {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl TryFrom<u8> for GyroscopeOutput {
+    type Error = RegisterError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        let value = (value >> Self::bit_offset()) & Self::mask();
+        match value {
+            0b0000 => Ok(Self::PowerDown),
+            0b0001 => Ok(Self::Rate12_5),
+            0b0010 => Ok(Self::Rate26),
+            0b0011 => Ok(Self::Rate52),
+            0b0100 => Ok(Self::Rate104),
+            0b0101 => Ok(Self::Rate208),
+            0b0110 => Ok(Self::Rate416),
+            0b0111 => Ok(Self::Rate833),
+            0b1000 => Ok(Self::Rate1_66k),
+            0b1001 => Ok(Self::Rate3_33k),
+            0b1010 => Ok(Self::Rate6_66k),
+            _ => Err(RegisterError::ConversionError),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GyroscopeFullScale {
     Dps125 = 0b001,
     Dps250 = 0b000,
@@ -283,6 +328,12 @@ impl TryFrom<u8> for GyroscopeFullScale {
     }
 }
 
+#[allow(unused)]
+/// Bit fields for CTRL1_XL
+pub enum Ctrl1Xl {
+    Lpf2Enable = 1,
+}
+
 #[allow(unused)]
 /// Bit fields for CTRL3_C
 pub enum Ctrl3C {
@@ -295,6 +346,21 @@ pub enum Ctrl3C {
     SoftwareReset = 0,
 }
 #[allow(unused)]
+/// Bit fields for CTRL4_C
+pub enum Ctrl4C {
+    GyroSleep = 6,
+    Int2OnInt1 = 5,
+    DataReadyMask = 3,
+    I2cDisable = 2,
+    GyroLpf1Enable = 1,
+}
+#[allow(unused)]
+/// Bit fields for CTRL5_C
+pub enum Ctrl5C {
+    AccelUltraLowPower = 7,
+    RoundingStatus = 4,
+}
+#[allow(unused)]
 /// Bit fields for CTRL6_C
 pub enum Ctrl6C {
     GyroEdgeTrigger = 7,
@@ -317,3 +383,523 @@ pub enum Ctrl7G {
     EnableAccelOffset = 1,
     EnableOISChainPrimary = 0,
 }
+#[allow(unused)]
+/// Bit fields for CTRL8_XL
+pub enum Ctrl8Xl {
+    HighPassReferenceMode = 4,
+    FastSettling = 3,
+    HighPassSlope = 2,
+    FullScaleMode = 1,
+    LowPass6d = 0,
+}
+#[allow(unused)]
+/// Bit fields for CTRL9_XL
+pub enum Ctrl9Xl {
+    DenX = 7,
+    DenY = 6,
+    DenZ = 5,
+    DenAccelerometer = 4,
+    DenAccelerometerExtend = 3,
+    DenActiveHigh = 2,
+    DeviceConfiguration = 1,
+    I3cDisable = 0,
+}
+#[allow(unused)]
+/// Bit fields for CTRL10_C
+pub enum Ctrl10C {
+    TimestampEnable = 5,
+}
+
+// -------------------------------------------------------------------------------------------------
+// --- CTRL5_C..CTRL8_XL ---------------------------------------------------------------------------
+// -------------------------------------------------------------------------------------------------
+
+/// Output registers a burst read wraps around in, CTRL5_C
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// No wrap, the address keeps counting up
+    None = 0b00,
+    Accelerometer = 0b01,
+    Gyroscope = 0b10,
+    /// Gyroscope then accelerometer
+    Both = 0b11,
+}
+
+impl RegisterOption for Rounding {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        5
+    }
+}
+
+/// Accelerometer self-test, CTRL5_C
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelerometerSelfTest {
+    Normal = 0b00,
+    Positive = 0b01,
+    Negative = 0b10,
+}
+
+impl RegisterOption for AccelerometerSelfTest {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        0
+    }
+}
+
+/// Gyroscope self-test, CTRL5_C
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroscopeSelfTest {
+    Normal = 0b00,
+    Positive = 0b01,
+    Negative = 0b11,
+}
+
+impl RegisterOption for GyroscopeSelfTest {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        2
+    }
+}
+
+/// Gyroscope LPF1 bandwidth, CTRL6_C FTYPE. The cutoff for each setting depends on the output
+/// rate, see the datasheet table. At 104 Hz and below it is set by the output rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroscopeLpf1Bandwidth {
+    Ftype0 = 0b000,
+    Ftype1 = 0b001,
+    Ftype2 = 0b010,
+    Ftype3 = 0b011,
+    Ftype4 = 0b100,
+    Ftype5 = 0b101,
+    Ftype6 = 0b110,
+    Ftype7 = 0b111,
+}
+
+impl RegisterOption for GyroscopeLpf1Bandwidth {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b111
+    }
+    fn bit_offset() -> u8 {
+        0
+    }
+}
+
+/// Gyroscope high pass cutoff, CTRL7_G
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroscopeHighPassCutoff {
+    Hz0_016 = 0b00,
+    Hz0_065 = 0b01,
+    Hz0_26 = 0b10,
+    Hz1_04 = 0b11,
+}
+
+impl RegisterOption for GyroscopeHighPassCutoff {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        4
+    }
+}
+
+/// Accelerometer LPF2 or high pass cutoff as a fraction of the output rate, CTRL8_XL.
+/// `Odr4` is the slope filter in high pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelerometerFilterBandwidth {
+    Odr4 = 0b000,
+    Odr10 = 0b001,
+    Odr20 = 0b010,
+    Odr45 = 0b011,
+    Odr100 = 0b100,
+    Odr200 = 0b101,
+    Odr400 = 0b110,
+    Odr800 = 0b111,
+}
+
+impl RegisterOption for AccelerometerFilterBandwidth {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b111
+    }
+    fn bit_offset() -> u8 {
+        5
+    }
+}
+
+// -------------------------------------------------------------------------------------------------
+// --- FIFO_CTRL1..4 -------------------------------------------------------------------------------
+// -------------------------------------------------------------------------------------------------
+
+/// FIFO operating mode, FIFO_CTRL4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FifoMode {
+    /// FIFO disabled and emptied
+    Bypass = 0b000,
+    /// Stops collecting once full
+    Fifo = 0b001,
+    /// Continuous until a trigger event, then FIFO mode
+    ContinuousToFifo = 0b011,
+    /// Bypass until a trigger event, then continuous mode
+    BypassToContinuous = 0b100,
+    /// Overwrites the oldest data once full
+    Continuous = 0b110,
+    /// Bypass until a trigger event, then FIFO mode
+    BypassToFifo = 0b111,
+}
+
+impl RegisterOption for FifoMode {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b111
+    }
+    fn bit_offset() -> u8 {
+        0
+    }
+}
+
+/// Rate accelerometer samples are written to the FIFO, FIFO_CTRL3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccelerometerBatchRate {
+    NotBatched = 0b0000,
+    Rate1_6 = 0b1011,
+    Rate12_5 = 0b0001,
+    Rate26 = 0b0010,
+    Rate52 = 0b0011,
+    Rate104 = 0b0100,
+    Rate208 = 0b0101,
+    Rate416 = 0b0110,
+    Rate833 = 0b0111,
+    Rate1_66k = 0b1000,
+    Rate3_33k = 0b1001,
+    Rate6_66k = 0b1010,
+}
+
+impl RegisterOption for AccelerometerBatchRate {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0xF
+    }
+    fn bit_offset() -> u8 {
+        0
+    }
+}
+
+/// Rate gyroscope samples are written to the FIFO, FIFO_CTRL3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GyroscopeBatchRate {
+    NotBatched = 0b0000,
+    Rate6_5 = 0b1011,
+    Rate12_5 = 0b0001,
+    Rate26 = 0b0010,
+    Rate52 = 0b0011,
+    Rate104 = 0b0100,
+    Rate208 = 0b0101,
+    Rate416 = 0b0110,
+    Rate833 = 0b0111,
+    Rate1_66k = 0b1000,
+    Rate3_33k = 0b1001,
+    Rate6_66k = 0b1010,
+}
+
+impl RegisterOption for GyroscopeBatchRate {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0xF
+    }
+    fn bit_offset() -> u8 {
+        4
+    }
+}
+
+/// Rate temperature samples are written to the FIFO, FIFO_CTRL4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemperatureBatchRate {
+    NotBatched = 0b00,
+    Rate1_6 = 0b01,
+    Rate12_5 = 0b10,
+    Rate52 = 0b11,
+}
+
+impl RegisterOption for TemperatureBatchRate {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        4
+    }
+}
+
+/// Batch a timestamp once every this many accelerometer or gyroscope batches, FIFO_CTRL4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampDecimation {
+    NotBatched = 0b00,
+    Every1 = 0b01,
+    Every8 = 0b10,
+    Every32 = 0b11,
+}
+
+impl RegisterOption for TimestampDecimation {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        6
+    }
+}
+
+#[allow(unused)]
+/// Bit fields for FIFO_CTRL2
+pub enum FifoCtrl2 {
+    StopOnWatermark = 7,
+    Watermark8 = 0,
+}
+
+#[allow(unused)]
+/// Bit fields for FIFO_STATUS2
+pub enum FifoStatus2 {
+    Watermark = 7,
+    Overrun = 6,
+    Full = 5,
+    CounterBdr = 4,
+    OverrunLatched = 3,
+}
+
+/// Sensor tags of FIFO_DATA_OUT_TAG, bits 7:3
+#[allow(unused)]
+pub enum FifoTag {
+    GyroscopeNc = 0x01,
+    AccelerometerNc = 0x02,
+    Temperature = 0x03,
+    Timestamp = 0x04,
+    ConfigChange = 0x05,
+}
+
+// -------------------------------------------------------------------------------------------------
+// --- Interrupts ----------------------------------------------------------------------------------
+// -------------------------------------------------------------------------------------------------
+
+#[allow(unused)]
+/// Bit fields shared by INT1_CTRL and INT2_CTRL
+pub enum IntCtrl {
+    CounterBdr = 6,
+    FifoFull = 5,
+    FifoOverrun = 4,
+    FifoThreshold = 3,
+    DataReadyGyroscope = 1,
+    DataReadyAccelerometer = 0,
+}
+
+#[allow(unused)]
+/// Bit fields shared by MD1_CFG and MD2_CFG
+pub enum MdCfg {
+    SleepChange = 7,
+    SingleTap = 6,
+    WakeUp = 5,
+    FreeFall = 4,
+    DoubleTap = 3,
+    Orientation6d = 2,
+    EmbeddedFunction = 1,
+}
+
+#[allow(unused)]
+/// Bit fields for TAP_CFG0
+pub enum TapCfg0 {
+    ClearOnRead = 6,
+    TapXEnable = 3,
+    TapYEnable = 2,
+    TapZEnable = 1,
+    LatchedInterrupt = 0,
+}
+
+#[allow(unused)]
+/// Bit fields for TAP_CFG2
+pub enum TapCfg2 {
+    InterruptsEnable = 7,
+}
+
+#[allow(unused)]
+/// Bit fields for COUNTER_BDR_REG1
+pub enum CounterBdrReg1 {
+    DataReadyPulsed = 7,
+}
+
+#[allow(unused)]
+/// Bit fields for ALL_INT_SRC
+pub enum AllIntSrc {
+    TimestampEndCount = 7,
+    SleepChange = 5,
+    Orientation6d = 4,
+    DoubleTap = 3,
+    SingleTap = 2,
+    WakeUp = 1,
+    FreeFall = 0,
+}
+
+// -------------------------------------------------------------------------------------------------
+// --- Event detection -----------------------------------------------------------------------------
+// -------------------------------------------------------------------------------------------------
+
+/// Free-fall threshold, FREE_FALL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FreeFallThreshold {
+    Mg156 = 0b000,
+    Mg219 = 0b001,
+    Mg250 = 0b010,
+    Mg312 = 0b011,
+    Mg344 = 0b100,
+    Mg406 = 0b101,
+    Mg469 = 0b110,
+    Mg500 = 0b111,
+}
+
+impl RegisterOption for FreeFallThreshold {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b111
+    }
+    fn bit_offset() -> u8 {
+        0
+    }
+}
+
+/// Tilt from an axis that counts as a 6D orientation change, TAP_THS_6D
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrientationThreshold {
+    Deg80 = 0b00,
+    Deg70 = 0b01,
+    Deg60 = 0b10,
+    Deg50 = 0b11,
+}
+
+impl RegisterOption for OrientationThreshold {
+    fn value(&self) -> u8 {
+        *self as u8
+    }
+    fn mask() -> u8 {
+        0b11
+    }
+    fn bit_offset() -> u8 {
+        5
+    }
+}
+
+#[allow(unused)]
+/// Bit fields for WAKE_UP_THS
+pub enum WakeUpThs {
+    SingleDoubleTap = 7,
+}
+
+#[allow(unused)]
+/// Bit fields for WAKE_UP_DUR
+pub enum WakeUpDur {
+    FreeFallDuration5 = 7,
+    WakeUpThresholdWeight = 4,
+}
+
+#[allow(unused)]
+/// Bit fields for WAKE_UP_SRC
+pub enum WakeUpSrc {
+    SleepChange = 6,
+    FreeFall = 5,
+    SleepState = 4,
+    WakeUp = 3,
+    X = 2,
+    Y = 1,
+    Z = 0,
+}
+
+#[allow(unused)]
+/// Bit fields for TAP_SRC
+pub enum TapSrc {
+    Tap = 6,
+    SingleTap = 5,
+    DoubleTap = 4,
+    Negative = 3,
+    X = 2,
+    Y = 1,
+    Z = 0,
+}
+
+#[allow(unused)]
+/// Bit fields for D6D_SRC
+pub enum D6dSrc {
+    Orientation6d = 6,
+    ZHigh = 5,
+    ZLow = 4,
+    YHigh = 3,
+    YLow = 2,
+    XHigh = 1,
+    XLow = 0,
+}
+
+// -------------------------------------------------------------------------------------------------
+// --- Embedded functions page ---------------------------------------------------------------------
+// -------------------------------------------------------------------------------------------------
+
+/// Registers of the embedded functions page, reached with FUNC_CFG_ACCESS set. They share
+/// addresses with the main page.
+#[allow(unused)]
+#[derive(Debug, Clone, Copy)]
+pub enum EmbeddedRegister {
+    EmbFuncEnA = 0x04,
+    EmbFuncEnB = 0x05,
+    EmbFuncInt1 = 0x0A,
+    EmbFuncInt2 = 0x0E,
+    EmbFuncStatus = 0x12,
+    EmbFuncInitA = 0x66,
+}
+
+#[allow(unused)]
+/// Bit fields for FUNC_CFG_ACCESS
+pub enum FuncCfgAccess {
+    EmbeddedFunctions = 7,
+}
+
+#[allow(unused)]
+/// Bit of the significant motion detection in EMB_FUNC_EN_A, EMB_FUNC_INT1/2, EMB_FUNC_INIT_A
+/// and EMB_FUNC_STATUS_MAINPAGE
+pub const SIGNIFICANT_MOTION_BIT: u8 = 5;
+
+impl From<EmbeddedRegister> for u8 {
+    fn from(r: EmbeddedRegister) -> u8 {
+        r as u8
+    }
+}
@@ -0,0 +1,157 @@
+use ner_can_messages::msb::{MsbShockpot, MsbStrain};
+
+/// Largest reading of the 12 bit ADC
+pub const ADC_MAX: u16 = 4095;
+
+/// ADC1 regular sequence, the DMA buffer repeats these in order
+pub const SEQUENCE: [AdcChannel; 3] = [
+    AdcChannel::Shockpot,
+    AdcChannel::Strain1,
+    AdcChannel::Strain2,
+];
+
+/// Analog inputs sampled by ADC1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum AdcChannel {
+    /// PA0
+    Shockpot,
+    /// PA5
+    Strain1,
+    /// PA6
+    Strain2,
+}
+
+/// Straight line conversion from counts to a physical value
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct Linear {
+    /// Counts at the physical zero
+    pub zero: u16,
+    /// Physical units per count, negative if the sensor is mounted reversed
+    pub gain: f32,
+}
+
+impl Linear {
+    pub fn apply(&self, counts: u16) -> f32 {
+        (counts as f32 - self.zero as f32) * self.gain
+    }
+
+    /// The zero fits the ADC range and the gain is a usable number
+    pub fn is_valid(&self) -> bool {
+        self.zero <= ADC_MAX && self.gain.is_finite() && self.gain != 0.0
+    }
+}
+
+/// Conversions of the averaged ADC counts, tuned per car from the raw counts in the frames
+#[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+pub struct AdcCalibration {
+    /// Counts to mm of shock travel
+    pub shockpot: Linear,
+    /// Counts to microstrain
+    pub strain_1: Linear,
+    /// Counts to microstrain
+    pub strain_2: Linear,
+}
+
+impl AdcCalibration {
+    /// Nominal values: a 50 mm linear pot across the 3.3 V reference, and gauge factor 2 quarter
+    /// bridges excited at 3.3 V behind a gain of 500, centered at half scale
+    pub const DEFAULT: AdcCalibration = AdcCalibration {
+        shockpot: Linear {
+            zero: 0,
+            gain: 50.0 / ADC_MAX as f32,
+        },
+        strain_1: Linear {
+            zero: 2048,
+            gain: STRAIN_GAIN,
+        },
+        strain_2: Linear {
+            zero: 2048,
+            gain: STRAIN_GAIN,
+        },
+    };
+
+    pub const fn get(&self, channel: AdcChannel) -> Linear {
+        match channel {
+            AdcChannel::Shockpot => self.shockpot,
+            AdcChannel::Strain1 => self.strain_1,
+            AdcChannel::Strain2 => self.strain_2,
+        }
+    }
+
+    /// A copy with the conversion of `channel` replaced, `None` if it isn't valid
+    pub fn with(&self, channel: AdcChannel, linear: Linear) -> Option<Self> {
+        if !linear.is_valid() {
+            return None;
+        }
+        let mut new = *self;
+        match channel {
+            AdcChannel::Shockpot => new.shockpot = linear,
+            AdcChannel::Strain1 => new.strain_1 = linear,
+            AdcChannel::Strain2 => new.strain_2 = linear,
+        }
+        Some(new)
+    }
+
+    pub fn is_valid(&self) -> bool {
+        SEQUENCE.iter().all(|channel| self.get(*channel).is_valid())
+    }
+}
+
+impl Default for AdcCalibration {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Quarter bridge microstrain per count: 4 * (3.3 V / 4095) / (2 * 3.3 V * 500) * 1e6
+const STRAIN_GAIN: f32 = 4.0e6 / (2.0 * 500.0 * ADC_MAX as f32);
+
+/// Mean counts of every channel over a DMA buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct AdcCounts {
+    pub shockpot: u16,
+    pub strain_1: u16,
+    pub strain_2: u16,
+}
+
+impl AdcCounts {
+    /// Average a buffer of samples interleaved in [`SEQUENCE`] order, starting with the first
+    /// channel. A trailing partial sequence is ignored. `None` if there isn't a full sequence.
+    pub fn average(samples: &[u16]) -> Option<Self> {
+        let mut sums = [0u32; SEQUENCE.len()];
+        let mut count = 0;
+        for set in samples.chunks_exact(SEQUENCE.len()) {
+            for (sum, sample) in sums.iter_mut().zip(set) {
+                *sum += *sample as u32;
+            }
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+
+        // round to the nearest count
+        let mean = |sum: u32| ((sum + count / 2) / count) as u16;
+        Some(Self {
+            shockpot: mean(sums[0]),
+            strain_1: mean(sums[1]),
+            strain_2: mean(sums[2]),
+        })
+    }
+
+    /// Convert to the CAN messages
+    pub fn messages(&self, calibration: &AdcCalibration) -> (MsbShockpot, MsbStrain) {
+        (
+            MsbShockpot {
+                travel: calibration.shockpot.apply(self.shockpot),
+                shockpot_raw: self.shockpot,
+            },
+            MsbStrain {
+                strain_1: calibration.strain_1.apply(self.strain_1),
+                strain_2: calibration.strain_2.apply(self.strain_2),
+                strain_1_raw: self.strain_1,
+                strain_2_raw: self.strain_2,
+            },
+        )
+    }
+}
@@ -0,0 +1,149 @@
+//! Recovery of an I2C bus that a sensor is holding down
+//!
+//! A target that lost power or clock edges in the middle of a read can be left driving SDA low,
+//! waiting for clocks that never come, and the controller then fails every transaction. The
+//! firmware watches the errors with a [`BusMonitor`] and, once the bus looks stuck, takes the pins
+//! from the I2C peripheral and runs [`clock_out`] on them.
+
+use embedded_hal::{
+    digital::{InputPin, OutputPin},
+    i2c::ErrorKind,
+};
+use embedded_hal_async::delay::DelayNs;
+
+/// Longest an I2C transaction may take before it is given up on, in ms. The largest transfers,
+/// the LSM6DSO FIFO drains, take about 10 ms at 100 kHz.
+pub const TRANSACTION_TIMEOUT_MS: u64 = 50;
+
+/// Times a transaction that failed like a stuck bus is tried again
+pub const TRANSACTION_RETRIES: u8 = 1;
+
+/// Stuck bus errors in a row before the bus is recovered
+pub const STUCK_AFTER_ERRORS: u8 = 3;
+
+/// Clock pulses sent to let a target finish the byte it is stuck in, one byte and its ACK
+const RECOVERY_CLOCKS: u8 = 9;
+
+/// Half of a recovery clock period, for a 100 kHz clock
+const RECOVERY_HALF_PERIOD_US: u32 = 5;
+
+/// Half periods to wait for a target stretching the clock during recovery
+const RECOVERY_STRETCH_LIMIT: u8 = 100;
+
+/// Whether an error looks like the bus itself is stuck rather than a target saying no. A NACK
+/// is a missing sensor or one that is busy, and trying again right away won't help.
+pub fn stuck_error(kind: ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::Bus | ErrorKind::ArbitrationLoss | ErrorKind::Overrun | ErrorKind::Other
+    )
+}
+
+/// Counts stuck bus errors in a row to decide when the bus needs recovering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, defmt::Format)]
+pub struct BusMonitor {
+    consecutive_errors: u8,
+    /// Recoveries since boot
+    pub recoveries: u16,
+}
+
+impl BusMonitor {
+    pub const fn new() -> Self {
+        Self {
+            consecutive_errors: 0,
+            recoveries: 0,
+        }
+    }
+
+    /// A transaction went through, or was NACKed, so the bus is working
+    pub fn ok(&mut self) {
+        self.consecutive_errors = 0;
+    }
+
+    /// Record a failed transaction, returning whether the bus should be recovered now
+    pub fn error(&mut self, kind: ErrorKind) -> bool {
+        if !stuck_error(kind) {
+            self.ok();
+            return false;
+        }
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        if self.consecutive_errors < STUCK_AFTER_ERRORS {
+            return false;
+        }
+        self.consecutive_errors = 0;
+        self.recoveries = self.recoveries.wrapping_add(1);
+        true
+    }
+}
+
+/// Why [`clock_out`] could not free the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum RecoveryError {
+    /// Something holds SCL low, which clocking can't fix
+    SclStuckLow,
+    /// SDA stayed low through every recovery clock
+    SdaStuckLow,
+    /// Reading or driving a pin failed
+    Pin,
+}
+
+/// Free a bus held down by a target. Both pins must be open drain outputs that read back the
+/// line, released (high) to start with and not connected to the I2C peripheral.
+///
+/// Clocks SCL until the target lets go of SDA, at most [`RECOVERY_CLOCKS`] times, then sends a
+/// STOP so every target is back to waiting for a START.
+pub async fn clock_out<SCL, SDA, D>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay: &mut D,
+) -> Result<(), RecoveryError>
+where
+    SCL: InputPin + OutputPin,
+    SDA: InputPin + OutputPin,
+    D: DelayNs,
+{
+    sda.set_high().map_err(|_| RecoveryError::Pin)?;
+    release_scl(scl, delay).await?;
+
+    for _ in 0..RECOVERY_CLOCKS {
+        if sda.is_high().map_err(|_| RecoveryError::Pin)? {
+            break;
+        }
+        scl.set_low().map_err(|_| RecoveryError::Pin)?;
+        delay.delay_us(RECOVERY_HALF_PERIOD_US).await;
+        release_scl(scl, delay).await?;
+    }
+    if sda.is_low().map_err(|_| RecoveryError::Pin)? {
+        return Err(RecoveryError::SdaStuckLow);
+    }
+
+    // STOP, SDA rising while SCL is high
+    scl.set_low().map_err(|_| RecoveryError::Pin)?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US).await;
+    sda.set_low().map_err(|_| RecoveryError::Pin)?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US).await;
+    release_scl(scl, delay).await?;
+    sda.set_high().map_err(|_| RecoveryError::Pin)?;
+    delay.delay_us(RECOVERY_HALF_PERIOD_US).await;
+
+    if sda.is_low().map_err(|_| RecoveryError::Pin)? {
+        return Err(RecoveryError::SdaStuckLow);
+    }
+    Ok(())
+}
+
+/// Let SCL go high and wait out a half period, and any clock stretching
+async fn release_scl<SCL, D>(scl: &mut SCL, delay: &mut D) -> Result<(), RecoveryError>
+where
+    SCL: InputPin + OutputPin,
+    D: DelayNs,
+{
+    scl.set_high().map_err(|_| RecoveryError::Pin)?;
+    for _ in 0..RECOVERY_STRETCH_LIMIT {
+        delay.delay_us(RECOVERY_HALF_PERIOD_US).await;
+        if scl.is_high().map_err(|_| RecoveryError::Pin)? {
+            return Ok(());
+        }
+    }
+    Err(RecoveryError::SclStuckLow)
+}
@@ -0,0 +1,179 @@
+use ner_can_messages::msb::{MsbShockHistogram, MsbShockSamples, MsbShockStats};
+
+/// Shock pot samples per second while capturing
+pub const CAPTURE_RATE_HZ: u32 = 500;
+
+/// Shock pot samples in each [`MsbShockSamples`]
+pub const SAMPLES_PER_FRAME: usize = 3;
+
+/// Frames per second sent in raw capture mode
+pub const RAW_FRAMES_PER_SECOND: u32 = CAPTURE_RATE_HZ.div_ceil(SAMPLES_PER_FRAME as u32);
+
+/// Time between summaries in summary capture mode, in ms
+pub const SUMMARY_WINDOW_MS: u32 = 1000;
+
+/// Bins in each histogram, the outer two are open ended
+pub const HISTOGRAM_BINS: usize = 12;
+
+/// Frames sent per summary window, stats then two frames per histogram
+pub const SUMMARY_FRAMES: u32 = 1 + 4;
+
+/// Inner edges of the velocity bins in mm/s, split into low and high speed bump and rebound
+pub const VELOCITY_EDGES: [f32; HISTOGRAM_BINS - 1] = [
+    -400.0, -200.0, -100.0, -50.0, -25.0, 0.0, 25.0, 50.0, 100.0, 200.0, 400.0,
+];
+
+/// Inner edges of the position bins in mm, 5 mm apart from 0 mm
+pub const POSITION_EDGES: [f32; HISTOGRAM_BINS - 1] = [
+    0.0, 5.0, 10.0, 15.0, 20.0, 25.0, 30.0, 35.0, 40.0, 45.0, 50.0,
+];
+
+/// Time spent in each bin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Histogram {
+    pub counts: [u32; HISTOGRAM_BINS],
+}
+
+impl Histogram {
+    /// Count a value into the bin it falls in, values on an edge go to the bin above
+    pub fn add(&mut self, edges: &[f32; HISTOGRAM_BINS - 1], value: f32) {
+        let bin = edges.iter().take_while(|edge| value >= **edge).count();
+        self.counts[bin] += 1;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    /// Share of the samples in each bin, in %
+    pub fn percent(&self) -> [f32; HISTOGRAM_BINS] {
+        let total = self.total().max(1) as f32;
+        self.counts.map(|count| count as f32 * 100.0 / total)
+    }
+}
+
+/// Histograms and extremes of a summary window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShockSummary {
+    pub position: Histogram,
+    pub velocity: Histogram,
+    pub position_min: f32,
+    pub position_max: f32,
+    pub velocity_min: f32,
+    pub velocity_max: f32,
+}
+
+impl ShockSummary {
+    const EMPTY: ShockSummary = ShockSummary {
+        position: Histogram {
+            counts: [0; HISTOGRAM_BINS],
+        },
+        velocity: Histogram {
+            counts: [0; HISTOGRAM_BINS],
+        },
+        position_min: f32::MAX,
+        position_max: f32::MIN,
+        velocity_min: f32::MAX,
+        velocity_max: f32::MIN,
+    };
+
+    /// The stats frame followed by the four histogram frames, tagged with `sequence`
+    pub fn messages(&self, sequence: u8) -> (MsbShockStats, [MsbShockHistogram; 4]) {
+        let stats = MsbShockStats {
+            position_min: self.position_min,
+            position_max: self.position_max,
+            velocity_min: self.velocity_min,
+            velocity_max: self.velocity_max,
+        };
+
+        let position = self.position.percent();
+        let velocity = self.velocity.percent();
+        let part = |part: u8| {
+            let histogram = if part < 2 { &position } else { &velocity };
+            let bins = &histogram[(part as usize % 2) * 6..];
+            MsbShockHistogram {
+                sequence,
+                part,
+                bin_1: bins[0],
+                bin_2: bins[1],
+                bin_3: bins[2],
+                bin_4: bins[3],
+                bin_5: bins[4],
+                bin_6: bins[5],
+            }
+        };
+        (stats, [part(0), part(1), part(2), part(3)])
+    }
+}
+
+/// Turns shock pot samples taken at [`CAPTURE_RATE_HZ`] into raw sample frames and summaries
+pub struct ShockCapture {
+    last: Option<f32>,
+    summary: ShockSummary,
+    samples: [f32; SAMPLES_PER_FRAME],
+    buffered: usize,
+    frame_sequence: u16,
+    summary_sequence: u8,
+}
+
+impl ShockCapture {
+    pub const fn new() -> Self {
+        Self {
+            last: None,
+            summary: ShockSummary::EMPTY,
+            samples: [0.0; SAMPLES_PER_FRAME],
+            buffered: 0,
+            frame_sequence: 0,
+            summary_sequence: 0,
+        }
+    }
+
+    /// Add the next sample in mm, returning a raw frame once [`SAMPLES_PER_FRAME`] are buffered.
+    /// A missed sample should still be pushed, repeating the last one, to keep the timing.
+    pub fn push(&mut self, travel: f32) -> Option<MsbShockSamples> {
+        let summary = &mut self.summary;
+        summary.position.add(&POSITION_EDGES, travel);
+        summary.position_min = summary.position_min.min(travel);
+        summary.position_max = summary.position_max.max(travel);
+        // positive while travel increases, which is bump with the zero at full droop
+        if let Some(last) = self.last {
+            let velocity = (travel - last) * CAPTURE_RATE_HZ as f32;
+            summary.velocity.add(&VELOCITY_EDGES, velocity);
+            summary.velocity_min = summary.velocity_min.min(velocity);
+            summary.velocity_max = summary.velocity_max.max(velocity);
+        }
+        self.last = Some(travel);
+
+        self.samples[self.buffered] = travel;
+        self.buffered += 1;
+        if self.buffered < SAMPLES_PER_FRAME {
+            return None;
+        }
+        self.buffered = 0;
+        let frame = MsbShockSamples {
+            sequence: self.frame_sequence,
+            sample_1: self.samples[0],
+            sample_2: self.samples[1],
+            sample_3: self.samples[2],
+        };
+        self.frame_sequence = self.frame_sequence.wrapping_add(1);
+        Some(frame)
+    }
+
+    /// The summary since the last call and its sequence number, `None` if no velocity was seen
+    pub fn take_summary(&mut self) -> Option<(u8, ShockSummary)> {
+        let summary = core::mem::replace(&mut self.summary, ShockSummary::EMPTY);
+        if summary.velocity.total() == 0 {
+            return None;
+        }
+        let sequence = self.summary_sequence;
+        self.summary_sequence = self.summary_sequence.wrapping_add(1);
+        Some((sequence, summary))
+    }
+}
+
+impl Default for ShockCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,354 @@
+use ner_can_messages::msb::{CaptureMode, DeviceLocation, Reader};
+
+use crate::{
+    adc::{AdcCalibration, AdcChannel, Linear, SEQUENCE},
+    capture::{RAW_FRAMES_PER_SECOND, SUMMARY_FRAMES, SUMMARY_WINDOW_MS},
+    health::DIAGNOSTIC_PERIOD_MS,
+    imu::{ImuRate, Mounting, SensorAxis},
+};
+
+/// Slowest refresh time any reader accepts, in ms
+pub const MAX_REFRESH_TIME: u16 = 60_000;
+
+/// I2C bits per second the readers may use together, half of the 100 kHz bus to leave room for
+/// retries and clock stretching
+pub const I2C_BUDGET: u32 = 50_000;
+
+/// CAN frames per second the readers may produce together. Four MSBs at this rate use about
+/// 40% of the 500 kbit/s bus, and `CAN_CHANNEL` never backs up while the bus keeps up.
+pub const CAN_BUDGET: u32 = 400;
+
+/// Fastest refresh time of a reader in ms, set by how long its sensor takes to measure
+pub const fn min_refresh_time(reader: Reader) -> u16 {
+    match reader {
+        // periodic mode measures at most 10 times a second
+        Reader::Temperature => 100,
+        Reader::Imu => 10,
+        // continuous ranging with the default convergence time
+        Reader::Tof => crate::tof::CONTINUOUS_MIN_MS,
+        Reader::Adc => 10,
+    }
+}
+
+/// Approximate bits clocked on the I2C bus per reading, including addressing and ACKs
+pub const fn i2c_bits(reader: Reader) -> u32 {
+    match reader {
+        // fetch command write, then 6 bytes read
+        Reader::Temperature => 10 * 9,
+        // register write then a 7 byte FIFO word read, for accel and gyro plus an eighth of a
+        // timestamp word, rounded up to cover the status read of each drain
+        Reader::Imu => (2 * 10 + 2) * 9,
+        // without GPIO1, an interrupt status read (5 bytes) per poll, counting the one that finds
+        // the sample, then the range status read (5 bytes), the range results read (38 bytes) and
+        // the interrupt clear (4 bytes). Waiting on GPIO1 leaves just the one status read.
+        Reader::Tof => ((crate::tof::TOF_POLLS_PER_PERIOD as u32 + 1) * 5 + 5 + 38 + 4) * 9,
+        Reader::Adc => 0,
+    }
+}
+
+/// CAN frames sent per reading, for the IMU a reading is one FIFO sample
+pub const fn can_frames(reader: Reader) -> u32 {
+    match reader {
+        Reader::Temperature | Reader::Tof => 1,
+        Reader::Imu | Reader::Adc => 2,
+    }
+}
+
+/// CAN frames per second sent by a shock capture mode, on top of the readers
+pub const fn capture_frames(mode: CaptureMode) -> u32 {
+    match mode {
+        CaptureMode::Off => 0,
+        CaptureMode::Summary => SUMMARY_FRAMES * 1000 / SUMMARY_WINDOW_MS,
+        CaptureMode::Raw => RAW_FRAMES_PER_SECOND,
+    }
+}
+
+/// Why a set of refresh times was rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ConfigError {
+    /// Faster than the sensor measures, argument is the minimum in ms
+    TooFast(Reader, u16),
+    /// Slower than [`MAX_REFRESH_TIME`]
+    TooSlow(Reader),
+    /// Would exceed [`I2C_BUDGET`], argument is the requested bits per second
+    I2cOverloaded(u32),
+    /// Would exceed [`CAN_BUDGET`], argument is the requested frames per second
+    CanOverloaded(u32),
+    /// Zero outside the ADC range, or a gain that is zero or not finite
+    BadCalibration(AdcChannel),
+}
+
+/// Time between readings of every reader, in ms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct RefreshTimes {
+    pub temperature: u16,
+    /// Longest time between IMU samples, which picks the FIFO rate, see [`ImuRate`]
+    pub imu: u16,
+    /// Sets the VL6180X ranging period, see [`continuous_rate`](crate::tof::continuous_rate)
+    pub tof: u16,
+    pub adc: u16,
+}
+
+impl RefreshTimes {
+    pub const DEFAULT: RefreshTimes = RefreshTimes {
+        temperature: 500,
+        imu: 500,
+        tof: 500,
+        adc: 250,
+    };
+
+    pub const fn get(&self, reader: Reader) -> u16 {
+        match reader {
+            Reader::Temperature => self.temperature,
+            Reader::Imu => self.imu,
+            Reader::Tof => self.tof,
+            Reader::Adc => self.adc,
+        }
+    }
+
+    fn get_mut(&mut self, reader: Reader) -> &mut u16 {
+        match reader {
+            Reader::Temperature => &mut self.temperature,
+            Reader::Imu => &mut self.imu,
+            Reader::Tof => &mut self.tof,
+            Reader::Adc => &mut self.adc,
+        }
+    }
+
+    /// A copy with the refresh time of `reader` changed, `None` changes every reader.
+    /// The copy is validated, so a rejected change leaves the current times in place.
+    pub fn with(&self, reader: Option<Reader>, ms: u16) -> Result<Self, ConfigError> {
+        let mut new = *self;
+        match reader {
+            Some(reader) => *new.get_mut(reader) = ms,
+            None => Reader::ALL
+                .iter()
+                .for_each(|reader| *new.get_mut(*reader) = ms),
+        }
+        new.validate()?;
+        Ok(new)
+    }
+
+    /// Check every reader is within its limits and the readers together stay within budget
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for reader in Reader::ALL {
+            let ms = self.get(reader);
+            if ms < min_refresh_time(reader) {
+                return Err(ConfigError::TooFast(reader, min_refresh_time(reader)));
+            }
+            if ms > MAX_REFRESH_TIME {
+                return Err(ConfigError::TooSlow(reader));
+            }
+        }
+
+        let i2c = self.i2c_load();
+        if i2c > I2C_BUDGET {
+            return Err(ConfigError::I2cOverloaded(i2c));
+        }
+        let can = self.can_load();
+        if can > CAN_BUDGET {
+            return Err(ConfigError::CanOverloaded(can));
+        }
+        Ok(())
+    }
+
+    /// I2C bits per second used by the readers together
+    pub fn i2c_load(&self) -> u32 {
+        self.per_second(i2c_bits)
+    }
+
+    /// CAN frames per second sent by the readers together, and the diagnostic frame
+    pub fn can_load(&self) -> u32 {
+        self.per_second(can_frames) + (1000 / DIAGNOSTIC_PERIOD_MS) as u32
+    }
+
+    /// Check the readers and a shock capture mode together stay within [`CAN_BUDGET`]
+    pub fn validate_capture(&self, mode: CaptureMode) -> Result<(), ConfigError> {
+        let can = self.can_load() + capture_frames(mode);
+        if can > CAN_BUDGET {
+            return Err(ConfigError::CanOverloaded(can));
+        }
+        Ok(())
+    }
+
+    fn per_second(&self, per_reading: fn(Reader) -> u32) -> u32 {
+        Reader::ALL
+            .iter()
+            .map(|reader| match reader {
+                // every sample batched at the rate picked for the refresh time is sent
+                Reader::Imu => {
+                    per_reading(*reader) * ImuRate::for_refresh_time(self.imu).samples_per_second()
+                }
+                _ => per_reading(*reader) * 1000 / self.get(*reader).max(1) as u32,
+            })
+            .sum()
+    }
+}
+
+impl Default for RefreshTimes {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Everything the MSB keeps in flash
+#[derive(Debug, Clone, Copy, PartialEq, Default, defmt::Format)]
+pub struct MsbSettings {
+    pub refresh_times: RefreshTimes,
+    pub adc_calibration: AdcCalibration,
+    /// How the LSM6DSO is turned relative to the car at each corner, in [`DeviceLocation::ALL`]
+    /// order. The one of the corner the board is strapped to is applied when the IMU calibrates
+    /// after a reboot, so every board can carry the same table.
+    pub mountings: [Mounting; 4],
+}
+
+/// Index of a corner in [`DeviceLocation::ALL`] and [`MsbSettings::mountings`]
+pub const fn corner(location: DeviceLocation) -> usize {
+    match location {
+        DeviceLocation::FrontLeft => 0,
+        DeviceLocation::FrontRight => 1,
+        DeviceLocation::BackLeft => 2,
+        DeviceLocation::BackRight => 3,
+    }
+}
+
+/// A corner as typed into the serial console
+pub const fn corner_name(location: DeviceLocation) -> &'static str {
+    match location {
+        DeviceLocation::FrontLeft => "frontleft",
+        DeviceLocation::FrontRight => "frontright",
+        DeviceLocation::BackLeft => "backleft",
+        DeviceLocation::BackRight => "backright",
+    }
+}
+
+impl ner_config_store::Record for MsbSettings {
+    const VERSION: u16 = 1;
+    const SIZE: usize = 8 + 3 * 6 + 4 * 3;
+
+    fn encode(&self, buf: &mut [u8]) {
+        for (i, reader) in Reader::ALL.into_iter().enumerate() {
+            buf[2 * i..2 * i + 2].copy_from_slice(&self.refresh_times.get(reader).to_le_bytes());
+        }
+        for (i, channel) in SEQUENCE.into_iter().enumerate() {
+            let linear = self.adc_calibration.get(channel);
+            let at = 8 + 6 * i;
+            buf[at..at + 2].copy_from_slice(&linear.zero.to_le_bytes());
+            buf[at + 2..at + 6].copy_from_slice(&linear.gain.to_le_bytes());
+        }
+        for (i, mounting) in self.mountings.iter().enumerate() {
+            let at = 26 + 3 * i;
+            buf[at..at + 3].copy_from_slice(&mounting.to_raw());
+        }
+    }
+
+    /// Each part is validated on its own, so refresh times that no longer pass validation, say
+    /// after a budget is tightened, are dropped for the defaults without losing the calibration.
+    /// Likewise a channel calibration that can't convert anything, or a mounting that isn't a
+    /// rotation, falls back to its default.
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let mut settings = Self::default();
+        for (i, reader) in Reader::ALL.into_iter().enumerate() {
+            *settings.refresh_times.get_mut(reader) =
+                u16::from_le_bytes([buf[2 * i], buf[2 * i + 1]]);
+        }
+        if settings.refresh_times.validate().is_err() {
+            settings.refresh_times = RefreshTimes::DEFAULT;
+        }
+
+        for (i, channel) in SEQUENCE.into_iter().enumerate() {
+            let at = 8 + 6 * i;
+            let linear = Linear {
+                zero: u16::from_le_bytes([buf[at], buf[at + 1]]),
+                gain: f32::from_le_bytes([buf[at + 2], buf[at + 3], buf[at + 4], buf[at + 5]]),
+            };
+            if let Some(calibration) = settings.adc_calibration.with(channel, linear) {
+                settings.adc_calibration = calibration;
+            }
+        }
+        for (i, mounting) in settings.mountings.iter_mut().enumerate() {
+            let at = 26 + 3 * i;
+            *mounting = Mounting::from_raw([buf[at], buf[at + 1], buf[at + 2]]).unwrap_or_default();
+        }
+        Some(settings)
+    }
+}
+
+/// A line typed into the serial console
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsoleCommand {
+    /// `rates`
+    ShowRates,
+    /// `rate <temperature|imu|tof|adc|all> <ms>`
+    SetRate(Option<Reader>, u16),
+    /// `capture <off|summary|raw>`
+    SetCapture(CaptureMode),
+    /// `cal`
+    ShowCalibration,
+    /// `cal <shockpot|strain1|strain2> <zero counts> <units per count>`
+    SetCalibration(AdcChannel, Linear),
+    /// `mount`
+    ShowMounting,
+    /// `mount <frontleft|frontright|backleft|backright> <x> <y> <z>`, the sensor axis (`+x`, `-z`
+    /// and so on) that the vehicle x forward, y left and z up each point along at that corner
+    SetMounting(DeviceLocation, Mounting),
+}
+
+impl ConsoleCommand {
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut words = line.split_whitespace();
+        let cmd = match words.next()? {
+            "rates" => ConsoleCommand::ShowRates,
+            "rate" => {
+                let reader = match words.next()? {
+                    "temperature" => Some(Reader::Temperature),
+                    "imu" => Some(Reader::Imu),
+                    "tof" => Some(Reader::Tof),
+                    "adc" => Some(Reader::Adc),
+                    "all" => None,
+                    _ => return None,
+                };
+                ConsoleCommand::SetRate(reader, words.next()?.parse().ok()?)
+            }
+            "capture" => ConsoleCommand::SetCapture(match words.next()? {
+                "off" => CaptureMode::Off,
+                "summary" => CaptureMode::Summary,
+                "raw" => CaptureMode::Raw,
+                _ => return None,
+            }),
+            "cal" => match words.next() {
+                None => ConsoleCommand::ShowCalibration,
+                Some(channel) => {
+                    let channel = match channel {
+                        "shockpot" => AdcChannel::Shockpot,
+                        "strain1" => AdcChannel::Strain1,
+                        "strain2" => AdcChannel::Strain2,
+                        _ => return None,
+                    };
+                    let linear = Linear {
+                        zero: words.next()?.parse().ok()?,
+                        gain: words.next()?.parse().ok()?,
+                    };
+                    ConsoleCommand::SetCalibration(channel, linear)
+                }
+            },
+            "mount" => match words.next() {
+                None => ConsoleCommand::ShowMounting,
+                Some(location) => {
+                    let location = DeviceLocation::ALL
+                        .into_iter()
+                        .find(|corner| corner_name(*corner) == location)?;
+                    let mounting = Mounting::new(
+                        SensorAxis::parse(words.next()?)?,
+                        SensorAxis::parse(words.next()?)?,
+                        SensorAxis::parse(words.next()?)?,
+                    )?;
+                    ConsoleCommand::SetMounting(location, mounting)
+                }
+            },
+            _ => return None,
+        };
+        // no trailing words
+        words.next().is_none().then_some(cmd)
+    }
+}
@@ -0,0 +1,251 @@
+use embedded_hal::i2c::ErrorKind;
+use ner_can_messages::msb::{MsbDiagnostic, SensorFault, SensorState};
+
+/// Time between [`MsbDiagnostic`] frames, in ms
+pub const DIAGNOSTIC_PERIOD_MS: u64 = 1000;
+
+/// Failed readings in a row after which a sensor counts as failed and is initialized again
+pub const FAILED_AFTER_ERRORS: u8 = 5;
+
+/// Wait before retrying a failed initialization the first time, doubled on every failure after
+pub const RETRY_MIN_MS: u64 = 1000;
+
+/// Longest wait between initialization retries, in ms
+pub const RETRY_MAX_MS: u64 = 60_000;
+
+/// A sensor on the MSB I2C bus whose health is tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Sensor {
+    /// SHT30
+    Temperature,
+    /// LSM6DSO
+    Imu,
+    /// VL6180X
+    Tof,
+}
+
+/// Sorts a driver error into the [`SensorFault`] reported on CAN
+pub trait Fault {
+    /// `None` for errors about the measurement rather than the sensor, such as the VL6180X having
+    /// nothing in range
+    fn fault(&self) -> Option<SensorFault>;
+}
+
+impl Fault for SensorFault {
+    fn fault(&self) -> Option<SensorFault> {
+        Some(*self)
+    }
+}
+
+fn bus_fault(kind: ErrorKind) -> SensorFault {
+    match kind {
+        ErrorKind::NoAcknowledge(_) => SensorFault::NoAcknowledge,
+        ErrorKind::Bus | ErrorKind::ArbitrationLoss | ErrorKind::Overrun => SensorFault::Bus,
+        _ => SensorFault::Other,
+    }
+}
+
+impl<E: embedded_hal::i2c::Error> Fault for sht3x_ner::Error<E> {
+    fn fault(&self) -> Option<SensorFault> {
+        Some(match self {
+            sht3x_ner::Error::Crc => SensorFault::Crc,
+            sht3x_ner::Error::I2c(err) => bus_fault(err.kind()),
+        })
+    }
+}
+
+impl<E: embedded_hal::i2c::Error> Fault for lsm6dso_ner::Error<E> {
+    fn fault(&self) -> Option<SensorFault> {
+        Some(match self {
+            lsm6dso_ner::Error::CommunicationError(err) => bus_fault(err.kind()),
+            lsm6dso_ner::Error::ChipDetectFailed => SensorFault::WrongDevice,
+            lsm6dso_ner::Error::DataNotReady => SensorFault::Timeout,
+            lsm6dso_ner::Error::RegisterReadFailed => SensorFault::Other,
+        })
+    }
+}
+
+impl<E: embedded_hal::i2c::Error> Fault for vl6180x_ner::Error<E> {
+    fn fault(&self) -> Option<SensorFault> {
+        use vl6180x_ner::RangeStatusErrorCode::*;
+        match self {
+            // the VCSEL and PLL checks fail on the sensor itself, the other codes are about what
+            // it measured
+            vl6180x_ner::Error::RangeStatusError(
+                VcselContinuityTest | VcselWatchdogTest | VcselWatchdog | Pll1Lock | Pll2Lock,
+            ) => Some(SensorFault::Other),
+            vl6180x_ner::Error::RangeStatusError(_) | vl6180x_ner::Error::AmbientStatusError(_) => {
+                None
+            }
+            vl6180x_ner::Error::BusError(err) | vl6180x_ner::Error::GpioPinError(err) => {
+                Some(bus_fault(err.kind()))
+            }
+            vl6180x_ner::Error::InvalidDevice(_) => Some(SensorFault::WrongDevice),
+            vl6180x_ner::Error::Timeout | vl6180x_ner::Error::ResultNotReady => {
+                Some(SensorFault::Timeout)
+            }
+            _ => Some(SensorFault::Other),
+        }
+    }
+}
+
+/// Health of one sensor, updated by its reader after every initialization and reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct SensorHealth {
+    pub state: SensorState,
+    /// Failed readings or initializations in a row
+    pub consecutive_errors: u8,
+    /// Kept after the sensor recovers, so a logger can see what went wrong last
+    pub last_fault: Option<SensorFault>,
+    /// Initializations failed in a row, sets the retry backoff
+    init_failures: u8,
+    initialized: bool,
+    self_test_failed: bool,
+}
+
+impl SensorHealth {
+    /// Not present until the first initialization
+    pub const NEW: SensorHealth = SensorHealth {
+        state: SensorState::NotPresent,
+        consecutive_errors: 0,
+        last_fault: None,
+        init_failures: 0,
+        initialized: false,
+        self_test_failed: false,
+    };
+
+    /// Ok, or degraded after a failed self-test
+    fn working(&self) -> SensorState {
+        if self.self_test_failed {
+            SensorState::Degraded
+        } else {
+            SensorState::Ok
+        }
+    }
+
+    fn count_error(&mut self, fault: SensorFault) {
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        self.last_fault = Some(fault);
+    }
+
+    pub fn init_ok(&mut self) {
+        self.state = self.working();
+        self.consecutive_errors = 0;
+        self.init_failures = 0;
+        self.initialized = true;
+    }
+
+    /// A sensor that never answered at its address is not fitted, anything else has failed
+    pub fn init_failed(&mut self, fault: Option<SensorFault>) {
+        let fault = fault.unwrap_or(SensorFault::Other);
+        self.count_error(fault);
+        self.init_failures = self.init_failures.saturating_add(1);
+        self.state = if !self.initialized && fault == SensorFault::NoAcknowledge {
+            SensorState::NotPresent
+        } else {
+            SensorState::Failed
+        };
+    }
+
+    /// The sensor is degraded until the next reboot, as the self-test only runs once
+    pub fn self_test_failed(&mut self) {
+        self.self_test_failed = true;
+        self.last_fault = Some(SensorFault::SelfTest);
+        if self.state == SensorState::Ok {
+            self.state = SensorState::Degraded;
+        }
+    }
+
+    pub fn reading_ok(&mut self) {
+        self.state = self.working();
+        self.consecutive_errors = 0;
+    }
+
+    /// Degraded on the first failed reading, failed after [`FAILED_AFTER_ERRORS`] in a row. A
+    /// reading that failed without a [`SensorFault`] still counts as working.
+    pub fn reading_failed(&mut self, fault: Option<SensorFault>) {
+        let Some(fault) = fault else {
+            self.reading_ok();
+            return;
+        };
+        self.count_error(fault);
+        self.state = if self.consecutive_errors >= FAILED_AFTER_ERRORS {
+            SensorState::Failed
+        } else {
+            SensorState::Degraded
+        };
+    }
+
+    /// Whether the reader should give up on the sensor and initialize it again
+    pub fn failed(&self) -> bool {
+        self.state == SensorState::Failed
+    }
+
+    /// Wait before retrying a failed initialization, doubling from [`RETRY_MIN_MS`] up to
+    /// [`RETRY_MAX_MS`]
+    pub fn retry_delay_ms(&self) -> u64 {
+        let doublings = self.init_failures.saturating_sub(1).min(16);
+        (RETRY_MIN_MS << doublings).min(RETRY_MAX_MS)
+    }
+}
+
+impl Default for SensorHealth {
+    fn default() -> Self {
+        Self::NEW
+    }
+}
+
+/// Health of every sensor on the MSB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct BoardHealth {
+    pub temperature: SensorHealth,
+    pub imu: SensorHealth,
+    pub tof: SensorHealth,
+}
+
+impl BoardHealth {
+    pub const NEW: BoardHealth = BoardHealth {
+        temperature: SensorHealth::NEW,
+        imu: SensorHealth::NEW,
+        tof: SensorHealth::NEW,
+    };
+
+    pub fn get(&self, sensor: Sensor) -> &SensorHealth {
+        match sensor {
+            Sensor::Temperature => &self.temperature,
+            Sensor::Imu => &self.imu,
+            Sensor::Tof => &self.tof,
+        }
+    }
+
+    pub fn get_mut(&mut self, sensor: Sensor) -> &mut SensorHealth {
+        match sensor {
+            Sensor::Temperature => &mut self.temperature,
+            Sensor::Imu => &mut self.imu,
+            Sensor::Tof => &mut self.tof,
+        }
+    }
+
+    /// The diagnostic frame, `uptime_s` wraps
+    pub fn message(&self, uptime_s: u64) -> MsbDiagnostic {
+        let (temperature, imu, tof) = (&self.temperature, &self.imu, &self.tof);
+        MsbDiagnostic {
+            temperature_state: temperature.state.to_raw(),
+            temperature_fault: SensorFault::to_raw(temperature.last_fault),
+            temperature_errors: temperature.consecutive_errors,
+            imu_state: imu.state.to_raw(),
+            imu_fault: SensorFault::to_raw(imu.last_fault),
+            imu_errors: imu.consecutive_errors,
+            tof_state: tof.state.to_raw(),
+            tof_fault: SensorFault::to_raw(tof.last_fault),
+            tof_errors: tof.consecutive_errors,
+            uptime: uptime_s as u16,
+        }
+    }
+}
+
+impl Default for BoardHealth {
+    fn default() -> Self {
+        Self::NEW
+    }
+}
@@ -0,0 +1,627 @@
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+use lsm6dso_ner::{
+    AccelerometerBatchRate, AccelerometerFilter, AccelerometerFilterBandwidth, AccelerometerOutput,
+    AccelerometerScale, Axes, Error, FifoConfig, FifoData, FifoMode, FifoRecord, FreeFallConfig,
+    FreeFallThreshold, GyroBiasEstimator, GyroscopeBatchRate, GyroscopeFilter, GyroscopeFullScale,
+    GyroscopeOutput, ImuBias, InterruptPin, InterruptRoute, Lsm6dso, Lsm6dsoConfig, TapConfig,
+    TemperatureBatchRate, TimestampDecimation,
+};
+use ner_can_messages::msb::{ImuEvent, MsbAccel, MsbGyro, MsbImuEvent};
+
+/// The LSM6DSO on the MSB has SDO/SA0 pulled low
+pub const LSM6DSO_ADDR: u8 = 0x6A;
+
+/// Time between FIFO drains in ms. The FIFO holds seconds of samples even at the fastest rate,
+/// so a drain held up by a busy bus loses nothing.
+pub const FIFO_DRAIN_MS: u64 = 100;
+
+/// Impact detection, a single tap over 5/8 of the full scale on any axis lasting at most 8 samples
+pub const IMPACT_DETECTION: TapConfig = TapConfig {
+    axes: Axes::ALL,
+    threshold: 20,
+    shock: 1,
+    quiet: 1,
+    duration: 0,
+    double_tap: false,
+};
+
+/// Free-fall detection, every axis under 312 mg for 6 samples
+pub const FREE_FALL_DETECTION: FreeFallConfig = FreeFallConfig {
+    threshold: FreeFallThreshold::Mg312,
+    duration: 6,
+};
+
+/// Readings averaged by [`calibrate_imu`]
+pub const CALIBRATION_SAMPLES: u16 = 32;
+
+/// Gyro readings slower than this on every axis are taken as the car standing still, in rad/s
+pub const GYRO_STILL_RATE: f32 = 0.02;
+
+/// How far each still reading moves the gyro bias estimate
+pub const GYRO_BIAS_WEIGHT: f32 = 0.001;
+
+/// Standard gravity in m/s^2
+const GRAVITY: f32 = 9.80665;
+
+/// LSM6DSO settings applied at init.
+///
+/// Both sensors stay off until [`start_imu_fifo`] picks the rate. LPF2 at a quarter of the
+/// output rate keeps engine and road vibration from aliasing into the chassis and suspension
+/// motion.
+pub const IMU_CONFIG: Lsm6dsoConfig = Lsm6dsoConfig {
+    accelerometer_output: AccelerometerOutput::PowerDown,
+    accelerometer_scale: AccelerometerScale::G02,
+    accelerometer_filter: AccelerometerFilter::LowPass(AccelerometerFilterBandwidth::Odr4),
+    gyroscope_output: GyroscopeOutput::PowerDown,
+    gyroscope_scale: GyroscopeFullScale::Dps250,
+    gyroscope_filter: GyroscopeFilter {
+        lpf1: None,
+        high_pass: None,
+    },
+    block_data_update: true,
+    timestamp: true,
+    ..Lsm6dsoConfig::RESET
+};
+
+/// Create and detect the LSM6DSO driver for the MSB, and apply [`IMU_CONFIG`]
+pub async fn init_imu<I2C, E>(i2c: I2C) -> Result<Lsm6dso<I2C>, Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    Lsm6dso::new_with_config(i2c, LSM6DSO_ADDR, &IMU_CONFIG).await
+}
+
+/// Rates the accelerometer and gyro run and are batched into the FIFO at, always together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ImuRate {
+    Hz12_5,
+    Hz26,
+    Hz52,
+    Hz104,
+}
+
+impl ImuRate {
+    /// The slowest rate with at least one sample every `ms`
+    pub const fn for_refresh_time(ms: u16) -> Self {
+        match ms {
+            80.. => ImuRate::Hz12_5,
+            39.. => ImuRate::Hz26,
+            20.. => ImuRate::Hz52,
+            _ => ImuRate::Hz104,
+        }
+    }
+
+    /// Samples per second, rounded up
+    pub const fn samples_per_second(self) -> u32 {
+        match self {
+            ImuRate::Hz12_5 => 13,
+            ImuRate::Hz26 => 26,
+            ImuRate::Hz52 => 52,
+            ImuRate::Hz104 => 104,
+        }
+    }
+
+    /// Time between samples in ticks of the LSM6DSO timestamp
+    pub const fn period_ticks(self) -> u32 {
+        match self {
+            ImuRate::Hz12_5 => 3200,
+            ImuRate::Hz26 => 1538,
+            ImuRate::Hz52 => 769,
+            ImuRate::Hz104 => 385,
+        }
+    }
+
+    /// FIFO words batched in one [`FIFO_DRAIN_MS`], the accel and gyro words of at least one
+    /// sample, so the watermark interrupt fires about as often as the timed drain
+    pub const fn fifo_watermark(self) -> u16 {
+        let samples = self.samples_per_second() * FIFO_DRAIN_MS as u32 / 1000;
+        2 * if samples == 0 { 1 } else { samples as u16 }
+    }
+
+    const fn outputs(self) -> (AccelerometerOutput, GyroscopeOutput) {
+        match self {
+            ImuRate::Hz12_5 => (AccelerometerOutput::Rate12_5, GyroscopeOutput::Rate12_5),
+            ImuRate::Hz26 => (AccelerometerOutput::Rate26, GyroscopeOutput::Rate26),
+            ImuRate::Hz52 => (AccelerometerOutput::Rate52, GyroscopeOutput::Rate52),
+            ImuRate::Hz104 => (AccelerometerOutput::Rate104, GyroscopeOutput::Rate104),
+        }
+    }
+
+    const fn batch_rates(self) -> (AccelerometerBatchRate, GyroscopeBatchRate) {
+        match self {
+            ImuRate::Hz12_5 => (
+                AccelerometerBatchRate::Rate12_5,
+                GyroscopeBatchRate::Rate12_5,
+            ),
+            ImuRate::Hz26 => (AccelerometerBatchRate::Rate26, GyroscopeBatchRate::Rate26),
+            ImuRate::Hz52 => (AccelerometerBatchRate::Rate52, GyroscopeBatchRate::Rate52),
+            ImuRate::Hz104 => (AccelerometerBatchRate::Rate104, GyroscopeBatchRate::Rate104),
+        }
+    }
+}
+
+/// A sensor axis and the way it points, for a [`Mounting`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SensorAxis {
+    PlusX,
+    MinusX,
+    PlusY,
+    MinusY,
+    PlusZ,
+    MinusZ,
+}
+
+impl SensorAxis {
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(SensorAxis::PlusX),
+            1 => Some(SensorAxis::MinusX),
+            2 => Some(SensorAxis::PlusY),
+            3 => Some(SensorAxis::MinusY),
+            4 => Some(SensorAxis::PlusZ),
+            5 => Some(SensorAxis::MinusZ),
+            _ => None,
+        }
+    }
+
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            SensorAxis::PlusX => 0,
+            SensorAxis::MinusX => 1,
+            SensorAxis::PlusY => 2,
+            SensorAxis::MinusY => 3,
+            SensorAxis::PlusZ => 4,
+            SensorAxis::MinusZ => 5,
+        }
+    }
+
+    /// `+x`, `-x`, `+y`, `-y`, `+z` or `-z`
+    pub fn parse(word: &str) -> Option<Self> {
+        match word {
+            "+x" => Some(SensorAxis::PlusX),
+            "-x" => Some(SensorAxis::MinusX),
+            "+y" => Some(SensorAxis::PlusY),
+            "-y" => Some(SensorAxis::MinusY),
+            "+z" => Some(SensorAxis::PlusZ),
+            "-z" => Some(SensorAxis::MinusZ),
+            _ => None,
+        }
+    }
+
+    /// As written for [`parse`](SensorAxis::parse)
+    pub const fn name(self) -> &'static str {
+        match self {
+            SensorAxis::PlusX => "+x",
+            SensorAxis::MinusX => "-x",
+            SensorAxis::PlusY => "+y",
+            SensorAxis::MinusY => "-y",
+            SensorAxis::PlusZ => "+z",
+            SensorAxis::MinusZ => "-z",
+        }
+    }
+
+    /// The unit vector along the axis, in sensor axes
+    const fn unit(self) -> [f32; 3] {
+        match self {
+            SensorAxis::PlusX => [1.0, 0.0, 0.0],
+            SensorAxis::MinusX => [-1.0, 0.0, 0.0],
+            SensorAxis::PlusY => [0.0, 1.0, 0.0],
+            SensorAxis::MinusY => [0.0, -1.0, 0.0],
+            SensorAxis::PlusZ => [0.0, 0.0, 1.0],
+            SensorAxis::MinusZ => [0.0, 0.0, -1.0],
+        }
+    }
+}
+
+/// How the LSM6DSO is turned relative to the car.
+///
+/// Given as the sensor axis each vehicle axis, x forward, y left and z up, points along. It
+/// depends on the corner a board is bolted to, so one is set for each [`DeviceLocation`] from
+/// the console and saved with the [`MsbSettings`](crate::config::MsbSettings). Until then it is
+/// [`IDENTITY`](Mounting::IDENTITY).
+///
+/// [`DeviceLocation`]: ner_can_messages::msb::DeviceLocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Mounting([SensorAxis; 3]);
+
+impl Mounting {
+    /// Sensor axes already match the vehicle
+    pub const IDENTITY: Mounting =
+        Mounting([SensorAxis::PlusX, SensorAxis::PlusY, SensorAxis::PlusZ]);
+
+    /// The sensor axes that the vehicle x, y and z point along. `None` unless they are a
+    /// rotation, each sensor axis used once and right handed, as a mirror image can't be bolted
+    /// on.
+    pub fn new(x: SensorAxis, y: SensorAxis, z: SensorAxis) -> Option<Self> {
+        let [a, b, c] = [x.unit(), y.unit(), z.unit()];
+        let determinant = a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * c[1] - b[1] * c[0]);
+        (determinant == 1.0).then_some(Mounting([x, y, z]))
+    }
+
+    /// The sensor axes that the vehicle x, y and z point along
+    pub fn axes(&self) -> [SensorAxis; 3] {
+        self.0
+    }
+
+    pub fn to_raw(&self) -> [u8; 3] {
+        self.0.map(SensorAxis::to_raw)
+    }
+
+    /// `None` for bytes that aren't a rotation
+    pub fn from_raw(raw: [u8; 3]) -> Option<Self> {
+        Self::new(
+            SensorAxis::from_raw(raw[0])?,
+            SensorAxis::from_raw(raw[1])?,
+            SensorAxis::from_raw(raw[2])?,
+        )
+    }
+
+    /// Rows are the vehicle axes in sensor axes
+    fn rows(&self) -> [[f32; 3]; 3] {
+        self.0.map(SensorAxis::unit)
+    }
+
+    /// A sensor frame vector in the vehicle frame
+    pub fn to_vehicle(&self, v: (f32, f32, f32)) -> (f32, f32, f32) {
+        let m = self.rows();
+        let row = |r: &[f32; 3]| r[0] * v.0 + r[1] * v.1 + r[2] * v.2;
+        (row(&m[0]), row(&m[1]), row(&m[2]))
+    }
+
+    /// A vehicle frame vector in the sensor frame, the rows are orthonormal so this is the
+    /// transpose
+    pub fn to_sensor(&self, v: (f32, f32, f32)) -> (f32, f32, f32) {
+        let m = self.rows();
+        let column = |c: usize| m[0][c] * v.0 + m[1][c] * v.1 + m[2][c] * v.2;
+        (column(0), column(1), column(2))
+    }
+
+    /// What the accelerometer reads with the car level and still, in m/s^2
+    pub fn gravity(&self) -> (f32, f32, f32) {
+        self.to_sensor((0.0, 0.0, GRAVITY))
+    }
+}
+
+impl Default for Mounting {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// Measure the accelerometer and gyro biases at `rate`, with the car level and still, and correct
+/// the accelerometer on the sensor. Returns the biases, the gyro one for [`ImuCorrection::new`].
+pub async fn calibrate_imu<I2C, E, D>(
+    lsm6dso: &mut Lsm6dso<I2C>,
+    delay: &mut D,
+    rate: ImuRate,
+    mounting: &Mounting,
+) -> Result<ImuBias, Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    let (accel_output, gyro_output) = rate.outputs();
+    lsm6dso.set_accelerometer_output(accel_output).await?;
+    lsm6dso.set_gyroscope_output(gyro_output).await?;
+    lsm6dso
+        .calibrate_bias(delay, CALIBRATION_SAMPLES, mounting.gravity())
+        .await
+}
+
+/// Removes the gyro bias from readings and turns them into the vehicle frame
+pub struct ImuCorrection {
+    mounting: Mounting,
+    gyro_bias: GyroBiasEstimator,
+}
+
+impl ImuCorrection {
+    /// Start from the gyro bias of a calibration, or estimate it from scratch for `None`
+    pub fn new(mounting: Mounting, bias: Option<&ImuBias>) -> Self {
+        let gyro_bias = GyroBiasEstimator::new(GYRO_STILL_RATE, GYRO_BIAS_WEIGHT);
+        Self {
+            mounting,
+            gyro_bias: match bias {
+                Some(bias) => gyro_bias.with_bias(bias.gyroscope),
+                None => gyro_bias,
+            },
+        }
+    }
+
+    /// The current gyro bias estimate, sensor frame
+    pub fn gyro_bias(&self) -> (f32, f32, f32) {
+        self.gyro_bias.bias()
+    }
+
+    /// Update the gyro bias estimate with a reading and return it corrected, in the vehicle frame
+    pub fn apply(&mut self, reading: &ImuReading) -> ImuReading {
+        self.gyro_bias.update(reading.gyro);
+        ImuReading {
+            time: reading.time,
+            accel: self.mounting.to_vehicle(reading.accel),
+            gyro: self
+                .mounting
+                .to_vehicle(self.gyro_bias.correct(reading.gyro)),
+        }
+    }
+}
+
+/// Run the accelerometer and gyro at `rate` and batch both into the FIFO.
+///
+/// A timestamp is batched every 8 samples, and the samples between are timed from it. The FIFO
+/// runs in continuous mode, so if it is not drained the oldest samples are overwritten.
+pub async fn start_imu_fifo<I2C, E>(
+    lsm6dso: &mut Lsm6dso<I2C>,
+    rate: ImuRate,
+) -> Result<(), Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let (accel_output, gyro_output) = rate.outputs();
+    lsm6dso.set_accelerometer_output(accel_output).await?;
+    lsm6dso.set_gyroscope_output(gyro_output).await?;
+
+    let (accelerometer_rate, gyroscope_rate) = rate.batch_rates();
+    lsm6dso
+        .configure_fifo(&FifoConfig {
+            mode: FifoMode::Continuous,
+            watermark: rate.fifo_watermark(),
+            stop_on_watermark: false,
+            accelerometer_rate,
+            gyroscope_rate,
+            temperature_rate: TemperatureBatchRate::NotBatched,
+            timestamp_decimation: TimestampDecimation::Every8,
+        })
+        .await
+}
+
+/// Hold INT1 high while the FIFO is at or above its watermark, so a reader can wait on the pin
+/// instead of a timer. Nothing else is routed to INT1.
+pub async fn route_imu_watermark<I2C, E>(lsm6dso: &mut Lsm6dso<I2C>) -> Result<(), Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    lsm6dso
+        .route_interrupts(
+            InterruptPin::Int1,
+            InterruptRoute {
+                fifo_watermark: true,
+                ..Default::default()
+            },
+        )
+        .await
+}
+
+/// Detect impacts and free falls. The events are latched until [`read_imu_events`], so none are
+/// missed between drains.
+pub async fn start_imu_events<I2C, E>(lsm6dso: &mut Lsm6dso<I2C>) -> Result<(), Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    lsm6dso.configure_tap(&IMPACT_DETECTION).await?;
+    lsm6dso.configure_free_fall(&FREE_FALL_DETECTION).await?;
+    lsm6dso.set_latched_interrupts(true).await?;
+    lsm6dso.set_event_detection(true).await
+}
+
+/// The impact and free-fall frames for the events since the last call
+pub async fn read_imu_events<I2C, E>(
+    lsm6dso: &mut Lsm6dso<I2C>,
+    clock: &ImuClock,
+) -> Result<[Option<MsbImuEvent>; 2], Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let sources = lsm6dso.read_event_sources().await?;
+    let (tap, free_fall) = (sources.tap, sources.wake_up.free_fall);
+    if !tap.single && !free_fall {
+        return Ok([None, None]);
+    }
+
+    let time = clock.time_ms(lsm6dso.read_timestamp().await?);
+    let axes = (tap.axes.x as u8)
+        | ((tap.axes.y as u8) << 1)
+        | ((tap.axes.z as u8) << 2)
+        | ((tap.negative as u8) << 3);
+    let event = |event: ImuEvent, axes: u8| MsbImuEvent {
+        event: event.to_raw(),
+        axes,
+        time,
+    };
+    Ok([
+        tap.single.then(|| event(ImuEvent::Impact, axes)),
+        free_fall.then(|| event(ImuEvent::FreeFall, 0)),
+    ])
+}
+
+/// Ties the LSM6DSO timestamp to the MSB clock, so IMU samples are timed like everything else the
+/// MSB sends. Until the first [`ImuClock::sync`] the timestamp is taken as the MSB clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImuClock {
+    tick_ps: u64,
+    ticks: u32,
+    micros: u64,
+}
+
+impl ImuClock {
+    /// A clock counting in ticks of `tick_ns`, see [`Lsm6dso::read_timestamp_tick_ns`]
+    pub fn new(tick_ns: f32) -> Self {
+        Self {
+            tick_ps: (tick_ns * 1000.0) as u64,
+            ticks: 0,
+            micros: 0,
+        }
+    }
+
+    /// Record that the timestamp read `ticks` at `micros` on the MSB clock
+    pub fn sync(&mut self, ticks: u32, micros: u64) {
+        self.ticks = ticks;
+        self.micros = micros;
+    }
+
+    /// MSB time in us of a timestamp. Timestamps up to half the counter range, about 15 hours,
+    /// before the last sync are in the past.
+    pub fn micros(&self, ticks: u32) -> u64 {
+        let ticks = ticks.wrapping_sub(self.ticks) as i32 as i64;
+        let micros = self.micros as i64 + ticks * self.tick_ps as i64 / 1_000_000;
+        micros.max(0) as u64
+    }
+
+    /// MSB time in ms of a timestamp, wrapping at 16 bits like the CAN signals
+    pub fn time_ms(&self, ticks: u32) -> u16 {
+        (self.micros(ticks) / 1000) as u16
+    }
+}
+
+/// Start the LSM6DSO timestamp if it is not running yet, and read how long its ticks are
+pub async fn start_imu_clock<I2C, E>(lsm6dso: &mut Lsm6dso<I2C>) -> Result<ImuClock, Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    lsm6dso.set_timestamp(true).await?;
+    Ok(ImuClock::new(lsm6dso.read_timestamp_tick_ns().await?))
+}
+
+/// Read the timestamp and sync `clock` to `now`, the MSB clock in us. The read takes a while over
+/// I2C, so the timestamp is taken as read halfway through.
+pub async fn sync_imu_clock<I2C, E>(
+    lsm6dso: &mut Lsm6dso<I2C>,
+    clock: &mut ImuClock,
+    mut now: impl FnMut() -> u64,
+) -> Result<(), Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let before = now();
+    let ticks = lsm6dso.read_timestamp().await?;
+    let after = now();
+    clock.sync(ticks, before + (after - before) / 2);
+    Ok(())
+}
+
+/// An accelerometer and gyro sample taken at the same instant
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ImuReading {
+    /// LSM6DSO timestamp in ticks, wrapping, put on the MSB clock by an [`ImuClock`]
+    pub time: u32,
+    /// m/s^2
+    pub accel: (f32, f32, f32),
+    /// rad/s
+    pub gyro: (f32, f32, f32),
+}
+
+impl ImuReading {
+    /// The accel and gyro frames, both carrying the same time on the MSB clock
+    pub fn messages(&self, clock: &ImuClock) -> (MsbAccel, MsbGyro) {
+        let time = clock.time_ms(self.time);
+        (
+            MsbAccel {
+                accel_x: self.accel.0,
+                accel_y: self.accel.1,
+                accel_z: self.accel.2,
+                time,
+            },
+            MsbGyro {
+                gyro_x: self.gyro.0,
+                gyro_y: self.gyro.1,
+                gyro_z: self.gyro.2,
+                time,
+            },
+        )
+    }
+}
+
+/// Pairs up the accelerometer and gyro words of each FIFO batch and times them from the
+/// batched timestamps
+pub struct ImuAligner {
+    period: u32,
+    counter: Option<u8>,
+    accel: Option<(f32, f32, f32)>,
+    gyro: Option<(f32, f32, f32)>,
+    timestamp: Option<u32>,
+    last_time: Option<u32>,
+}
+
+impl ImuAligner {
+    pub const fn new(rate: ImuRate) -> Self {
+        Self {
+            period: rate.period_ticks(),
+            counter: None,
+            accel: None,
+            gyro: None,
+            timestamp: None,
+            last_time: None,
+        }
+    }
+
+    /// Forget the batch in progress and the timing, for after the FIFO overran
+    pub fn reset(&mut self) {
+        self.counter = None;
+        self.accel = None;
+        self.gyro = None;
+        self.timestamp = None;
+        self.last_time = None;
+    }
+
+    /// Add the next FIFO word. A batch is only complete once a word of the next batch arrives, so
+    /// this returns the previous batch, if it had both sensors and a known time.
+    pub fn push(&mut self, record: FifoRecord) -> Option<ImuReading> {
+        let reading = match self.counter {
+            Some(counter) if counter != record.counter => self.finish(),
+            _ => None,
+        };
+        self.counter = Some(record.counter);
+        match record.data {
+            FifoData::Accelerometer(accel) => self.accel = Some(accel),
+            FifoData::Gyroscope(gyro) => self.gyro = Some(gyro),
+            FifoData::Timestamp(time) => self.timestamp = Some(time),
+            FifoData::Temperature(_) | FifoData::Other(_) => (),
+        }
+        reading
+    }
+
+    fn finish(&mut self) -> Option<ImuReading> {
+        // batches between timestamps are a sample period apart
+        let time = self
+            .timestamp
+            .take()
+            .or(self.last_time.map(|last| last.wrapping_add(self.period)));
+        self.last_time = time;
+        let (accel, gyro) = (self.accel.take(), self.gyro.take());
+        Some(ImuReading {
+            time: time?,
+            accel: accel?,
+            gyro: gyro?,
+        })
+    }
+}
+
+/// Read the words waiting in the FIFO into time aligned readings, until `readings` is full.
+///
+/// Returns how many readings were filled and whether the FIFO overran since the last drain.
+/// Readings before the first timestamp have no time and are dropped.
+pub async fn drain_imu<I2C, E>(
+    lsm6dso: &mut Lsm6dso<I2C>,
+    aligner: &mut ImuAligner,
+    readings: &mut [ImuReading],
+) -> Result<(usize, bool), Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let status = lsm6dso.fifo_status().await?;
+    if status.overrun {
+        aligner.reset();
+    }
+
+    let mut count = 0;
+    for _ in 0..status.unread {
+        if count == readings.len() {
+            break;
+        }
+        if let Some(reading) = aligner.push(lsm6dso.read_fifo().await?) {
+            readings[count] = reading;
+            count += 1;
+        }
+    }
+    Ok((count, status.overrun))
+}
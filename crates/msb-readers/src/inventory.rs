@@ -0,0 +1,89 @@
+use embedded_hal_async::i2c::I2c;
+use ner_can_messages::msb::{DevicePresence, MsbInventory};
+use ner_i2c_probe::{Id, Inventory, Presence, Signature};
+
+use crate::{health::Sensor, imu::LSM6DSO_ADDR, temperature::SHT3X_ADDR, tof::VL6180X_ADDR};
+
+/// The sensors an MSB may have fitted, in the order of [`Sensor`]. The SHT30 has no ID register,
+/// so is taken as present if it acknowledges a read status command.
+pub const SIGNATURES: [Signature; 3] = [
+    Signature {
+        name: "sht30",
+        address: SHT3X_ADDR as u8,
+        id: Id::Ack(&[0xF3, 0x2D]),
+    },
+    Signature {
+        name: "lsm6dso",
+        address: LSM6DSO_ADDR,
+        id: Id::Register {
+            register: &[0x0F],
+            value: 0x6C,
+        },
+    },
+    Signature {
+        name: "vl6180x",
+        address: VL6180X_ADDR,
+        id: Id::Register {
+            register: &[0x00, 0x00],
+            value: 0xB4,
+        },
+    },
+];
+
+/// What is fitted on the MSB I2C bus
+pub type MsbDevices = Inventory<3>;
+
+fn index(sensor: Sensor) -> usize {
+    match sensor {
+        Sensor::Temperature => 0,
+        Sensor::Imu => 1,
+        Sensor::Tof => 2,
+    }
+}
+
+pub fn signature(sensor: Sensor) -> &'static Signature {
+    &SIGNATURES[index(sensor)]
+}
+
+/// Scan the bus for the MSB sensors, before any of their drivers are initialized
+pub async fn discover<I2C: I2c>(i2c: &mut I2C) -> Result<MsbDevices, I2C::Error> {
+    ner_i2c_probe::discover(i2c, &SIGNATURES).await
+}
+
+pub fn presence(devices: &MsbDevices, sensor: Sensor) -> Presence {
+    devices.devices[index(sensor)]
+}
+
+fn device_presence(presence: Presence) -> DevicePresence {
+    match presence {
+        Presence::Absent => DevicePresence::Absent,
+        Presence::Present => DevicePresence::Present,
+        Presence::WrongId(_) => DevicePresence::WrongDevice,
+    }
+}
+
+/// The ID read from a sensor's ID register, 0 if nothing answered
+fn id(presence: Presence, sensor: Sensor) -> u8 {
+    match (presence, signature(sensor).id) {
+        (Presence::WrongId(read), _) => read,
+        (Presence::Present, Id::Register { value, .. }) => value,
+        _ => 0,
+    }
+}
+
+pub fn inventory_message(devices: &MsbDevices) -> MsbInventory {
+    let mut other = devices.unknown.iter();
+    let mut next_other = || other.next().unwrap_or(0);
+    MsbInventory {
+        temperature: device_presence(presence(devices, Sensor::Temperature)).to_raw(),
+        imu: device_presence(presence(devices, Sensor::Imu)).to_raw(),
+        tof: device_presence(presence(devices, Sensor::Tof)).to_raw(),
+        imu_id: id(presence(devices, Sensor::Imu), Sensor::Imu),
+        tof_id: id(presence(devices, Sensor::Tof), Sensor::Tof),
+        devices: devices.addresses.len() as u8,
+        other_1: next_other(),
+        other_2: next_other(),
+        other_3: next_other(),
+        other_4: next_other(),
+    }
+}
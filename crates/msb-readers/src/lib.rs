@@ -0,0 +1,17 @@
+#![no_std]
+//! Hardware agnostic sensor reading logic for the MSB
+//!
+//! Every reader here only depends on embedded hal traits, so the exact CAN payloads the MSB sends
+//! can be produced (and tested) on the host against the simulated devices in [`sim`].
+//! `msb-fw-rs` owns the timing and the embassy specific plumbing, within the limits in [`config`].
+
+pub mod adc;
+pub mod bus;
+pub mod capture;
+pub mod config;
+pub mod health;
+pub mod imu;
+pub mod inventory;
+pub mod sim;
+pub mod temperature;
+pub mod tof;
@@ -0,0 +1,124 @@
+//! Simulated MSB I2C bus and sensor register models, for running the readers on the host
+//!
+//! Each device only models the registers and commands the drivers actually use.
+
+mod lsm6dso;
+mod sht3x;
+mod vl6180x;
+
+pub use lsm6dso::{SimLsm6dso, SIM_FIFO_DEPTH};
+pub use sht3x::SimSht3x;
+pub use vl6180x::SimVl6180x;
+
+use embedded_hal::i2c::{ErrorKind, ErrorType, NoAcknowledgeSource, Operation};
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+
+/// Errors a simulated bus can return
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimError {
+    /// No simulated device is fitted at the address
+    NoDevice(u8),
+    /// The device does not understand what was written to it
+    InvalidWrite,
+    /// The device NACKed a read as it has nothing to send
+    NoData,
+    /// More than one device answered the address
+    Contention(u8),
+}
+
+impl embedded_hal::i2c::Error for SimError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SimError::NoDevice(_) => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            SimError::InvalidWrite => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            SimError::NoData => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            SimError::Contention(_) => ErrorKind::Bus,
+        }
+    }
+}
+
+/// A simulated I2C target
+pub trait SimDevice {
+    /// Handle a write transfer addressed to the device
+    fn write(&mut self, data: &[u8]) -> Result<(), SimError>;
+    /// Handle a read transfer addressed to the device
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), SimError>;
+}
+
+/// The I2C3 bus of an MSB, with any combination of its sensors fitted
+#[derive(Default)]
+pub struct SimBus {
+    pub sht3x: Option<SimSht3x>,
+    pub lsm6dso: Option<SimLsm6dso>,
+    pub vl6180x: Option<SimVl6180x>,
+    /// Further VL6180X, for boards with more than one ToF, each held in shutdown until its
+    /// XSHUT is driven high
+    pub more_vl6180x: [Option<SimVl6180x>; 2],
+}
+
+impl SimBus {
+    /// A bus with every MSB sensor fitted in its power on state
+    pub fn new() -> Self {
+        Self {
+            sht3x: Some(SimSht3x::new()),
+            lsm6dso: Some(SimLsm6dso::new()),
+            vl6180x: Some(SimVl6180x::new()),
+            more_vl6180x: Default::default(),
+        }
+    }
+
+    fn device(&mut self, address: u8) -> Result<&mut dyn SimDevice, SimError> {
+        let sht3x = self.sht3x.as_mut().filter(|d| d.address() == address);
+        let lsm6dso = self.lsm6dso.as_mut().filter(|d| d.address() == address);
+        let vl6180x = self
+            .vl6180x
+            .iter_mut()
+            .chain(self.more_vl6180x.iter_mut().flatten())
+            .filter(|d| d.answers(address));
+        let mut devices = sht3x
+            .into_iter()
+            .map(|d| d as &mut dyn SimDevice)
+            .chain(lsm6dso.into_iter().map(|d| d as &mut dyn SimDevice))
+            .chain(vl6180x.map(|d| d as &mut dyn SimDevice));
+        let dev = devices.next().ok_or(SimError::NoDevice(address))?;
+        if devices.next().is_some() {
+            return Err(SimError::Contention(address));
+        }
+        Ok(dev)
+    }
+}
+
+impl ErrorType for SimBus {
+    type Error = SimError;
+}
+
+impl I2c for SimBus {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let dev = self.device(address)?;
+        for op in operations {
+            match op {
+                // only the address, as sent by a bus scan
+                Operation::Write([]) => {}
+                Operation::Write(data) => dev.write(data)?,
+                Operation::Read(buf) => dev.read(buf)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A delay that returns immediately, but keeps count of the time that should have passed
+#[derive(Default)]
+pub struct SimDelay {
+    pub elapsed_ns: u64,
+}
+
+impl DelayNs for SimDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.elapsed_ns += ns as u64;
+    }
+}
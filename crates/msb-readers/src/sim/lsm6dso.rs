@@ -0,0 +1,219 @@
+use super::{SimDevice, SimError};
+use crate::imu::LSM6DSO_ADDR;
+
+const FUNC_CFG_ACCESS: usize = 0x01;
+const WHO_AM_I: usize = 0x0F;
+const CTRL3_C: usize = 0x12;
+const CTRL5_C: usize = 0x14;
+const OUTX_L_G: usize = 0x22;
+const OUTX_L_A: usize = 0x28;
+const OUTX_L_A_END: usize = 0x2E;
+const FIFO_CTRL4: usize = 0x0A;
+const FIFO_STATUS1: usize = 0x3A;
+const FIFO_STATUS2: usize = 0x3B;
+const FIFO_DATA_OUT_TAG: usize = 0x78;
+const FIFO_DATA_OUT_Z_H: usize = 0x7E;
+
+/// Words the simulated FIFO holds, far fewer than the real one
+pub const SIM_FIFO_DEPTH: usize = 64;
+
+/// Simulated LSM6DSO, a flat register file with the WHO_AM_I and auto increment behaviour, a
+/// second file for the embedded functions page, and a FIFO that the test fills with words directly
+pub struct SimLsm6dso {
+    pub regs: [u8; 0x80],
+    pub embedded_regs: [u8; 0x80],
+    /// Raw change of every accelerometer axis while the self-test is on, positive direction
+    pub self_test_accel_raw: i16,
+    /// Raw change of every gyroscope axis while the self-test is on, positive direction
+    pub self_test_gyro_raw: i16,
+    pointer: usize,
+    fifo: [[u8; 7]; SIM_FIFO_DEPTH],
+    fifo_head: usize,
+    fifo_len: usize,
+    fifo_overrun: bool,
+}
+
+impl Default for SimLsm6dso {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimLsm6dso {
+    /// Register reset values from the datasheet for the registers the driver touches
+    pub fn new() -> Self {
+        let mut regs = [0u8; 0x80];
+        regs[WHO_AM_I] = 0x6C;
+        regs[CTRL3_C] = 0x04;
+        Self {
+            regs,
+            embedded_regs: [0u8; 0x80],
+            // 500 mg at +-4 g and 300 dps at 2000 dps, well within the limits
+            self_test_accel_raw: 4098,
+            self_test_gyro_raw: 4286,
+            pointer: 0,
+            fifo: [[0; 7]; SIM_FIFO_DEPTH],
+            fifo_head: 0,
+            fifo_len: 0,
+            fifo_overrun: false,
+        }
+    }
+
+    pub fn address(&self) -> u8 {
+        LSM6DSO_ADDR
+    }
+
+    /// Set the raw accelerometer output registers
+    pub fn set_accel_raw(&mut self, x: i16, y: i16, z: i16) {
+        self.set_axes(OUTX_L_A, [x, y, z]);
+    }
+
+    /// Set the raw gyroscope output registers
+    pub fn set_gyro_raw(&mut self, x: i16, y: i16, z: i16) {
+        self.set_axes(OUTX_L_G, [x, y, z]);
+    }
+
+    fn set_axes(&mut self, start: usize, axes: [i16; 3]) {
+        self.regs[start..start + 6].copy_from_slice(&Self::axes_bytes(axes));
+    }
+
+    /// Batch a raw accelerometer word into the FIFO, `counter` is the two bit batch counter
+    pub fn push_fifo_accel_raw(&mut self, counter: u8, x: i16, y: i16, z: i16) {
+        self.push_fifo(0x02, counter, Self::axes_bytes([x, y, z]));
+    }
+
+    /// Batch a raw gyroscope word into the FIFO
+    pub fn push_fifo_gyro_raw(&mut self, counter: u8, x: i16, y: i16, z: i16) {
+        self.push_fifo(0x01, counter, Self::axes_bytes([x, y, z]));
+    }
+
+    /// Batch a timestamp word into the FIFO
+    pub fn push_fifo_timestamp(&mut self, counter: u8, ticks: u32) {
+        let mut data = [0u8; 6];
+        data[..4].copy_from_slice(&ticks.to_le_bytes());
+        self.push_fifo(0x04, counter, data);
+    }
+
+    /// Batch a word with any tag, overwriting the oldest word when full like continuous mode
+    pub fn push_fifo(&mut self, tag: u8, counter: u8, data: [u8; 6]) {
+        if self.fifo_len == SIM_FIFO_DEPTH {
+            self.fifo_head = (self.fifo_head + 1) % SIM_FIFO_DEPTH;
+            self.fifo_len -= 1;
+            self.fifo_overrun = true;
+        }
+        let word = &mut self.fifo[(self.fifo_head + self.fifo_len) % SIM_FIFO_DEPTH];
+        word[0] = (tag << 3) | ((counter & 0b11) << 1);
+        word[1..].copy_from_slice(&data);
+        self.fifo_len += 1;
+    }
+
+    /// Words waiting in the FIFO
+    pub fn fifo_len(&self) -> usize {
+        self.fifo_len
+    }
+
+    fn axes_bytes(axes: [i16; 3]) -> [u8; 6] {
+        let mut data = [0u8; 6];
+        for (i, axis) in axes.iter().enumerate() {
+            data[2 * i..2 * i + 2].copy_from_slice(&axis.to_le_bytes());
+        }
+        data
+    }
+
+    /// The register file the pointer addresses, FUNC_CFG_ACCESS is on both pages
+    fn page(&mut self) -> &mut [u8; 0x80] {
+        if self.regs[FUNC_CFG_ACCESS] & 0x80 != 0 && self.pointer != FUNC_CFG_ACCESS {
+            &mut self.embedded_regs
+        } else {
+            &mut self.regs
+        }
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        if self.regs[FUNC_CFG_ACCESS] & 0x80 != 0 {
+            let pointer = self.pointer;
+            return self.page()[pointer];
+        }
+        match self.pointer {
+            FIFO_STATUS1 => self.fifo_len as u8,
+            FIFO_STATUS2 => {
+                let status = ((self.fifo_len >> 8) as u8 & 0b11)
+                    | ((self.fifo_overrun as u8) << 6)
+                    | (((self.fifo_len == SIM_FIFO_DEPTH) as u8) << 5);
+                self.fifo_overrun = false;
+                status
+            }
+            FIFO_DATA_OUT_TAG..=FIFO_DATA_OUT_Z_H if self.fifo_len == 0 => 0,
+            FIFO_DATA_OUT_TAG..=FIFO_DATA_OUT_Z_H => {
+                let byte = self.fifo[self.fifo_head][self.pointer - FIFO_DATA_OUT_TAG];
+                // the word is popped once its last byte is read
+                if self.pointer == FIFO_DATA_OUT_Z_H {
+                    self.fifo_head = (self.fifo_head + 1) % SIM_FIFO_DEPTH;
+                    self.fifo_len -= 1;
+                }
+                byte
+            }
+            reg @ OUTX_L_G..OUTX_L_A_END => self.output_byte(reg),
+            reg => self.regs[reg],
+        }
+    }
+
+    /// An output register byte, moved by the self-test response if it is on
+    fn output_byte(&self, reg: usize) -> u8 {
+        let ctrl5 = self.regs[CTRL5_C];
+        let (start, shift) = if reg < OUTX_L_A {
+            let shift = match (ctrl5 >> 2) & 0b11 {
+                0b01 => self.self_test_gyro_raw,
+                0b11 => -self.self_test_gyro_raw,
+                _ => 0,
+            };
+            (OUTX_L_G, shift)
+        } else {
+            let shift = match ctrl5 & 0b11 {
+                0b01 => self.self_test_accel_raw,
+                0b10 => -self.self_test_accel_raw,
+                _ => 0,
+            };
+            (OUTX_L_A, shift)
+        };
+        let low = start + (reg - start) / 2 * 2;
+        let value = i16::from_le_bytes([self.regs[low], self.regs[low + 1]]).wrapping_add(shift);
+        value.to_le_bytes()[reg - low]
+    }
+
+    fn advance(&mut self) {
+        if self.regs[CTRL3_C] & 0x04 != 0 {
+            self.pointer = (self.pointer + 1) % self.regs.len();
+        }
+    }
+}
+
+impl SimDevice for SimLsm6dso {
+    fn write(&mut self, data: &[u8]) -> Result<(), SimError> {
+        let (reg, values) = data.split_first().ok_or(SimError::InvalidWrite)?;
+        self.pointer = *reg as usize;
+        if self.pointer >= self.regs.len() {
+            return Err(SimError::InvalidWrite);
+        }
+        for value in values {
+            let pointer = self.pointer;
+            self.page()[pointer] = *value;
+            // bypass mode empties the FIFO
+            let main_page = self.regs[FUNC_CFG_ACCESS] & 0x80 == 0;
+            if main_page && self.pointer == FIFO_CTRL4 && value & 0b111 == 0 {
+                self.fifo_len = 0;
+                self.fifo_overrun = false;
+            }
+            self.advance();
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), SimError> {
+        for byte in buf {
+            *byte = self.read_byte();
+            self.advance();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use super::{SimDevice, SimError};
+use crate::temperature::SHT3X_ADDR;
+
+/// Simulated SHT3x, responding to single shot, periodic, status, heater and alert limit commands
+pub struct SimSht3x {
+    /// Raw temperature ticks, T = -45 + 175 * raw / 65535
+    pub raw_temperature: u16,
+    /// Raw humidity ticks, RH = 100 * raw / 65535
+    pub raw_humidity: u16,
+    pub status: u16,
+    /// Periodic data acquisition is running
+    pub periodic: bool,
+    /// A periodic measurement is waiting to be fetched, set by tests in place of the sensor's
+    /// own clock
+    pub data_ready: bool,
+    /// Alert limit words, high set, high clear, low clear then low set
+    pub alert_limits: [u16; 4],
+    pending: Option<[u8; 6]>,
+    pending_len: usize,
+}
+
+impl Default for SimSht3x {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimSht3x {
+    /// 25 degC, 50 %RH
+    pub fn new() -> Self {
+        Self {
+            raw_temperature: 0x6666,
+            raw_humidity: 0x8000,
+            status: 0,
+            periodic: false,
+            data_ready: false,
+            alert_limits: [0xCD33, 0xC92D, 0x3869, 0x3466],
+            pending: None,
+            pending_len: 0,
+        }
+    }
+
+    pub fn address(&self) -> u8 {
+        SHT3X_ADDR as u8
+    }
+
+    /// Set the reading from physical units
+    pub fn set_measurement(&mut self, temperature_c: f32, humidity_rh: f32) {
+        self.raw_temperature = ((temperature_c + 45.0) * 65535.0 / 175.0) as u16;
+        self.raw_humidity = (humidity_rh * 65535.0 / 100.0) as u16;
+    }
+
+    fn respond(&mut self, words: &[u16]) {
+        let mut buf = [0u8; 6];
+        for (word, chunk) in words.iter().zip(buf.chunks_mut(3)) {
+            let bytes = word.to_be_bytes();
+            chunk[..2].copy_from_slice(&bytes);
+            chunk[2] = crc8(bytes);
+        }
+        self.pending = Some(buf);
+        self.pending_len = words.len() * 3;
+    }
+}
+
+impl SimDevice for SimSht3x {
+    fn write(&mut self, data: &[u8]) -> Result<(), SimError> {
+        // alert limit writes carry a data word and its CRC
+        if let [0x61, lo, msb, lsb, crc] = data {
+            let index = alert_limit_index(*lo).ok_or(SimError::InvalidWrite)?;
+            if crc8([*msb, *lsb]) == *crc {
+                self.alert_limits[index] = u16::from_be_bytes([*msb, *lsb]);
+                self.status &= !STATUS_WRITE_CHECKSUM;
+            } else {
+                self.status |= STATUS_WRITE_CHECKSUM;
+            }
+            return Ok(());
+        }
+        let [hi, lo] = data else {
+            return Err(SimError::InvalidWrite);
+        };
+        match u16::from_be_bytes([*hi, *lo]) {
+            // single shot, any clock stretch or repeatability, not accepted while periodic
+            0x2C06 | 0x2C0D | 0x2C10 | 0x2400 | 0x240B | 0x2416 if !self.periodic => {
+                self.respond(&[self.raw_temperature, self.raw_humidity])
+            }
+            // periodic at any rate or repeatability, or ART
+            0x2032 | 0x2024 | 0x202F | 0x2130 | 0x2126 | 0x212D | 0x2236 | 0x2220 | 0x222B
+            | 0x2334 | 0x2322 | 0x2329 | 0x2737 | 0x2721 | 0x272A | 0x2B32 => {
+                self.periodic = true;
+                self.data_ready = false;
+            }
+            // fetch data, the read is NACKed if nothing was measured since the last fetch
+            0xE000 => {
+                self.pending = None;
+                if self.periodic && self.data_ready {
+                    self.data_ready = false;
+                    self.respond(&[self.raw_temperature, self.raw_humidity]);
+                }
+            }
+            // break
+            0x3093 => self.periodic = false,
+            // status
+            0xF32D => self.respond(&[self.status]),
+            // clear status, the heater bit shows the heater state so it stays
+            0x3041 => self.status &= STATUS_HEATER,
+            // heater enable and disable
+            0x306D => self.status |= STATUS_HEATER,
+            0x3066 => self.status &= !STATUS_HEATER,
+            // read alert limit
+            0xE11F | 0xE114 | 0xE109 | 0xE102 => {
+                let index = alert_limit_index(*lo).ok_or(SimError::InvalidWrite)?;
+                self.respond(&[self.alert_limits[index]]);
+            }
+            // soft reset
+            0x30A2 => {
+                *self = Self {
+                    raw_temperature: self.raw_temperature,
+                    raw_humidity: self.raw_humidity,
+                    ..Self::new()
+                }
+            }
+            _ => return Err(SimError::InvalidWrite),
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), SimError> {
+        // the real sensor NACKs a read with no data ready
+        let data = self.pending.take().ok_or(SimError::NoData)?;
+        let len = buf.len().min(self.pending_len);
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(())
+    }
+}
+
+/// Status register heater bit
+const STATUS_HEATER: u16 = 1 << 13;
+/// Status register bit set when a write had a bad CRC
+const STATUS_WRITE_CHECKSUM: u16 = 1 << 0;
+
+/// Index into [`SimSht3x::alert_limits`] from the low byte of a read or write limit command
+fn alert_limit_index(lo: u8) -> Option<usize> {
+    match lo {
+        0x1F | 0x1D => Some(0),
+        0x14 | 0x16 => Some(1),
+        0x09 | 0x0B => Some(2),
+        0x02 | 0x00 => Some(3),
+        _ => None,
+    }
+}
+
+/// CRC-8, polynomial 0x31, init 0xFF, as used on every SHT3x data word
+fn crc8(data: [u8; 2]) -> u8 {
+    let mut crc: u8 = 0xff;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            if crc & 0x80 > 0 {
+                crc = (crc << 1) ^ 0x31;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
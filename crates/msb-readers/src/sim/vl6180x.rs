@@ -0,0 +1,213 @@
+use super::{SimDevice, SimError};
+
+const MODEL_ID: usize = 0x000;
+const SYSTEM_MODE_GPIO1: usize = 0x011;
+const SYSTEM_INTERRUPT_CONFIG_GPIO: usize = 0x014;
+const SYSTEM_INTERRUPT_CLEAR: usize = 0x015;
+const SYSTEM_FRESH_OUT_OF_RESET: usize = 0x016;
+const SYSRANGE_START: usize = 0x018;
+const SYSRANGE_INTERMEASUREMENT_PERIOD: usize = 0x01B;
+const SYSRANGE_CROSSTALK_COMPENSATION_RATE: usize = 0x01E;
+const SYSRANGE_PART_TO_PART_RANGE_OFFSET: usize = 0x024;
+const SYSALS_START: usize = 0x038;
+const RESULT_RANGE_STATUS: usize = 0x04D;
+const RESULT_ALS_STATUS: usize = 0x04E;
+const RESULT_INTERRUPT_STATUS_GPIO: usize = 0x04F;
+const RESULT_ALS_VAL: usize = 0x050;
+const RESULT_RANGE_VAL: usize = 0x062;
+const RESULT_RANGE_RAW: usize = 0x064;
+const RESULT_RANGE_RETURN_RATE: usize = 0x066;
+const RESULT_RANGE_RETURN_SIGNAL_COUNT: usize = 0x06C;
+const RESULT_RANGE_RETURN_AMB_COUNT: usize = 0x074;
+const RESULT_RANGE_RETURN_CONV_TIME: usize = 0x07C;
+const I2C_SLAVE_DEVICE_ADDRESS: usize = 0x212;
+
+const DEFAULT_ADDRESS: u8 = 0x29;
+
+/// Simulated VL6180X with 16 bit register addressing.
+///
+/// Starting a range or ambient measurement completes it immediately with the configured result.
+/// Continuous ranging measures once every [`range_period_elapsed`](Self::range_period_elapsed).
+/// The part-to-part offset is added to every range, crosstalk compensation is not modelled.
+/// Driving XSHUT low with [`set_xshut`](Self::set_xshut) shuts it down, and it boots again in its
+/// power on state, back at the default address.
+pub struct SimVl6180x {
+    pub regs: [u8; 0x300],
+    /// Raw RESULT__RANGE_VAL returned by the next range measurement
+    pub range_raw: u8,
+    /// Error code (bits 7:4 of RESULT__RANGE_STATUS) returned by the next range measurement
+    pub range_error: u8,
+    /// RESULT__RANGE_RETURN_RATE of the next range measurement, in Mcps 9.7 fixed point
+    pub return_rate: u16,
+    /// RESULT__RANGE_RETURN_SIGNAL_COUNT of the next range measurement
+    pub return_signal_count: u32,
+    /// RESULT__RANGE_RETURN_AMB_COUNT of the next range measurement
+    pub return_ambient_count: u32,
+    /// RESULT__RANGE_RETURN_CONV_TIME of the next range measurement, in us
+    pub return_conv_time_us: u32,
+    /// Raw RESULT__ALS_VAL returned by the next ambient measurement
+    pub ambient_raw: u16,
+    continuous: bool,
+    powered: bool,
+    pointer: usize,
+}
+
+impl Default for SimVl6180x {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimVl6180x {
+    /// Freshly booted sensor at the default address, measuring 100 mm
+    pub fn new() -> Self {
+        let mut regs = [0u8; 0x300];
+        regs[MODEL_ID] = 0xB4;
+        regs[SYSTEM_FRESH_OUT_OF_RESET] = 0x01;
+        regs[I2C_SLAVE_DEVICE_ADDRESS] = DEFAULT_ADDRESS;
+        Self {
+            regs,
+            range_raw: 100,
+            range_error: 0,
+            return_rate: 0,
+            return_signal_count: 0,
+            return_ambient_count: 0,
+            return_conv_time_us: 0,
+            ambient_raw: 0,
+            continuous: false,
+            powered: true,
+            pointer: 0,
+        }
+    }
+
+    pub fn address(&self) -> u8 {
+        self.regs[I2C_SLAVE_DEVICE_ADDRESS]
+    }
+
+    /// Whether the sensor answers at `address`, it doesn't while shut down
+    pub fn answers(&self, address: u8) -> bool {
+        self.powered && self.address() == address
+    }
+
+    /// Drive XSHUT, low shuts the sensor down and high boots it again, losing every register
+    /// written since it last booted
+    pub fn set_xshut(&mut self, high: bool) {
+        if high && !self.powered {
+            self.regs = Self::new().regs;
+            self.continuous = false;
+            self.pointer = 0;
+        }
+        self.powered = high;
+    }
+
+    /// SYSRANGE__PART_TO_PART_RANGE_OFFSET, in range scaling units
+    pub fn range_offset(&self) -> i8 {
+        self.regs[SYSRANGE_PART_TO_PART_RANGE_OFFSET] as i8
+    }
+
+    /// SYSRANGE__CROSSTALK_COMPENSATION_RATE, in Mcps 9.7 fixed point
+    pub fn crosstalk_rate(&self) -> u16 {
+        u16::from_be_bytes([
+            self.regs[SYSRANGE_CROSSTALK_COMPENSATION_RATE],
+            self.regs[SYSRANGE_CROSSTALK_COMPENSATION_RATE + 1],
+        ])
+    }
+
+    /// Whether continuous ranging is running
+    pub fn ranging(&self) -> bool {
+        self.continuous
+    }
+
+    /// Time between continuous range measurements in ms
+    pub fn range_period_ms(&self) -> u16 {
+        (self.regs[SYSRANGE_INTERMEASUREMENT_PERIOD] as u16 + 1) * 10
+    }
+
+    /// Finish a continuous range measurement, as the sensor does once every period
+    pub fn range_period_elapsed(&mut self) {
+        if self.continuous {
+            self.finish_range();
+        }
+    }
+
+    /// Level of GPIO1 when it is the interrupt output, active while a range interrupt that is
+    /// enabled waits to be cleared
+    pub fn gpio1(&self) -> bool {
+        let mode = self.regs[SYSTEM_MODE_GPIO1];
+        let enabled = self.regs[SYSTEM_INTERRUPT_CONFIG_GPIO] & 0b111 != 0;
+        let pending = self.regs[RESULT_INTERRUPT_STATUS_GPIO] & 0b111 != 0;
+        let active = mode & 0x10 != 0 && enabled && pending;
+        let active_high = mode & 0x20 != 0;
+        active == active_high
+    }
+
+    fn finish_range(&mut self) {
+        self.regs[RESULT_RANGE_STATUS] = (self.range_error << 4) | 0x01;
+        let offset = self.regs[SYSRANGE_PART_TO_PART_RANGE_OFFSET] as i8;
+        self.regs[RESULT_RANGE_VAL] = self.range_raw.saturating_add_signed(offset);
+        self.regs[RESULT_RANGE_RAW] = self.range_raw;
+        self.regs[RESULT_RANGE_RETURN_RATE..RESULT_RANGE_RETURN_RATE + 2]
+            .copy_from_slice(&self.return_rate.to_be_bytes());
+        for (reg, value) in [
+            (RESULT_RANGE_RETURN_SIGNAL_COUNT, self.return_signal_count),
+            (RESULT_RANGE_RETURN_AMB_COUNT, self.return_ambient_count),
+            (RESULT_RANGE_RETURN_CONV_TIME, self.return_conv_time_us),
+        ] {
+            self.regs[reg..reg + 4].copy_from_slice(&value.to_be_bytes());
+        }
+        self.regs[RESULT_INTERRUPT_STATUS_GPIO] |= 0b00_000_100;
+    }
+
+    fn write_register(&mut self, reg: usize, value: u8) {
+        match reg {
+            SYSTEM_INTERRUPT_CLEAR => {
+                let mut clear = 0;
+                if value & 0b001 != 0 {
+                    clear |= 0b00_000_111;
+                }
+                if value & 0b010 != 0 {
+                    clear |= 0b00_111_000;
+                }
+                if value & 0b100 != 0 {
+                    clear |= 0b11_000_000;
+                }
+                self.regs[RESULT_INTERRUPT_STATUS_GPIO] &= !clear;
+            }
+            // continuous mode start and stop are the same write
+            SYSRANGE_START if value & 0x03 == 0x03 => self.continuous = !self.continuous,
+            SYSRANGE_START if value & 0x01 != 0 => self.finish_range(),
+            SYSALS_START if value & 0x01 != 0 => {
+                self.regs[RESULT_ALS_STATUS] = 0x01;
+                self.regs[RESULT_ALS_VAL..RESULT_ALS_VAL + 2]
+                    .copy_from_slice(&self.ambient_raw.to_be_bytes());
+                self.regs[RESULT_INTERRUPT_STATUS_GPIO] |= 0b00_100_000;
+            }
+            _ => self.regs[reg] = value,
+        }
+    }
+}
+
+impl SimDevice for SimVl6180x {
+    fn write(&mut self, data: &[u8]) -> Result<(), SimError> {
+        let [hi, lo, values @ ..] = data else {
+            return Err(SimError::InvalidWrite);
+        };
+        self.pointer = u16::from_be_bytes([*hi, *lo]) as usize;
+        for value in values {
+            if self.pointer >= self.regs.len() {
+                return Err(SimError::InvalidWrite);
+            }
+            self.write_register(self.pointer, *value);
+            self.pointer += 1;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), SimError> {
+        for byte in buf {
+            *byte = *self.regs.get(self.pointer).ok_or(SimError::InvalidWrite)?;
+            self.pointer += 1;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+use ner_can_messages::msb::MsbTemperature;
+use sht3x_ner::{
+    Address, AlertLimit, AlertLimits, ClockStretch, CondensationRecovery, Error, Measurement,
+    Periodic, Rate, Repeatability, Sht3x,
+};
+
+/// The SHT30 on the MSB has its address pin held high
+pub const SHT3X_ADDR: Address = Address::High;
+
+/// The SHT30 ALERT pin flags the air near the brakes and uprights going over 80 degC, clearing
+/// below 75 degC. Humidity is left as wide as the limits go, so it only alerts once saturated.
+pub const TEMPERATURE_ALERT: AlertLimits = AlertLimits {
+    high_set: AlertLimit {
+        temperature: 8000,
+        humidity: 10000,
+    },
+    high_clear: AlertLimit {
+        temperature: 7500,
+        humidity: 10000,
+    },
+    low_clear: AlertLimit {
+        temperature: -4500,
+        humidity: 0,
+    },
+    low_set: AlertLimit {
+        temperature: -4500,
+        humidity: 0,
+    },
+};
+
+/// Humidity that starts the heater to dry out condensation, in centi %RH
+pub const CONDENSATION_HUMIDITY: u16 = 9500;
+
+/// How long the heater runs for each condensation recovery, in ms
+pub const HEATER_ON_MS: u64 = 30_000;
+
+/// How long the SHT30 is left to cool after heating before its readings are sent again, in ms
+pub const HEATER_COOL_MS: u64 = 30_000;
+
+/// Condensation recovery for the MSB SHT30
+pub const fn condensation_recovery() -> CondensationRecovery {
+    CondensationRecovery::new(CONDENSATION_HUMIDITY, HEATER_ON_MS, HEATER_COOL_MS)
+}
+
+/// Create the SHT30 driver for the MSB
+pub fn init_temperature<I2C: I2c>(i2c: I2C) -> Sht3x<I2C> {
+    Sht3x::new(i2c, SHT3X_ADDR)
+}
+
+/// Take a single shot measurement, returning the measurement and its CAN message
+pub async fn read_temperature<I2C, E, D>(
+    sht30: &mut Sht3x<I2C>,
+    delay: &mut D,
+) -> Result<(Measurement, MsbTemperature), Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    let res = sht30
+        .measure(ClockStretch::Disabled, Repeatability::High, delay)
+        .await?;
+    let msg = temperature_message(&res);
+    Ok((res, msg))
+}
+
+/// Periodic rates from slowest to fastest
+const RATES: [Rate; 5] = [Rate::R0_5, Rate::R1, Rate::R2, Rate::R4, Rate::R10];
+
+/// The slowest periodic rate that still measures between two fetches `refresh_ms` apart, or the
+/// fastest rate for refresh times below its period
+pub fn periodic_rate(refresh_ms: u16) -> Rate {
+    RATES
+        .into_iter()
+        .find(|rate| rate.period_ms() < refresh_ms)
+        .unwrap_or(Rate::R10)
+}
+
+/// Program the alert limits, make sure the heater is off, then start the SHT30 measuring at `rate`.
+///
+/// The heater may still be on after a re-init. Measuring on its own means reading it never
+/// waits. On failure the driver is handed back still in single shot mode.
+pub async fn start_temperature<I2C, E, D>(
+    mut sht30: Sht3x<I2C>,
+    rate: Rate,
+    delay: &mut D,
+) -> Result<Sht3x<I2C, Periodic>, (Sht3x<I2C>, Error<E>)>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    if let Err(err) = sht30.set_alert_limits(&TEMPERATURE_ALERT, delay).await {
+        return Err((sht30, err));
+    }
+    if let Err(err) = sht30.set_heater(false, delay).await {
+        return Err((sht30, err));
+    }
+    sht30.start_periodic(rate, Repeatability::High, delay).await
+}
+
+/// Fetch the latest periodic measurement and its CAN message, `None` if the SHT30 has not
+/// measured since the last fetch
+pub async fn fetch_temperature<I2C, E>(
+    sht30: &mut Sht3x<I2C, Periodic>,
+) -> Result<Option<(Measurement, MsbTemperature)>, Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let res = sht30.fetch().await?;
+    Ok(res.map(|res| {
+        let msg = temperature_message(&res);
+        (res, msg)
+    }))
+}
+
+/// Convert the centi degC and centi %RH measurement to the CAN message
+pub fn temperature_message(res: &Measurement) -> MsbTemperature {
+    MsbTemperature {
+        temperature: res.temperature as f32 / 100.0,
+        humidity: res.humidity as f32 / 100.0,
+    }
+}
@@ -0,0 +1,139 @@
+use core::convert::Infallible;
+
+use embedded_hal_async::{delay::DelayNs, digital::Wait, i2c::I2c};
+use ner_can_messages::msb::{MsbTof, TofQuality};
+use vl6180x_ner::{
+    AmbientInterruptMode, Config, Error, RangeContinuousMode, RangeInterruptMode, RangeMeasurement,
+    RangeQuality, ReadyMode, VL6180X,
+};
+
+/// The VL6180X powers up at this address
+pub const VL6180X_ADDR: u8 = 0x29;
+
+/// Shortest continuous ranging period in ms, a range with the default 49 ms max convergence time
+/// must fit in 90% of it
+pub const CONTINUOUS_MIN_MS: u16 = 60;
+
+/// Longest continuous ranging period the VL6180X can be set to, in ms
+pub const CONTINUOUS_MAX_MS: u16 = 2550;
+
+/// Interrupt status polls per continuous ranging period while waiting for a sample
+pub const TOF_POLLS_PER_PERIOD: u16 = 4;
+
+/// Create and initialize the VL6180X driver at its default address
+pub async fn init_tof<I2C, E>(i2c: I2C) -> Result<VL6180X<ReadyMode, I2C>, Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    VL6180X::new(i2c).await
+}
+
+/// The [`TofQuality`] sent on CAN for the quality of a range
+pub const fn tof_quality(quality: RangeQuality) -> TofQuality {
+    match quality {
+        RangeQuality::Valid => TofQuality::Valid,
+        RangeQuality::NoTarget => TofQuality::NoTarget,
+        RangeQuality::Noisy => TofQuality::Noisy,
+        RangeQuality::TooClose => TofQuality::TooClose,
+        RangeQuality::TooFar => TofQuality::TooFar,
+        RangeQuality::SensorFault => TofQuality::SensorFault,
+    }
+}
+
+/// CAN message of a range measurement, sent whatever its quality so a receiver can tell no
+/// target from a missing sample
+pub fn tof_message(measurement: &RangeMeasurement) -> MsbTof {
+    MsbTof {
+        range: measurement.range_mm,
+        quality: tof_quality(measurement.quality()).to_raw(),
+        confidence: measurement.confidence(),
+    }
+}
+
+/// Take a single range measurement, polling for it with `delay`, returning the measurement and
+/// its CAN message. A range the sensor flags is returned with its quality, not as an error.
+pub async fn read_tof<I2C, E, D>(
+    vl6180x: &mut VL6180X<ReadyMode, I2C>,
+    delay: &mut D,
+) -> Result<(RangeMeasurement, MsbTof), Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    let measurement = vl6180x
+        .poll_range_measurement_single_blocking(delay)
+        .await?;
+    Ok((measurement, tof_message(&measurement)))
+}
+
+/// Continuous ranging period for a refresh time, in 10 ms steps, and how many samples make up
+/// one refresh. Refresh times past [`CONTINUOUS_MAX_MS`] send every so many samples.
+pub fn continuous_rate(refresh_ms: u16) -> (u16, u16) {
+    let period = (refresh_ms / 10 * 10).clamp(CONTINUOUS_MIN_MS, CONTINUOUS_MAX_MS);
+    (period, (refresh_ms / period).max(1))
+}
+
+/// Initialize the VL6180X to flag every new range sample, and start ranging continuously.
+///
+/// `period_ms` is a multiple of 10 from [`CONTINUOUS_MIN_MS`] to [`CONTINUOUS_MAX_MS`]. The flag
+/// shows in the interrupt status and on GPIO1. [`poll_tof`] checks it [`TOF_POLLS_PER_PERIOD`]
+/// times a period and gives up after three periods without a sample.
+pub async fn start_tof_continuous<I2C, E>(
+    i2c: I2C,
+    period_ms: u16,
+) -> Result<VL6180X<RangeContinuousMode, I2C>, Error<E>>
+where
+    I2C: I2c<Error = E>,
+{
+    let mut config = Config::new();
+    config.set_range_interrupt_mode(RangeInterruptMode::NewSampleReady);
+    config.set_ambient_interrupt_mode(AmbientInterruptMode::Disabled);
+    config
+        .set_range_inter_measurement_period(period_ms)
+        .map_err(|_| Error::InvalidConfigurationValue(period_ms))?;
+    config
+        .set_poll_interval_ms(period_ms / TOF_POLLS_PER_PERIOD)
+        .map_err(|_| Error::InvalidConfigurationValue(period_ms))?;
+    config.set_poll_timeout_ms(3 * period_ms);
+    let mut vl6180x = VL6180X::with_config(i2c, &config).await?;
+    // a range left over from before would hold GPIO1 active
+    vl6180x.clear_all_interrupts().await?;
+    vl6180x.start_range_continuous_mode().await
+}
+
+/// Poll the interrupt status for the next range sample with `delay` and read it.
+///
+/// The fallback to [`wait_tof`] for boards without GPIO1 wired to an EXTI line. Returns
+/// [`Error::Timeout`] if no sample comes within three periods. Reading the sample clears the
+/// interrupt. A range the sensor flags is returned with its quality, not as an error.
+pub async fn poll_tof<I2C, E, D>(
+    vl6180x: &mut VL6180X<RangeContinuousMode, I2C>,
+    delay: &mut D,
+) -> Result<(RangeMeasurement, MsbTof), Error<E>>
+where
+    I2C: I2c<Error = E>,
+    D: DelayNs,
+{
+    let measurement = vl6180x.read_range_measurement_blocking(delay).await?;
+    Ok((measurement, tof_message(&measurement)))
+}
+
+/// Wait for GPIO1 to flag the next range sample and read it, leaving the bus alone until then.
+///
+/// Reading the sample clears the interrupt, releasing GPIO1. A range the sensor flags is
+/// returned with its quality, not as an error.
+pub async fn wait_tof<I2C, E, P>(
+    vl6180x: &mut VL6180X<RangeContinuousMode, I2C>,
+    gpio1: &mut P,
+) -> Result<(RangeMeasurement, MsbTof), Error<E>>
+where
+    I2C: I2c<Error = E>,
+    P: Wait<Error = Infallible>,
+{
+    gpio1
+        .wait_for_high()
+        .await
+        .unwrap_or_else(|never| match never {});
+    let measurement = vl6180x.read_range_measurement().await?;
+    Ok((measurement, tof_message(&measurement)))
+}
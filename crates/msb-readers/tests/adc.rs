@@ -0,0 +1,102 @@
+use msb_readers::adc::{AdcCalibration, AdcChannel, AdcCounts, Linear};
+use ner_can_messages::CanMessage;
+
+#[test]
+fn averages_interleaved_channels() {
+    let samples = [
+        100, 2000, 3000, //
+        102, 2010, 3001, //
+        104, 2020, 3001, //
+        // partial sequence left over from the ring buffer
+        4095, 4095,
+    ];
+    assert_eq!(
+        AdcCounts::average(&samples),
+        Some(AdcCounts {
+            shockpot: 102,
+            strain_1: 2010,
+            strain_2: 3001,
+        })
+    );
+    assert_eq!(AdcCounts::average(&[1, 2]), None);
+}
+
+#[test]
+fn average_does_not_overflow() {
+    let samples = [4095; 3 * 1000];
+    let counts = AdcCounts::average(&samples).unwrap();
+    assert_eq!(counts.shockpot, 4095);
+}
+
+#[test]
+fn default_conversion() {
+    let counts = AdcCounts {
+        shockpot: 4095,
+        strain_1: 2048,
+        strain_2: 2048 + 1024,
+    };
+    let (shockpot, strain) = counts.messages(&AdcCalibration::DEFAULT);
+
+    assert!((shockpot.travel - 50.0).abs() < 0.001);
+    assert_eq!(shockpot.shockpot_raw, 4095);
+    assert_eq!(strain.strain_1, 0.0);
+    // 1024 counts is a quarter of the reference, 1000 ue at gauge factor 2 and gain 500
+    assert!(
+        (strain.strain_2 - 1000.2).abs() < 0.1,
+        "{}",
+        strain.strain_2
+    );
+
+    assert_eq!(
+        shockpot.encode().as_bytes(),
+        [0x13, 0x88, 0x0F, 0xFF],
+        "5000 centi mm, then raw counts"
+    );
+    assert_eq!(
+        strain.encode().as_bytes(),
+        [0x00, 0x00, 0x03, 0xE8, 0x08, 0x00, 0x0C, 0x00]
+    );
+}
+
+#[test]
+fn calibrated_conversion() {
+    let calibration = AdcCalibration::DEFAULT
+        .with(
+            AdcChannel::Shockpot,
+            Linear {
+                zero: 1000,
+                gain: -0.02,
+            },
+        )
+        .unwrap();
+    let counts = AdcCounts {
+        shockpot: 1500,
+        strain_1: 0,
+        strain_2: 0,
+    };
+    let (shockpot, strain) = counts.messages(&calibration);
+
+    assert!((shockpot.travel + 10.0).abs() < 0.001);
+    assert!(strain.strain_1 < -2000.0);
+}
+
+#[test]
+fn rejects_unusable_calibration() {
+    for linear in [
+        Linear {
+            zero: 4096,
+            gain: 1.0,
+        },
+        Linear { zero: 0, gain: 0.0 },
+        Linear {
+            zero: 0,
+            gain: f32::INFINITY,
+        },
+    ] {
+        assert_eq!(
+            AdcCalibration::DEFAULT.with(AdcChannel::Strain1, linear),
+            None
+        );
+    }
+    assert!(AdcCalibration::DEFAULT.is_valid());
+}
@@ -0,0 +1,181 @@
+use std::{cell::RefCell, convert::Infallible};
+
+use embassy_futures::block_on;
+use embedded_hal::{
+    digital::{ErrorType, InputPin, OutputPin},
+    i2c::{ErrorKind, NoAcknowledgeSource},
+};
+use msb_readers::{
+    bus::{clock_out, stuck_error, BusMonitor, RecoveryError, STUCK_AFTER_ERRORS},
+    sim::SimDelay,
+};
+
+/// Open drain SCL and SDA with one target on them that is stuck in the middle of a byte
+#[derive(Default)]
+struct Lines {
+    scl_low: bool,
+    sda_low: bool,
+    /// Something other than the controller holds SCL low
+    scl_held: bool,
+    /// SCL rising edges until the target lets go of SDA, `None` if it never does
+    target_holds_sda: Option<u8>,
+    clocks: u8,
+    stops: u8,
+}
+
+impl Lines {
+    fn holding(clocks: Option<u8>) -> RefCell<Self> {
+        RefCell::new(Self {
+            target_holds_sda: clocks,
+            ..Default::default()
+        })
+    }
+
+    fn scl(&self) -> bool {
+        !self.scl_low && !self.scl_held
+    }
+
+    fn sda(&self) -> bool {
+        !self.sda_low && self.target_holds_sda == Some(0)
+    }
+}
+
+struct Scl<'a>(&'a RefCell<Lines>);
+
+struct Sda<'a>(&'a RefCell<Lines>);
+
+impl ErrorType for Scl<'_> {
+    type Error = Infallible;
+}
+
+impl ErrorType for Sda<'_> {
+    type Error = Infallible;
+}
+
+impl OutputPin for Scl<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().scl_low = true;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut lines = self.0.borrow_mut();
+        let was_high = lines.scl();
+        lines.scl_low = false;
+        if !was_high && lines.scl() {
+            lines.clocks += 1;
+            if let Some(holds) = &mut lines.target_holds_sda {
+                *holds = holds.saturating_sub(1);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl InputPin for Scl<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.0.borrow().scl())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.0.borrow().scl())
+    }
+}
+
+impl OutputPin for Sda<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.borrow_mut().sda_low = true;
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut lines = self.0.borrow_mut();
+        let was_high = lines.sda();
+        lines.sda_low = false;
+        if !was_high && lines.sda() && lines.scl() {
+            lines.stops += 1;
+        }
+        Ok(())
+    }
+}
+
+impl InputPin for Sda<'_> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.0.borrow().sda())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.0.borrow().sda())
+    }
+}
+
+fn recover(lines: &RefCell<Lines>) -> Result<(), RecoveryError> {
+    block_on(clock_out(
+        &mut Scl(lines),
+        &mut Sda(lines),
+        &mut SimDelay::default(),
+    ))
+}
+
+#[test]
+fn clock_out_free_bus() {
+    let lines = Lines::holding(Some(0));
+    assert_eq!(recover(&lines), Ok(()));
+    let lines = lines.borrow();
+    // only the clock of the STOP
+    assert_eq!(lines.clocks, 1);
+    assert_eq!(lines.stops, 1);
+    assert!(lines.scl() && lines.sda());
+}
+
+#[test]
+fn clock_out_stuck_target() {
+    let lines = Lines::holding(Some(5));
+    assert_eq!(recover(&lines), Ok(()));
+    let lines = lines.borrow();
+    assert_eq!(lines.clocks, 5 + 1);
+    assert_eq!(lines.stops, 1);
+    assert!(lines.scl() && lines.sda());
+}
+
+#[test]
+fn clock_out_gives_up() {
+    let lines = Lines::holding(None);
+    assert_eq!(recover(&lines), Err(RecoveryError::SdaStuckLow));
+    assert_eq!(lines.borrow().clocks, 9);
+    assert_eq!(lines.borrow().stops, 0);
+
+    let lines = Lines::holding(Some(0));
+    lines.borrow_mut().scl_held = true;
+    assert_eq!(recover(&lines), Err(RecoveryError::SclStuckLow));
+    assert_eq!(lines.borrow().clocks, 0);
+}
+
+#[test]
+fn monitor_stuck_detection() {
+    assert!(stuck_error(ErrorKind::ArbitrationLoss));
+    assert!(stuck_error(ErrorKind::Other));
+    assert!(!stuck_error(ErrorKind::NoAcknowledge(
+        NoAcknowledgeSource::Address
+    )));
+
+    let mut monitor = BusMonitor::new();
+    for _ in 1..STUCK_AFTER_ERRORS {
+        assert!(!monitor.error(ErrorKind::Bus));
+    }
+    // a NACK means the bus is working again
+    assert!(!monitor.error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data)));
+    for _ in 1..STUCK_AFTER_ERRORS {
+        assert!(!monitor.error(ErrorKind::Other));
+    }
+    assert!(monitor.error(ErrorKind::Other));
+    assert_eq!(monitor.recoveries, 1);
+    // counting starts over after a recovery
+    assert!(!monitor.error(ErrorKind::Other));
+    monitor.ok();
+    for _ in 1..STUCK_AFTER_ERRORS {
+        assert!(!monitor.error(ErrorKind::ArbitrationLoss));
+    }
+    assert!(monitor.error(ErrorKind::ArbitrationLoss));
+    assert_eq!(monitor.recoveries, 2);
+}
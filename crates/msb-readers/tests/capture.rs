@@ -0,0 +1,88 @@
+use msb_readers::capture::{Histogram, ShockCapture, CAPTURE_RATE_HZ, POSITION_EDGES};
+use ner_can_messages::CanMessage;
+
+#[test]
+fn histogram_bins() {
+    let mut histogram = Histogram::default();
+    for value in [-3.0, 0.0, 4.9, 5.0, 49.9, 50.0, 80.0] {
+        histogram.add(&POSITION_EDGES, value);
+    }
+    assert_eq!(histogram.counts, [1, 2, 1, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+    assert_eq!(histogram.total(), 7);
+    assert_eq!(Histogram::default().percent(), [0.0; 12]);
+}
+
+#[test]
+fn raw_frames_keep_sample_order() {
+    let mut capture = ShockCapture::new();
+    let mut frames = Vec::new();
+    for i in 0..9 {
+        if let Some(frame) = capture.push(i as f32) {
+            frames.push(frame);
+        }
+    }
+
+    assert_eq!(frames.len(), 3);
+    for (sequence, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.sequence, sequence as u16);
+        assert_eq!(frame.sample_1, 3.0 * sequence as f32);
+        assert_eq!(frame.sample_3, 3.0 * sequence as f32 + 2.0);
+    }
+    assert_eq!(
+        frames[1].encode().as_bytes(),
+        [0x00, 0x01, 0x01, 0x2C, 0x01, 0x90, 0x01, 0xF4]
+    );
+}
+
+#[test]
+fn sine_summary() {
+    // 20 mm +- 10 mm at 2 Hz, peak velocity 2 * pi * 2 Hz * 10 mm = 125.7 mm/s
+    let mut capture = ShockCapture::new();
+    for i in 0..CAPTURE_RATE_HZ {
+        let t = i as f32 / CAPTURE_RATE_HZ as f32;
+        capture.push(20.0 + 10.0 * (2.0 * core::f32::consts::PI * 2.0 * t).sin());
+    }
+
+    let (sequence, summary) = capture.take_summary().unwrap();
+    assert_eq!(sequence, 0);
+    assert!((summary.position_min - 10.0).abs() < 0.01);
+    assert!((summary.position_max - 30.0).abs() < 0.01);
+    assert!(
+        (summary.velocity_max - 125.7).abs() < 0.5,
+        "{}",
+        summary.velocity_max
+    );
+    assert!(
+        (summary.velocity_min + 125.7).abs() < 0.5,
+        "{}",
+        summary.velocity_min
+    );
+
+    // travel stays within 10-30 mm, bins 3-6
+    assert_eq!(summary.position.total(), CAPTURE_RATE_HZ);
+    let percent = summary.position.percent();
+    assert_eq!(percent[..3], [0.0; 3]);
+    assert_eq!(percent[7..], [0.0; 5]);
+    // bump and rebound are symmetric and never reach the high speed bins
+    let velocity = summary.velocity.counts;
+    assert_eq!(velocity[0] + velocity[1] + velocity[10] + velocity[11], 0);
+    assert!(velocity[3].abs_diff(velocity[8]) <= 2, "{velocity:?}");
+
+    let (stats, histograms) = summary.messages(sequence);
+    assert_eq!(stats.encode().as_bytes()[..4], [0x03, 0xE8, 0x0B, 0xB8]);
+    for (part, histogram) in histograms.iter().enumerate() {
+        assert_eq!(histogram.part, part as u8);
+        assert_eq!(histogram.sequence, 0);
+    }
+    let position_total: f32 = histograms[..2]
+        .iter()
+        .map(|h| h.bin_1 + h.bin_2 + h.bin_3 + h.bin_4 + h.bin_5 + h.bin_6)
+        .sum();
+    assert!((position_total - 100.0).abs() < 0.01);
+
+    // the next window starts empty and counts up
+    assert!(capture.take_summary().is_none());
+    capture.push(1.0);
+    capture.push(2.0);
+    assert_eq!(capture.take_summary().unwrap().0, 1);
+}
@@ -0,0 +1,268 @@
+use msb_readers::{
+    adc::{AdcCalibration, AdcChannel, Linear},
+    config::{
+        capture_frames, corner, corner_name, min_refresh_time, ConfigError, ConsoleCommand,
+        MsbSettings, RefreshTimes, I2C_BUDGET, MAX_REFRESH_TIME,
+    },
+    imu::{Mounting, SensorAxis},
+};
+use ner_can_messages::msb::{CaptureMode, DeviceLocation, Reader};
+use ner_config_store::{ram::RamFlash, ConfigStore, Record};
+
+#[test]
+fn default_is_valid() {
+    assert_eq!(RefreshTimes::DEFAULT.validate(), Ok(()));
+}
+
+#[test]
+fn set_one_reader() {
+    let times = RefreshTimes::DEFAULT.with(Some(Reader::Imu), 50).unwrap();
+    assert_eq!(times.imu, 50);
+    assert_eq!(times.temperature, RefreshTimes::DEFAULT.temperature);
+}
+
+#[test]
+fn set_every_reader() {
+    let times = RefreshTimes::DEFAULT.with(None, 1000).unwrap();
+    for reader in Reader::ALL {
+        assert_eq!(times.get(reader), 1000);
+    }
+}
+
+#[test]
+fn per_reader_limits() {
+    assert_eq!(
+        RefreshTimes::DEFAULT.with(Some(Reader::Temperature), 10),
+        Err(ConfigError::TooFast(Reader::Temperature, 100))
+    );
+    assert_eq!(
+        RefreshTimes::DEFAULT.with(Some(Reader::Adc), MAX_REFRESH_TIME + 1),
+        Err(ConfigError::TooSlow(Reader::Adc))
+    );
+}
+
+#[test]
+fn i2c_budget() {
+    assert_eq!(
+        RefreshTimes::DEFAULT.with(Some(Reader::Tof), 20),
+        Err(ConfigError::TooFast(Reader::Tof, 60))
+    );
+    // with the ToF status polled a few times a period, every reader at its fastest fits
+    let fastest = RefreshTimes {
+        temperature: min_refresh_time(Reader::Temperature),
+        imu: min_refresh_time(Reader::Imu),
+        tof: min_refresh_time(Reader::Tof),
+        adc: min_refresh_time(Reader::Adc),
+    };
+    assert!(fastest.i2c_load() <= I2C_BUDGET);
+}
+
+#[test]
+fn can_budget() {
+    let times = RefreshTimes::DEFAULT
+        .with(Some(Reader::Adc), 10)
+        .and_then(|t| t.with(Some(Reader::Temperature), 100))
+        .unwrap();
+    // the IMU sends every sample batched at 12.5 Hz, plus the diagnostic frame
+    assert_eq!(times.can_load(), 200 + 10 + 26 + 2 + 1);
+    assert_eq!(
+        times.with(Some(Reader::Imu), 10),
+        Err(ConfigError::CanOverloaded(200 + 10 + 208 + 2 + 1))
+    );
+}
+
+#[test]
+fn capture_budget() {
+    assert_eq!(capture_frames(CaptureMode::Off), 0);
+    assert_eq!(capture_frames(CaptureMode::Summary), 5);
+    assert_eq!(capture_frames(CaptureMode::Raw), 167);
+    assert_eq!(
+        RefreshTimes::DEFAULT.validate_capture(CaptureMode::Raw),
+        Ok(())
+    );
+
+    // fast IMU and ADC readings leave no room for raw samples
+    let times = RefreshTimes::DEFAULT
+        .with(Some(Reader::Imu), 10)
+        .and_then(|t| t.with(Some(Reader::Adc), 20))
+        .unwrap();
+    assert_eq!(times.validate_capture(CaptureMode::Summary), Ok(()));
+    assert_eq!(
+        times.validate_capture(CaptureMode::Raw),
+        Err(ConfigError::CanOverloaded(times.can_load() + 167))
+    );
+}
+
+#[test]
+fn console_commands() {
+    assert_eq!(
+        ConsoleCommand::parse("rates\r\n"),
+        Some(ConsoleCommand::ShowRates)
+    );
+    assert_eq!(
+        ConsoleCommand::parse("rate imu 100"),
+        Some(ConsoleCommand::SetRate(Some(Reader::Imu), 100))
+    );
+    assert_eq!(
+        ConsoleCommand::parse(" rate all 1000\n"),
+        Some(ConsoleCommand::SetRate(None, 1000))
+    );
+    assert_eq!(
+        ConsoleCommand::parse("capture raw"),
+        Some(ConsoleCommand::SetCapture(CaptureMode::Raw))
+    );
+    assert_eq!(
+        ConsoleCommand::parse("cal"),
+        Some(ConsoleCommand::ShowCalibration)
+    );
+    assert_eq!(
+        ConsoleCommand::parse("cal strain2 2000 1.25\r\n"),
+        Some(ConsoleCommand::SetCalibration(
+            AdcChannel::Strain2,
+            Linear {
+                zero: 2000,
+                gain: 1.25
+            }
+        ))
+    );
+    assert_eq!(
+        ConsoleCommand::parse("mount\r\n"),
+        Some(ConsoleCommand::ShowMounting)
+    );
+    assert_eq!(
+        ConsoleCommand::parse("mount backleft -x -y +z"),
+        Some(ConsoleCommand::SetMounting(
+            DeviceLocation::BackLeft,
+            Mounting::new(SensorAxis::MinusX, SensorAxis::MinusY, SensorAxis::PlusZ).unwrap()
+        ))
+    );
+    for (i, location) in DeviceLocation::ALL.into_iter().enumerate() {
+        assert_eq!(corner(location), i);
+        let line = format!("mount {} +x +y +z", corner_name(location));
+        assert_eq!(
+            ConsoleCommand::parse(&line),
+            Some(ConsoleCommand::SetMounting(location, Mounting::IDENTITY))
+        );
+    }
+    for bad in [
+        "",
+        "rate",
+        "capture",
+        "capture fast",
+        "cal shockpot",
+        "cal shockpot 10",
+        "cal strain3 0 1.0",
+        "cal shockpot 10 1.0 2.0",
+        "rate imu",
+        "rate gps 100",
+        "rate imu fast",
+        "rates now",
+        "mount +x +y +z",
+        "mount middle +x +y +z",
+        "mount frontleft +x +y",
+        "mount frontleft +x +y -z",
+        "mount frontleft +x +x +z",
+        "mount frontleft +x +y +w",
+        "mount frontleft +x +y +z +x",
+    ] {
+        assert_eq!(ConsoleCommand::parse(bad), None, "{bad}");
+    }
+}
+
+#[test]
+fn settings_record() {
+    let mut settings = MsbSettings {
+        refresh_times: RefreshTimes::DEFAULT.with(Some(Reader::Imu), 50).unwrap(),
+        adc_calibration: AdcCalibration::DEFAULT
+            .with(
+                AdcChannel::Shockpot,
+                Linear {
+                    zero: 100,
+                    gain: -0.5,
+                },
+            )
+            .unwrap(),
+        mountings: [Mounting::IDENTITY; 4],
+    };
+    settings.mountings[corner(DeviceLocation::FrontRight)] =
+        Mounting::new(SensorAxis::PlusY, SensorAxis::MinusX, SensorAxis::PlusZ).unwrap();
+    settings.mountings[corner(DeviceLocation::BackLeft)] =
+        Mounting::new(SensorAxis::MinusX, SensorAxis::MinusY, SensorAxis::PlusZ).unwrap();
+    let mut buf = [0; MsbSettings::SIZE];
+    settings.encode(&mut buf);
+    assert_eq!(buf[..8], [0xF4, 0x01, 0x32, 0x00, 0xF4, 0x01, 0xFA, 0x00]);
+    assert_eq!(buf[8..14], [0x64, 0x00, 0x00, 0x00, 0x00, 0xBF]);
+    assert_eq!(buf[26..], [0, 2, 4, 2, 1, 4, 1, 3, 4, 0, 2, 4]);
+    assert_eq!(MsbSettings::decode(&buf), Some(settings));
+
+    // times that break the limits are never loaded, but the calibration still is
+    let mut bad = buf;
+    bad[0] = 0x01;
+    bad[1] = 0x00;
+    assert_eq!(
+        MsbSettings::decode(&bad),
+        Some(MsbSettings {
+            refresh_times: RefreshTimes::DEFAULT,
+            ..settings
+        })
+    );
+    // nor are calibrations that can't convert anything, which leaves the other channels
+    let mut bad = buf;
+    bad[10..14].copy_from_slice(&f32::NAN.to_le_bytes());
+    assert_eq!(
+        MsbSettings::decode(&bad),
+        Some(MsbSettings {
+            adc_calibration: AdcCalibration::DEFAULT,
+            ..settings
+        })
+    );
+    // nor a mounting that mirrors the axes, which leaves the other corners
+    let mut bad = buf;
+    bad[34] = 5;
+    let mut mountings = settings.mountings;
+    mountings[corner(DeviceLocation::BackLeft)] = Mounting::IDENTITY;
+    assert_eq!(
+        MsbSettings::decode(&bad),
+        Some(MsbSettings {
+            mountings,
+            ..settings
+        })
+    );
+
+    let mut store = ConfigStore::<_, MsbSettings>::new(RamFlash::<512>::new()).unwrap();
+    assert_eq!(store.load_or_default(), MsbSettings::default());
+    store.store(&settings).unwrap();
+    let mut store = ConfigStore::<_, MsbSettings>::new(store.release()).unwrap();
+    assert_eq!(store.load_or_default(), settings);
+}
+
+#[test]
+fn settings_record_over_budget() {
+    // saved by a release with looser limits than this one
+    let mut settings = MsbSettings {
+        refresh_times: RefreshTimes::DEFAULT,
+        adc_calibration: AdcCalibration::DEFAULT
+            .with(
+                AdcChannel::Strain1,
+                Linear {
+                    zero: 2048,
+                    gain: 1.25,
+                },
+            )
+            .unwrap(),
+        mountings: [Mounting::IDENTITY; 4],
+    };
+    settings.refresh_times.tof = 1;
+    assert!(settings.refresh_times.validate().is_err());
+
+    let mut store = ConfigStore::<_, MsbSettings>::new(RamFlash::<512>::new()).unwrap();
+    store.store(&settings).unwrap();
+    let mut store = ConfigStore::<_, MsbSettings>::new(store.release()).unwrap();
+    assert_eq!(
+        store.load_or_default(),
+        MsbSettings {
+            refresh_times: RefreshTimes::DEFAULT,
+            ..settings
+        }
+    );
+}
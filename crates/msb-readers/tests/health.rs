@@ -0,0 +1,124 @@
+use msb_readers::{
+    health::{
+        BoardHealth, Fault, Sensor, SensorHealth, FAILED_AFTER_ERRORS, RETRY_MAX_MS, RETRY_MIN_MS,
+    },
+    sim::SimError,
+};
+use ner_can_messages::{
+    msb::{SensorFault, SensorState},
+    CanMessage,
+};
+use vl6180x_ner::RangeStatusErrorCode;
+
+#[test]
+fn driver_faults() {
+    let no_device = SimError::NoDevice(0x44);
+    assert_eq!(
+        sht3x_ner::Error::I2c(no_device).fault(),
+        Some(SensorFault::NoAcknowledge)
+    );
+    assert_eq!(
+        sht3x_ner::Error::<SimError>::Crc.fault(),
+        Some(SensorFault::Crc)
+    );
+    assert_eq!(
+        lsm6dso_ner::Error::<SimError>::ChipDetectFailed.fault(),
+        Some(SensorFault::WrongDevice)
+    );
+    assert_eq!(
+        vl6180x_ner::Error::<SimError>::Timeout.fault(),
+        Some(SensorFault::Timeout)
+    );
+    // nothing in range is a reading, not a sensor fault
+    assert_eq!(
+        vl6180x_ner::Error::<SimError>::RangeStatusError(RangeStatusErrorCode::MaxConvergence)
+            .fault(),
+        None
+    );
+    assert_eq!(
+        vl6180x_ner::Error::<SimError>::RangeStatusError(RangeStatusErrorCode::VcselWatchdog)
+            .fault(),
+        Some(SensorFault::Other)
+    );
+}
+
+#[test]
+fn missing_sensor() {
+    let mut health = SensorHealth::NEW;
+    assert_eq!(health.state, SensorState::NotPresent);
+
+    health.init_failed(Some(SensorFault::NoAcknowledge));
+    assert_eq!(health.state, SensorState::NotPresent);
+    assert_eq!(health.retry_delay_ms(), RETRY_MIN_MS);
+    health.init_failed(Some(SensorFault::NoAcknowledge));
+    assert_eq!(health.retry_delay_ms(), 2 * RETRY_MIN_MS);
+    for _ in 0..20 {
+        health.init_failed(Some(SensorFault::NoAcknowledge));
+    }
+    assert_eq!(health.retry_delay_ms(), RETRY_MAX_MS);
+    assert_eq!(health.consecutive_errors, 22);
+
+    health.init_ok();
+    assert_eq!(health.state, SensorState::Ok);
+    assert_eq!(health.consecutive_errors, 0);
+    assert_eq!(health.retry_delay_ms(), RETRY_MIN_MS);
+
+    // once it has answered, losing it is a failure
+    health.init_failed(Some(SensorFault::NoAcknowledge));
+    assert_eq!(health.state, SensorState::Failed);
+}
+
+#[test]
+fn failing_readings() {
+    let mut health = SensorHealth::NEW;
+    health.init_ok();
+
+    health.reading_failed(Some(SensorFault::Crc));
+    assert_eq!(health.state, SensorState::Degraded);
+    health.reading_ok();
+    assert_eq!(health.state, SensorState::Ok);
+    assert_eq!(health.last_fault, Some(SensorFault::Crc));
+
+    for _ in 1..FAILED_AFTER_ERRORS {
+        health.reading_failed(Some(SensorFault::Bus));
+        assert!(!health.failed());
+    }
+    health.reading_failed(Some(SensorFault::Bus));
+    assert!(health.failed());
+
+    // a measurement error does not count against the sensor
+    health.init_ok();
+    health.reading_failed(None);
+    assert_eq!(health.state, SensorState::Ok);
+    assert_eq!(health.consecutive_errors, 0);
+}
+
+#[test]
+fn self_test_degrades() {
+    let mut health = SensorHealth::NEW;
+    health.self_test_failed();
+    assert_eq!(health.state, SensorState::NotPresent);
+    health.init_ok();
+    assert_eq!(health.state, SensorState::Degraded);
+    health.reading_ok();
+    assert_eq!(health.state, SensorState::Degraded);
+    assert_eq!(health.last_fault, Some(SensorFault::SelfTest));
+}
+
+#[test]
+fn diagnostic_frame() {
+    let mut health = BoardHealth::NEW;
+    health.get_mut(Sensor::Temperature).init_ok();
+    let imu = health.get_mut(Sensor::Imu);
+    imu.init_ok();
+    imu.reading_failed(Some(SensorFault::Crc));
+    imu.reading_failed(Some(SensorFault::Crc));
+    health
+        .get_mut(Sensor::Tof)
+        .init_failed(Some(SensorFault::NoAcknowledge));
+
+    assert_eq!(
+        health.message(65536 + 1000).encode().as_bytes(),
+        [0x00, 0, 0x31, 2, 0x13, 1, 0x03, 0xE8]
+    );
+}
@@ -0,0 +1,61 @@
+use embassy_futures::block_on;
+use msb_readers::{
+    health::Sensor,
+    inventory::{self, discover, inventory_message, presence},
+    sim::SimBus,
+};
+use ner_can_messages::{msb::MsbInventory, CanMessage};
+use ner_i2c_probe::Presence;
+
+#[test]
+fn every_sensor_fitted() {
+    let devices = block_on(discover(&mut SimBus::new())).unwrap();
+    for sensor in [Sensor::Temperature, Sensor::Imu, Sensor::Tof] {
+        assert_eq!(presence(&devices, sensor), Presence::Present);
+    }
+    assert_eq!(
+        devices.addresses.iter().collect::<Vec<_>>(),
+        [0x29, 0x45, 0x6A]
+    );
+    assert!(devices.unknown.is_empty());
+    assert_eq!(
+        inventory_message(&devices),
+        MsbInventory {
+            temperature: 1,
+            imu: 1,
+            tof: 1,
+            imu_id: 0x6C,
+            tof_id: 0xB4,
+            devices: 3,
+            other_1: 0,
+            other_2: 0,
+            other_3: 0,
+            other_4: 0,
+        }
+    );
+}
+
+#[test]
+fn missing_and_wrong_sensors() {
+    let mut bus = SimBus::new();
+    bus.lsm6dso.as_mut().unwrap().regs[0x0F] = 0x69;
+    bus.vl6180x = None;
+    let devices = block_on(discover(&mut bus)).unwrap();
+    assert_eq!(presence(&devices, Sensor::Temperature), Presence::Present);
+    assert_eq!(presence(&devices, Sensor::Imu), Presence::WrongId(0x69));
+    assert_eq!(presence(&devices, Sensor::Tof), Presence::Absent);
+    let msg = inventory_message(&devices);
+    assert_eq!(msg.encode().as_bytes(), [0x09, 0x69, 0, 2, 0, 0, 0, 0]);
+
+    // a VL6180X left at another address by an earlier boot is not where the reader looks
+    let mut bus = SimBus::new();
+    bus.sht3x = None;
+    bus.vl6180x.as_mut().unwrap().regs[0x212] = 0x30;
+    let devices = block_on(discover(&mut bus)).unwrap();
+    assert_eq!(presence(&devices, Sensor::Tof), Presence::Absent);
+    assert_eq!(presence(&devices, Sensor::Temperature), Presence::Absent);
+    assert_eq!(devices.unknown.iter().collect::<Vec<_>>(), [0x30]);
+    let msg = inventory_message(&devices);
+    assert_eq!((msg.devices, msg.other_1, msg.other_2), (2, 0x30, 0));
+    assert_eq!(inventory::signature(Sensor::Tof).address, 0x29);
+}
@@ -0,0 +1,1185 @@
+use core::{
+    cell::{Cell, RefCell},
+    convert::Infallible,
+};
+
+use embassy_futures::block_on;
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, digital::Wait, i2c::I2c};
+use lsm6dso_ner::{
+    AccelerometerFilter, AccelerometerFilterBandwidth, AccelerometerOutput, AccelerometerScale,
+    Axes, GyroscopeFilter, GyroscopeFullScale, GyroscopeHighPassCutoff, GyroscopeLpf1Bandwidth,
+    GyroscopeOutput, InterruptPin, InterruptRoute, Lsm6dsoConfig, Rounding, SensorState,
+};
+use msb_readers::{
+    imu::{self, ImuAligner, ImuClock, ImuCorrection, ImuRate, ImuReading, Mounting, SensorAxis},
+    sim::{SimBus, SimDelay, SimError, SimVl6180x, SIM_FIFO_DEPTH},
+    temperature, tof,
+};
+use ner_can_messages::{
+    msb::{ImuEvent, MsbImuEvent, TofQuality},
+    CanMessage,
+};
+use sht3x_ner::{AlertLimit, AlertLimitKind, Measurement, Rate, Status};
+use vl6180x_ner::{MultiVL6180X, RangeQuality, RangeStatusErrorCode};
+
+#[test]
+fn temperature_payload() {
+    let mut bus = SimBus::new();
+    let mut sht30 = temperature::init_temperature(&mut bus);
+    let (res, msg) = block_on(temperature::read_temperature(
+        &mut sht30,
+        &mut SimDelay::default(),
+    ))
+    .unwrap();
+
+    assert_eq!(res.temperature, 2500);
+    assert_eq!(res.humidity, 5000);
+    assert_eq!(msg.encode().as_bytes(), [0x09, 0xC4, 0x13, 0x88]);
+}
+
+#[test]
+fn temperature_negative() {
+    let mut bus = SimBus::new();
+    bus.sht3x.as_mut().unwrap().raw_temperature = 0x2000;
+    let mut sht30 = temperature::init_temperature(&mut bus);
+    let (res, msg) = block_on(temperature::read_temperature(
+        &mut sht30,
+        &mut SimDelay::default(),
+    ))
+    .unwrap();
+
+    // -45 + 175 * 8192 / 65535 = -23.13 degC
+    assert_eq!(res.temperature, -2313);
+    assert_eq!(msg.encode().as_bytes()[..2], (-2313i16).to_be_bytes());
+}
+
+#[test]
+fn temperature_waits_for_measurement() {
+    let mut bus = SimBus::new();
+    let mut sht30 = temperature::init_temperature(&mut bus);
+    let mut delay = SimDelay::default();
+    block_on(temperature::read_temperature(&mut sht30, &mut delay)).unwrap();
+
+    // high repeatability single shot
+    assert_eq!(delay.elapsed_ns, 15_000_000);
+}
+
+#[test]
+fn temperature_periodic() {
+    let bus = RefCell::new(SimBus::new());
+    let fail = Cell::new(false);
+    let i2c = FlakyBus {
+        bus: &bus,
+        fail: &fail,
+    };
+    let mut delay = SimDelay::default();
+    let rate = temperature::periodic_rate(500);
+    assert_eq!(rate, Rate::R4);
+    assert_eq!(temperature::periodic_rate(100), Rate::R10);
+    assert_eq!(temperature::periodic_rate(5000), Rate::R0_5);
+
+    // a failed start hands the driver back
+    fail.set(true);
+    let (sht30, _) = block_on(temperature::start_temperature(
+        temperature::init_temperature(i2c),
+        rate,
+        &mut delay,
+    ))
+    .err()
+    .unwrap();
+    fail.set(false);
+    let Ok(mut sht30) = block_on(temperature::start_temperature(sht30, rate, &mut delay)) else {
+        panic!("periodic mode did not start");
+    };
+    assert!(bus.borrow().sht3x.as_ref().unwrap().periodic);
+
+    // nothing measured yet, and fetching never waits
+    delay.elapsed_ns = 0;
+    assert!(block_on(temperature::fetch_temperature(&mut sht30))
+        .unwrap()
+        .is_none());
+    bus.borrow_mut().sht3x.as_mut().unwrap().data_ready = true;
+    let (res, msg) = block_on(temperature::fetch_temperature(&mut sht30))
+        .unwrap()
+        .unwrap();
+    assert_eq!(res.temperature, 2500);
+    assert_eq!(msg.encode().as_bytes(), [0x09, 0xC4, 0x13, 0x88]);
+    assert!(block_on(temperature::fetch_temperature(&mut sht30))
+        .unwrap()
+        .is_none());
+    assert_eq!(delay.elapsed_ns, 0);
+
+    // a bus error is still an error
+    fail.set(true);
+    assert!(block_on(temperature::fetch_temperature(&mut sht30)).is_err());
+    fail.set(false);
+
+    let Ok(mut sht30) = block_on(sht30.stop(&mut delay)) else {
+        panic!("periodic mode did not stop");
+    };
+    assert!(!bus.borrow().sht3x.as_ref().unwrap().periodic);
+    block_on(temperature::read_temperature(&mut sht30, &mut delay)).unwrap();
+}
+
+#[test]
+fn temperature_alert_limits() {
+    let mut bus = SimBus::new();
+    let mut delay = SimDelay::default();
+    let sht30 = temperature::init_temperature(&mut bus);
+    let Ok(sht30) = block_on(temperature::start_temperature(sht30, Rate::R1, &mut delay)) else {
+        panic!("periodic mode did not start");
+    };
+    let Ok(mut sht30) = block_on(sht30.stop(&mut delay)) else {
+        panic!("periodic mode did not stop");
+    };
+
+    // kept to the 7 humidity and 9 temperature bits of each limit
+    assert_eq!(
+        block_on(sht30.alert_limit(AlertLimitKind::HighSet, &mut delay)).unwrap(),
+        AlertLimit {
+            temperature: 7975,
+            humidity: 9922,
+        }
+    );
+    assert_eq!(
+        block_on(sht30.alert_limit(AlertLimitKind::LowSet, &mut delay)).unwrap(),
+        AlertLimit {
+            temperature: -4500,
+            humidity: 0,
+        }
+    );
+    let sim = bus.sht3x.as_ref().unwrap();
+    assert_eq!(sim.alert_limits, [0xFF6D, 0xFF5F, 0x0000, 0x0000]);
+    assert_eq!(sim.status & Status::WRITE_DATA_CHECKSUM.bits(), 0);
+
+    // a limit with a bad CRC is ignored and flagged
+    block_on(bus.write(
+        temperature::SHT3X_ADDR as u8,
+        &[0x61, 0x1D, 0x12, 0x34, 0x00],
+    ))
+    .unwrap();
+    let mut sht30 = temperature::init_temperature(&mut bus);
+    let status = block_on(sht30.status(&mut delay)).unwrap();
+    assert!(status.contains(Status::WRITE_DATA_CHECKSUM));
+    // the defaults are back after a reset
+    block_on(sht30.reset(&mut delay)).unwrap();
+    assert_eq!(
+        block_on(sht30.alert_limit(AlertLimitKind::HighClear, &mut delay)).unwrap(),
+        AlertLimit {
+            temperature: 5788,
+            humidity: 7812,
+        }
+    );
+}
+
+#[test]
+fn temperature_condensation_recovery() {
+    let mut bus = SimBus::new();
+    let mut delay = SimDelay::default();
+    let mut sht30 = temperature::init_temperature(&mut bus);
+    let mut recovery = temperature::condensation_recovery();
+    let wet = Measurement {
+        temperature: 1000,
+        humidity: 9800,
+    };
+    let dry = Measurement {
+        temperature: 1500,
+        humidity: 6000,
+    };
+
+    recovery.update(&dry, 0);
+    assert!(!recovery.recovering());
+    recovery.update(&wet, 1000);
+    assert!(recovery.heater());
+    block_on(sht30.set_heater(recovery.heater(), &mut delay)).unwrap();
+    assert!(block_on(sht30.status(&mut delay))
+        .unwrap()
+        .contains(Status::HEATER));
+
+    // heating dries the sensor out, but the cycle runs its full length
+    recovery.update(&dry, 1000 + temperature::HEATER_ON_MS - 1);
+    assert!(recovery.heater());
+    recovery.update(&dry, 1000 + temperature::HEATER_ON_MS);
+    assert!(!recovery.heater());
+    assert!(recovery.recovering());
+    block_on(sht30.set_heater(recovery.heater(), &mut delay)).unwrap();
+    assert!(!block_on(sht30.status(&mut delay))
+        .unwrap()
+        .contains(Status::HEATER));
+
+    // still wet after cooling starts another cycle on the next measurement
+    let cooled = 1000 + temperature::HEATER_ON_MS + temperature::HEATER_COOL_MS;
+    recovery.update(&wet, cooled);
+    assert!(!recovery.recovering());
+    recovery.update(&wet, cooled + 1000);
+    assert!(recovery.heater());
+}
+
+#[test]
+fn imu_config() {
+    let mut bus = SimBus::new();
+    block_on(imu::init_imu(&mut bus)).unwrap();
+
+    let lsm = bus.lsm6dso.as_ref().unwrap();
+    // both sensors off, +-2 g with LPF2 on
+    assert_eq!(lsm.regs[0x10], 0b0000_0010);
+    assert_eq!(lsm.regs[0x11], 0x00);
+    // block data update, auto increment left on
+    assert_eq!(lsm.regs[0x12], 0x44);
+    // LPF2 at ODR/4, not the slope filter
+    assert_eq!(lsm.regs[0x17], 0x00);
+    // timestamp on
+    assert_eq!(lsm.regs[0x19], 0x20);
+
+    let config = Lsm6dsoConfig {
+        accelerometer_filter: AccelerometerFilter::HighPass(AccelerometerFilterBandwidth::Odr100),
+        gyroscope_filter: GyroscopeFilter {
+            lpf1: Some(GyroscopeLpf1Bandwidth::Ftype3),
+            high_pass: Some(GyroscopeHighPassCutoff::Hz0_26),
+        },
+        low_power: true,
+        rounding: Rounding::Both,
+        ..imu::IMU_CONFIG
+    };
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    block_on(lsm6dso.configure(&config)).unwrap();
+
+    let lsm = bus.lsm6dso.as_ref().unwrap();
+    assert_eq!(lsm.regs[0x10], 0x00);
+    assert_eq!(lsm.regs[0x17], 0b1000_0100);
+    assert_eq!(lsm.regs[0x13], 0b10);
+    assert_eq!(lsm.regs[0x14], 0b0110_0000);
+    assert_eq!(lsm.regs[0x15], 0b1_0011);
+    assert_eq!(lsm.regs[0x16], 0b1110_0000);
+}
+
+#[test]
+fn imu_fifo_setup() {
+    let mut bus = SimBus::new();
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    block_on(imu::start_imu_fifo(&mut lsm6dso, ImuRate::Hz104)).unwrap();
+
+    let regs = &bus.lsm6dso.as_ref().unwrap().regs;
+    // 104 Hz output and batch rates, continuous mode with a timestamp every 8 batches
+    assert_eq!(regs[0x10] >> 4, 0b0100);
+    assert_eq!(regs[0x11] >> 4, 0b0100);
+    assert_eq!(regs[0x09], 0x44);
+    assert_eq!(regs[0x0A], 0x86);
+    assert_eq!(regs[0x19] & 0x20, 0x20);
+    // about 100 ms of accel and gyro words
+    assert_eq!(regs[0x07], 20);
+}
+
+#[test]
+fn imu_interrupt_routing() {
+    let mut bus = SimBus::new();
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    block_on(imu::route_imu_watermark(&mut lsm6dso)).unwrap();
+    let route = InterruptRoute {
+        free_fall: true,
+        orientation: true,
+        significant_motion: true,
+        ..Default::default()
+    };
+    block_on(lsm6dso.route_interrupts(InterruptPin::Int2, route)).unwrap();
+
+    let lsm = bus.lsm6dso.as_ref().unwrap();
+    assert_eq!((lsm.regs[0x0D], lsm.regs[0x5E]), (0x08, 0x00));
+    assert_eq!((lsm.regs[0x0E], lsm.regs[0x5F]), (0x00, 0x16));
+    // significant motion is routed in the embedded functions page, which is left afterwards
+    assert_eq!(lsm.embedded_regs[0x0E], 0x20);
+    assert_eq!(lsm.regs[0x01], 0x00);
+    assert_eq!(lsm.regs[0x0A], 0x00);
+}
+
+#[test]
+fn imu_events() {
+    let mut bus = SimBus::new();
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    block_on(imu::start_imu_events(&mut lsm6dso)).unwrap();
+    // not synced, the sensor time is taken as is
+    let clock = ImuClock::new(25_000.0);
+    assert_eq!(
+        block_on(imu::read_imu_events(&mut lsm6dso, &clock)).unwrap(),
+        [None, None]
+    );
+
+    let lsm = bus.lsm6dso.as_mut().unwrap();
+    // tap on all axes at 20/32 of full scale, latched, with detection enabled
+    assert_eq!(lsm.regs[0x56] & 0x0F, 0x0F);
+    assert_eq!(lsm.regs[0x57..=0x59], [20, 0x80 | 20, 20]);
+    assert_eq!(lsm.regs[0x5A], 0x05);
+    // 312 mg for 6 samples
+    assert_eq!(lsm.regs[0x5D], (6 << 3) | 0b011);
+
+    // a single tap on -x, and a free fall
+    lsm.regs[0x1B] = 0x20;
+    lsm.regs[0x1C] = 0x40 | 0x20 | 0x08 | 0x04;
+    lsm.regs[0x40..0x44].copy_from_slice(&40_000u32.to_le_bytes());
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let [impact, free_fall] = block_on(imu::read_imu_events(&mut lsm6dso, &clock)).unwrap();
+    assert_eq!(
+        impact.unwrap(),
+        MsbImuEvent {
+            event: ImuEvent::Impact.to_raw(),
+            axes: 0b1001,
+            time: 1000,
+        }
+    );
+    assert_eq!(free_fall.unwrap().event, ImuEvent::FreeFall.to_raw());
+
+    let sources = block_on(lsm6dso.read_event_sources()).unwrap();
+    assert!(sources.tap.single && !sources.tap.double);
+    assert_eq!(
+        sources.tap.axes,
+        Axes {
+            x: true,
+            y: false,
+            z: false
+        }
+    );
+    assert!(!sources.significant_motion);
+}
+
+#[test]
+fn imu_fifo_payloads() {
+    let mut bus = SimBus::new();
+    let lsm = bus.lsm6dso.as_mut().unwrap();
+    lsm.push_fifo_timestamp(0, 40_000);
+    for counter in 0..3 {
+        lsm.push_fifo_gyro_raw(counter, 1000, -1000, 0);
+        lsm.push_fifo_accel_raw(counter, 1000, -1000, 0);
+    }
+
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut aligner = ImuAligner::new(ImuRate::Hz104);
+    let mut readings = [ImuReading::default(); 8];
+    let (count, overrun) =
+        block_on(imu::drain_imu(&mut lsm6dso, &mut aligner, &mut readings)).unwrap();
+
+    // the third batch is held until a word of the next one shows it is complete
+    assert_eq!((count, overrun), (2, false));
+    assert_eq!(readings[0].time, 40_000);
+    assert_eq!(readings[1].time, 40_000 + 385);
+
+    let clock = ImuClock::new(25_000.0);
+    let (accel, gyro) = readings[0].messages(&clock);
+    // 1000 * 0.061 mg = 0.598 m/s^2, 40000 * 25 us = 1000 ms
+    assert_eq!(
+        accel.encode().as_bytes(),
+        [0x02, 0x56, 0xFD, 0xAA, 0x00, 0x00, 0x03, 0xE8]
+    );
+    // 1000 * 8.75 mdps = 0.1527 rad/s, rounded to the nearest mrad/s
+    assert_eq!(
+        gyro.encode().as_bytes(),
+        [0x00, 0x99, 0xFF, 0x67, 0x00, 0x00, 0x03, 0xE8]
+    );
+    assert_eq!(readings[1].messages(&clock).1.time, 1009);
+}
+
+#[test]
+fn imu_clock() {
+    let mut bus = SimBus::new();
+    let lsm = bus.lsm6dso.as_mut().unwrap();
+    // INTERNAL_FREQ_FINE of -10, the counter runs 1.5% slow
+    lsm.regs[0x63] = -10i8 as u8;
+    lsm.regs[0x40..0x44].copy_from_slice(&40_000u32.to_le_bytes());
+
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut clock = block_on(imu::start_imu_clock(&mut lsm6dso)).unwrap();
+    let mut now = [5_000_000, 5_000_400].into_iter();
+    block_on(imu::sync_imu_clock(&mut lsm6dso, &mut clock, || {
+        now.next().unwrap()
+    }))
+    .unwrap();
+
+    // 40000 ticks read halfway through the I2C read
+    assert_eq!(clock.micros(40_000), 5_000_200);
+    // 394 ticks of 25.38 us, 9999.99 us truncated
+    assert_eq!(clock.micros(40_394), 5_010_199);
+    assert_eq!(clock.micros(39_606), 4_990_201);
+    assert_eq!(clock.time_ms(40_394), 5010);
+
+    // timestamp on and left running
+    let lsm = bus.lsm6dso.as_ref().unwrap();
+    assert_eq!(lsm.regs[0x19] & 0x20, 0x20);
+
+    // timestamps just before a wrap of the counter are still in the past
+    let mut clock = ImuClock::new(25_000.0);
+    clock.sync(4, 70_000);
+    assert_eq!(clock.micros(u32::MAX - 35), 69_000);
+    // and the MSB clock never goes below zero
+    clock.sync(0, 0);
+    assert_eq!(clock.micros(u32::MAX), 0);
+}
+
+#[test]
+fn imu_fifo_drain_limits() {
+    let mut bus = SimBus::new();
+    let lsm = bus.lsm6dso.as_mut().unwrap();
+    lsm.push_fifo_timestamp(0, 0);
+    for batch in 0..10 {
+        lsm.push_fifo_accel_raw(batch % 4, 0, 0, 0);
+        lsm.push_fifo_gyro_raw(batch % 4, 0, 0, 0);
+    }
+
+    // a full buffer leaves the rest in the FIFO for the next drain
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut aligner = ImuAligner::new(ImuRate::Hz12_5);
+    let mut readings = [ImuReading::default(); 4];
+    let (count, _) = block_on(imu::drain_imu(&mut lsm6dso, &mut aligner, &mut readings)).unwrap();
+    assert_eq!(count, 4);
+    let (count, _) = block_on(imu::drain_imu(&mut lsm6dso, &mut aligner, &mut readings)).unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(readings[3].time, 7 * 3200);
+    // the eighth batch finished on the first word of the ninth
+    assert_eq!(bus.lsm6dso.as_ref().unwrap().fifo_len(), 3);
+}
+
+#[test]
+fn imu_fifo_overrun() {
+    let mut bus = SimBus::new();
+    let lsm = bus.lsm6dso.as_mut().unwrap();
+    lsm.push_fifo_timestamp(0, 0);
+    for batch in 0..SIM_FIFO_DEPTH as u8 {
+        lsm.push_fifo_accel_raw(batch % 4, 0, 0, 0);
+        lsm.push_fifo_gyro_raw(batch % 4, 0, 0, 0);
+    }
+
+    // the timestamp was overwritten, so nothing left can be timed
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut aligner = ImuAligner::new(ImuRate::Hz104);
+    let mut readings = [ImuReading::default(); SIM_FIFO_DEPTH];
+    let (count, overrun) =
+        block_on(imu::drain_imu(&mut lsm6dso, &mut aligner, &mut readings)).unwrap();
+    assert_eq!((count, overrun), (0, true));
+    let (_, overrun) = block_on(imu::drain_imu(&mut lsm6dso, &mut aligner, &mut readings)).unwrap();
+    assert!(!overrun);
+}
+
+#[test]
+fn imu_rate_for_refresh_time() {
+    assert_eq!(ImuRate::for_refresh_time(500), ImuRate::Hz12_5);
+    assert_eq!(ImuRate::for_refresh_time(80), ImuRate::Hz12_5);
+    assert_eq!(ImuRate::for_refresh_time(79), ImuRate::Hz26);
+    assert_eq!(ImuRate::for_refresh_time(20), ImuRate::Hz52);
+    assert_eq!(ImuRate::for_refresh_time(10), ImuRate::Hz104);
+}
+
+#[test]
+fn imu_calibration() {
+    let mut bus = SimBus::new();
+    let sim = bus.lsm6dso.as_mut().unwrap();
+    // both samples ready, 100 mg on x and 1 g on z at +-2 g, 0.875 dps on x at 250 dps
+    sim.regs[0x1E] = 0b11;
+    sim.set_accel_raw(1639, 0, 16393);
+    sim.set_gyro_raw(100, 0, -100);
+
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut delay = SimDelay::default();
+    let bias = block_on(imu::calibrate_imu(
+        &mut lsm6dso,
+        &mut delay,
+        ImuRate::Hz104,
+        &Mounting::IDENTITY,
+    ))
+    .unwrap();
+    assert_eq!(delay.elapsed_ns, 0);
+    assert!((bias.accelerometer.0 - 0.9805).abs() < 0.001, "{bias:?}");
+    assert!(bias.accelerometer.2.abs() < 0.001, "{bias:?}");
+    assert!((bias.gyroscope.0 - 0.875f32.to_radians()).abs() < 1e-6);
+    assert!((bias.gyroscope.2 + 0.875f32.to_radians()).abs() < 1e-6);
+
+    // ODRs at 104 Hz
+    let sim = bus.lsm6dso.as_ref().unwrap();
+    assert_eq!(sim.regs[0x10] >> 4, 0b0100);
+    assert_eq!(sim.regs[0x11] >> 4, 0b0100);
+
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    block_on(lsm6dso.set_accelerometer_offset((4.903, -4.903, 0.0))).unwrap();
+
+    let sim = bus.lsm6dso.as_ref().unwrap();
+    // 500 mg is past the fine weight, 32 steps of 15.6 mg
+    assert_eq!(sim.regs[0x73..0x76], [0x20, 0xE0, 0x00]);
+    assert_eq!(sim.regs[0x15] & 0b1000, 0b1000);
+    // offset correction on
+    assert_eq!(sim.regs[0x16] & 0b10, 0b10);
+}
+
+#[test]
+fn imu_calibration_fine_offset() {
+    let mut bus = SimBus::new();
+    let sim = bus.lsm6dso.as_mut().unwrap();
+    sim.regs[0x1E] = 0b11;
+    sim.set_accel_raw(1639, 0, 16393);
+
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    block_on(imu::calibrate_imu(
+        &mut lsm6dso,
+        &mut SimDelay::default(),
+        ImuRate::Hz104,
+        &Mounting::IDENTITY,
+    ))
+    .unwrap();
+
+    // 100 mg in steps of 0.98 mg
+    let sim = bus.lsm6dso.as_ref().unwrap();
+    assert_eq!(sim.regs[0x73..0x76], [102, 0, 0]);
+    assert_eq!(sim.regs[0x15] & 0b1000, 0);
+}
+
+#[test]
+fn imu_calibration_not_ready() {
+    let mut bus = SimBus::new();
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut delay = SimDelay::default();
+    assert!(matches!(
+        block_on(imu::calibrate_imu(
+            &mut lsm6dso,
+            &mut delay,
+            ImuRate::Hz104,
+            &Mounting::IDENTITY,
+        )),
+        Err(lsm6dso_ner::Error::DataNotReady)
+    ));
+    assert_eq!(delay.elapsed_ns, 100_000_000);
+}
+
+#[test]
+fn imu_mounting() {
+    assert_eq!(Mounting::default(), Mounting::IDENTITY);
+    // half a turn about z, sensor x points back and y right
+    let turned = Mounting::new(SensorAxis::MinusX, SensorAxis::MinusY, SensorAxis::PlusZ).unwrap();
+    assert_eq!(turned.to_vehicle((1.0, 2.0, 3.0)), (-1.0, -2.0, 3.0));
+    assert_eq!(turned.to_sensor((1.0, 2.0, 3.0)), (-1.0, -2.0, 3.0));
+    assert_eq!(turned.gravity(), (0.0, 0.0, 9.80665));
+
+    // sensor x up, y left and z back
+    let upright = Mounting::new(SensorAxis::MinusZ, SensorAxis::PlusY, SensorAxis::PlusX).unwrap();
+    assert_eq!(upright.gravity(), (9.80665, 0.0, 0.0));
+    assert_eq!(upright.to_vehicle((9.80665, 0.0, 0.0)), (0.0, 0.0, 9.80665));
+    assert_eq!(Mounting::from_raw(upright.to_raw()), Some(upright));
+
+    // a mirror image, or an axis used twice, is not a way to bolt the board on
+    assert_eq!(
+        Mounting::new(SensorAxis::PlusX, SensorAxis::PlusY, SensorAxis::MinusZ),
+        None
+    );
+    assert_eq!(
+        Mounting::new(SensorAxis::PlusX, SensorAxis::MinusX, SensorAxis::PlusZ),
+        None
+    );
+    assert_eq!(Mounting::from_raw([0, 2, 6]), None);
+
+    for axis in upright.axes() {
+        assert_eq!(SensorAxis::parse(axis.name()), Some(axis));
+    }
+}
+
+#[test]
+fn imu_correction() {
+    let bias = lsm6dso_ner::ImuBias {
+        accelerometer: (0.0, 0.0, 0.0),
+        gyroscope: (0.01, 0.0, -0.01),
+    };
+    let turned = Mounting::new(SensorAxis::MinusX, SensorAxis::MinusY, SensorAxis::PlusZ).unwrap();
+    let mut correction = ImuCorrection::new(turned, Some(&bias));
+    let still = ImuReading {
+        time: 7,
+        accel: (0.5, 0.0, 9.8),
+        gyro: (0.011, 0.0, -0.01),
+    };
+    let corrected = correction.apply(&still);
+    assert_eq!(corrected.time, 7);
+    assert_eq!(corrected.accel, (-0.5, 0.0, 9.8));
+    assert!((corrected.gyro.0 + 0.001).abs() < 1e-5, "{corrected:?}");
+    // the still reading nudged the bias
+    assert!(correction.gyro_bias().0 > 0.01);
+
+    // turning is not taken as bias
+    let bias_before = correction.gyro_bias();
+    let turning = ImuReading {
+        gyro: (0.0, 0.0, 0.5),
+        ..still
+    };
+    let corrected = correction.apply(&turning);
+    assert_eq!(correction.gyro_bias(), bias_before);
+    assert!((corrected.gyro.2 - 0.51).abs() < 1e-6, "{corrected:?}");
+
+    // with no calibration the first still readings are averaged evenly
+    let mut correction = ImuCorrection::new(Mounting::IDENTITY, None);
+    for rate in [0.004, 0.008] {
+        correction.apply(&ImuReading {
+            gyro: (rate, 0.0, 0.0),
+            ..still
+        });
+    }
+    assert!((correction.gyro_bias().0 - 0.006).abs() < 1e-6);
+}
+
+#[test]
+fn imu_self_test() {
+    let mut bus = SimBus::new();
+    bus.lsm6dso.as_mut().unwrap().regs[0x1E] = 0b11;
+    block_on(imu::init_imu(&mut bus)).unwrap();
+    let before = bus.lsm6dso.as_ref().unwrap().regs;
+
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let mut delay = SimDelay::default();
+    let test = block_on(lsm6dso.self_test(&mut delay)).unwrap();
+    assert!(test.passed(), "{test:?}");
+    assert!((test.accelerometer_positive.change.0 - 500.0).abs() < 0.1);
+    assert!((test.accelerometer_negative.change.2 + 500.0).abs() < 0.1);
+    assert!((test.gyroscope_negative.change.1 + 300.0).abs() < 0.1);
+    // two settling times per test
+    assert_eq!(delay.elapsed_ns, 8 * 100_000_000);
+
+    // the control registers are put back and the self-test is off
+    let after = bus.lsm6dso.as_ref().unwrap().regs;
+    assert_eq!(after[0x10..0x1A], before[0x10..0x1A]);
+
+    // a gyro that does not move fails, the accelerometer still passes
+    bus.lsm6dso.as_mut().unwrap().self_test_gyro_raw = 0;
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let test = block_on(lsm6dso.self_test(&mut SimDelay::default())).unwrap();
+    assert!(test.accelerometer_passed());
+    assert!(!test.gyroscope_positive.passed && !test.gyroscope_negative.passed);
+    assert!(!test.passed());
+
+    // one moving too far fails too
+    bus.lsm6dso.as_mut().unwrap().self_test_accel_raw = 16000;
+    let mut lsm6dso = block_on(imu::init_imu(&mut bus)).unwrap();
+    let test = block_on(lsm6dso.self_test_accelerometer(
+        &mut SimDelay::default(),
+        lsm6dso_ner::SelfTestSign::Positive,
+    ))
+    .unwrap();
+    assert!(!test.passed, "{test:?}");
+}
+
+/// Passes transactions to a shared [`SimBus`] until told to fail
+#[derive(Clone, Copy)]
+struct FlakyBus<'a> {
+    bus: &'a RefCell<SimBus>,
+    fail: &'a Cell<bool>,
+}
+
+impl embedded_hal::i2c::ErrorType for FlakyBus<'_> {
+    type Error = SimError;
+}
+
+impl I2c for FlakyBus<'_> {
+    // the simulated bus never actually waits, so nothing else can want the borrow
+    #[allow(clippy::await_holding_refcell_ref)]
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [embedded_hal::i2c::Operation<'_>],
+    ) -> Result<(), SimError> {
+        if self.fail.get() {
+            return Err(SimError::InvalidWrite);
+        }
+        self.bus.borrow_mut().transaction(address, operations).await
+    }
+}
+
+#[test]
+fn imu_scale_state() {
+    let bus = RefCell::new(SimBus::new());
+    let fail = Cell::new(false);
+    let i2c = FlakyBus {
+        bus: &bus,
+        fail: &fail,
+    };
+    let mut lsm6dso = block_on(imu::init_imu(i2c)).unwrap();
+    assert_eq!(
+        block_on(lsm6dso.sensor_state()).unwrap(),
+        SensorState {
+            accelerometer_output: AccelerometerOutput::PowerDown,
+            accelerometer_scale: AccelerometerScale::G02,
+            gyroscope_output: GyroscopeOutput::PowerDown,
+            gyroscope_scale: GyroscopeFullScale::Dps250,
+        }
+    );
+
+    block_on(lsm6dso.set_accelerometer_scale(AccelerometerScale::G08)).unwrap();
+    block_on(lsm6dso.set_gyroscope_scale(GyroscopeFullScale::Dps1000)).unwrap();
+    block_on(lsm6dso.set_gyroscope_output(GyroscopeOutput::Rate52)).unwrap();
+
+    // a failed write leaves the scale unknown, so it is read back from the sensor
+    fail.set(true);
+    assert!(block_on(lsm6dso.set_gyroscope_scale(GyroscopeFullScale::Dps2000)).is_err());
+    fail.set(false);
+    bus.borrow_mut().lsm6dso.as_mut().unwrap().regs[0x11] = 0b0011_0100;
+    assert_eq!(
+        block_on(lsm6dso.read_gyroscope_scale()).unwrap(),
+        GyroscopeFullScale::Dps500
+    );
+    let state = block_on(lsm6dso.sensor_state()).unwrap();
+    assert_eq!(state.accelerometer_scale, AccelerometerScale::G08);
+    assert_eq!(state.gyroscope_output, GyroscopeOutput::Rate52);
+}
+
+#[test]
+fn imu_missing() {
+    let mut bus = SimBus {
+        lsm6dso: None,
+        ..SimBus::new()
+    };
+    assert!(matches!(
+        block_on(imu::init_imu(&mut bus)),
+        Err(lsm6dso_ner::Error::CommunicationError(SimError::NoDevice(
+            imu::LSM6DSO_ADDR
+        )))
+    ));
+}
+
+#[test]
+fn tof_payload() {
+    let mut bus = SimBus::new();
+    let sim = bus.vl6180x.as_mut().unwrap();
+    sim.range_raw = 187;
+    sim.return_rate = 3 << 7;
+    sim.return_signal_count = 900;
+    sim.return_ambient_count = 100;
+    sim.return_conv_time_us = 1200;
+
+    let mut vl6180x = block_on(tof::init_tof(&mut bus)).unwrap();
+    let (measurement, msg) =
+        block_on(tof::read_tof(&mut vl6180x, &mut SimDelay::default())).unwrap();
+
+    assert_eq!(measurement.range_mm, 187);
+    assert_eq!(measurement.raw_range, 187);
+    assert_eq!(measurement.return_rate, 3 << 7);
+    assert_eq!(measurement.return_signal_count, 900);
+    assert_eq!(measurement.return_ambient_count, 100);
+    assert_eq!(measurement.return_conv_time_us, 1200);
+    assert_eq!(measurement.quality(), RangeQuality::Valid);
+    assert_eq!(measurement.confidence(), 90);
+    assert_eq!(msg.encode().as_bytes(), [0x00, 0xBB, 0x00, 90]);
+}
+
+#[test]
+fn tof_range_error() {
+    let mut bus = SimBus::new();
+    let sim = bus.vl6180x.as_mut().unwrap();
+    // no target before max convergence time
+    sim.range_error = 0b0111;
+    sim.return_signal_count = 900;
+
+    // sent with its quality rather than dropped
+    let mut vl6180x = block_on(tof::init_tof(&mut bus)).unwrap();
+    let (measurement, msg) =
+        block_on(tof::read_tof(&mut vl6180x, &mut SimDelay::default())).unwrap();
+    assert_eq!(measurement.status, RangeStatusErrorCode::MaxConvergence);
+    assert_eq!(measurement.quality(), RangeQuality::NoTarget);
+    assert_eq!(measurement.confidence(), 0);
+    assert_eq!(msg.quality, TofQuality::NoTarget.to_raw());
+    assert_eq!(msg.confidence, 0);
+
+    // the VCSEL and PLL checks are about the sensor
+    let mut bus = SimBus::new();
+    bus.vl6180x.as_mut().unwrap().range_error = 0b0100;
+    let mut vl6180x = block_on(tof::init_tof(&mut bus)).unwrap();
+    let (measurement, msg) =
+        block_on(tof::read_tof(&mut vl6180x, &mut SimDelay::default())).unwrap();
+    assert_eq!(measurement.quality(), RangeQuality::SensorFault);
+    assert_eq!(msg.quality, TofQuality::SensorFault.to_raw());
+
+    // the range only calls it an error
+    assert!(matches!(
+        block_on(vl6180x.poll_range_mm_single_blocking(&mut SimDelay::default())),
+        Err(vl6180x_ner::Error::RangeStatusError(
+            RangeStatusErrorCode::Pll1Lock
+        ))
+    ));
+}
+
+#[test]
+fn tof_offset_calibration() {
+    let mut bus = SimBus::new();
+    let mut delay = SimDelay::default();
+    // a factory offset, which calibration replaces
+    bus.vl6180x.as_mut().unwrap().regs[0x024] = 3;
+    // the cover window makes a 50 mm target range at 57 mm
+    bus.vl6180x.as_mut().unwrap().range_raw = 57;
+
+    let mut vl6180x = block_on(tof::init_tof(&mut bus)).unwrap();
+    assert_eq!(vl6180x.range_calibration().offset_mm, 3);
+    assert_eq!(
+        block_on(vl6180x.calibrate_range_offset(50, &mut delay)),
+        Ok(-7)
+    );
+    assert_eq!(vl6180x.range_calibration().offset_mm, -7);
+    assert_eq!(
+        block_on(vl6180x.poll_range_mm_single_blocking(&mut delay)),
+        Ok(50)
+    );
+    assert_eq!(bus.vl6180x.as_ref().unwrap().range_offset(), -7);
+}
+
+#[test]
+fn tof_crosstalk_calibration() {
+    let mut bus = SimBus::new();
+    let mut delay = SimDelay::default();
+    // a 100 mm black target ranges short at 80 mm, returning 2 Mcps
+    let sim = bus.vl6180x.as_mut().unwrap();
+    sim.range_raw = 80;
+    sim.return_rate = 2 << 7;
+
+    let mut vl6180x = block_on(tof::init_tof(&mut bus)).unwrap();
+    assert_eq!(
+        block_on(vl6180x.calibrate_crosstalk(100, &mut delay)),
+        Ok(51)
+    );
+    assert_eq!(vl6180x.range_calibration().crosstalk_rate, 51);
+    assert_eq!(
+        block_on(vl6180x.calibrate_crosstalk(0, &mut delay)),
+        Err(vl6180x_ner::Error::InvalidConfigurationValue(0))
+    );
+
+    // reading long needs no compensation
+    bus.vl6180x.as_mut().unwrap().range_raw = 120;
+    let mut vl6180x = block_on(tof::init_tof(&mut bus)).unwrap();
+    assert_eq!(
+        block_on(vl6180x.calibrate_crosstalk(100, &mut delay)),
+        Ok(0)
+    );
+    assert_eq!(bus.vl6180x.as_ref().unwrap().crosstalk_rate(), 0);
+}
+
+#[test]
+fn tof_calibration_restore() {
+    let calibration = vl6180x_ner::RangeCalibration {
+        offset_mm: -7,
+        crosstalk_rate: 51,
+    };
+    let bytes = calibration.to_bytes();
+    assert_eq!(bytes, [0xF9, 51, 0]);
+    assert_eq!(
+        vl6180x_ner::RangeCalibration::from_bytes(bytes),
+        calibration
+    );
+
+    // applied at init after the sensor lost it in a power cycle
+    let mut bus = SimBus::new();
+    let mut config = vl6180x_ner::Config::new();
+    config.set_range_calibration(calibration);
+    let vl6180x = block_on(vl6180x_ner::VL6180X::with_config(&mut bus, &config)).unwrap();
+    assert_eq!(vl6180x.range_calibration(), calibration);
+    let sim = bus.vl6180x.as_ref().unwrap();
+    assert_eq!(sim.range_offset(), -7);
+    assert_eq!(sim.crosstalk_rate(), 51);
+}
+
+/// XSHUT of one of the VL6180X on a shared [`SimBus`], 0 is `vl6180x` and the rest are
+/// `more_vl6180x`
+#[derive(Clone, Copy)]
+struct SimXshut<'a> {
+    bus: &'a RefCell<SimBus>,
+    index: usize,
+}
+
+fn sim_vl6180x(bus: &mut SimBus, index: usize) -> Option<&mut SimVl6180x> {
+    match index {
+        0 => bus.vl6180x.as_mut(),
+        _ => bus.more_vl6180x[index - 1].as_mut(),
+    }
+}
+
+impl embedded_hal::digital::ErrorType for SimXshut<'_> {
+    type Error = Infallible;
+}
+
+impl OutputPin for SimXshut<'_> {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        if let Some(sim) = sim_vl6180x(&mut self.bus.borrow_mut(), self.index) {
+            sim.set_xshut(false);
+        }
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        if let Some(sim) = sim_vl6180x(&mut self.bus.borrow_mut(), self.index) {
+            sim.set_xshut(true);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn tof_multiple() {
+    let mut sim = SimBus::new();
+    sim.more_vl6180x = [Some(SimVl6180x::new()), Some(SimVl6180x::new())];
+    for (index, range) in [100, 150, 200].into_iter().enumerate() {
+        sim_vl6180x(&mut sim, index).unwrap().range_raw = range;
+    }
+    let bus = RefCell::new(sim);
+    let fail = Cell::new(false);
+    let i2c = [FlakyBus {
+        bus: &bus,
+        fail: &fail,
+    }; 3];
+    let xshut = [0, 1, 2].map(|index| SimXshut { bus: &bus, index });
+    let config = vl6180x_ner::Config::new();
+    let mut delay = SimDelay::default();
+
+    // all three answer at the default address until they are sequenced
+    assert_eq!(
+        block_on(tof::init_tof(i2c[0])).err(),
+        Some(vl6180x_ner::Error::BusError(SimError::Contention(0x29)))
+    );
+
+    assert!(MultiVL6180X::new(i2c, xshut, [0x30, 0x31, 0x30], &config).is_err());
+    assert!(MultiVL6180X::new(i2c, xshut, [0x30, 0x29, 0x31], &config).is_err());
+    let mut tofs = MultiVL6180X::new(i2c, xshut, [0x30, 0x31, 0x32], &config).unwrap();
+    block_on(tofs.power_on_all(&mut delay)).unwrap();
+    for (index, address) in [0x30, 0x31, 0x32].into_iter().enumerate() {
+        assert!(tofs.is_powered(index));
+        assert_eq!(tofs.address(index), address);
+        let mut bus = bus.borrow_mut();
+        assert_eq!(sim_vl6180x(&mut bus, index).unwrap().address(), address);
+    }
+    let ranges: Vec<_> = tofs
+        .iter_mut()
+        .map(|tof| block_on(tof.try_poll_range_mm_single_blocking(&mut delay)).unwrap())
+        .collect();
+    assert_eq!(ranges, [100, 150, 200]);
+
+    // a brown out puts one back at the default address, until it is restarted
+    {
+        let mut bus = bus.borrow_mut();
+        let sim = sim_vl6180x(&mut bus, 1).unwrap();
+        sim.set_xshut(false);
+        sim.set_xshut(true);
+    }
+    let tof = tofs.get_mut(1).unwrap();
+    assert_eq!(
+        block_on(tof.try_poll_range_mm_single_blocking(&mut delay)),
+        Err(vl6180x_ner::Error::BusError(SimError::NoDevice(0x31)))
+    );
+    block_on(tofs.restart(1, &mut delay)).unwrap();
+    let tof = tofs.get_mut(1).unwrap();
+    assert_eq!(
+        block_on(tof.try_poll_range_mm_single_blocking(&mut delay)),
+        Ok(150)
+    );
+
+    // one that never boots is left in shutdown, and the others still come up
+    bus.borrow_mut().more_vl6180x[1] = None;
+    let mut delay = SimDelay::default();
+    assert_eq!(
+        block_on(tofs.power_on_all(&mut delay)),
+        Err(vl6180x_ner::Error2::Timeout)
+    );
+    assert!(tofs.is_powered(0) && tofs.is_powered(1) && !tofs.is_powered(2));
+    // waited out the poll timeout, after holding XSHUT low
+    assert_eq!(delay.elapsed_ns, 1_001_000_000);
+}
+
+/// GPIO1 of the VL6180X on a shared [`SimBus`]. Waiting lets a ranging period pass, which can
+/// only raise it, it goes low again when the sample is read over I2C.
+struct SimGpio1<'a> {
+    bus: &'a RefCell<SimBus>,
+}
+
+impl SimGpio1<'_> {
+    fn level(&self) -> bool {
+        self.bus.borrow().vl6180x.as_ref().unwrap().gpio1()
+    }
+}
+
+impl embedded_hal::digital::ErrorType for SimGpio1<'_> {
+    type Error = Infallible;
+}
+
+impl Wait for SimGpio1<'_> {
+    async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+        if !self.level() {
+            let mut bus = self.bus.borrow_mut();
+            bus.vl6180x.as_mut().unwrap().range_period_elapsed();
+        }
+        assert!(self.level(), "GPIO1 would never go high");
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+        assert!(!self.level(), "GPIO1 would never go low");
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+        assert!(!self.level(), "GPIO1 would never rise");
+        self.wait_for_high().await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+        panic!("GPIO1 would never fall");
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+        self.wait_for_rising_edge().await
+    }
+}
+
+/// A clock for the VL6180X on a shared [`SimBus`], ranging periods pass as it is delayed
+struct SimClock<'a> {
+    bus: &'a RefCell<SimBus>,
+    elapsed_ms: u32,
+}
+
+impl DelayNs for SimClock<'_> {
+    async fn delay_ns(&mut self, ns: u32) {
+        let mut bus = self.bus.borrow_mut();
+        let sim = bus.vl6180x.as_mut().unwrap();
+        let period = sim.range_period_ms() as u32;
+        let before = self.elapsed_ms / period;
+        self.elapsed_ms += ns / 1_000_000;
+        for _ in before..self.elapsed_ms / period {
+            sim.range_period_elapsed();
+        }
+    }
+}
+
+#[test]
+fn tof_blocking_read() {
+    let bus = RefCell::new(SimBus::new());
+    let fail = Cell::new(false);
+    let i2c = FlakyBus {
+        bus: &bus,
+        fail: &fail,
+    };
+    let mut clock = SimClock {
+        bus: &bus,
+        elapsed_ms: 0,
+    };
+
+    let mut vl6180x = block_on(tof::start_tof_continuous(i2c, 100)).unwrap();
+    assert_eq!(
+        block_on(vl6180x.read_range_mm_blocking(&mut clock)),
+        Ok(100)
+    );
+    // polled every quarter period until the first sample
+    assert_eq!(clock.elapsed_ms, 100);
+}
+
+#[test]
+fn tof_poll() {
+    let bus = RefCell::new(SimBus::new());
+    let fail = Cell::new(false);
+    let i2c = FlakyBus {
+        bus: &bus,
+        fail: &fail,
+    };
+    let mut clock = SimClock {
+        bus: &bus,
+        elapsed_ms: 0,
+    };
+
+    let mut vl6180x = block_on(tof::start_tof_continuous(i2c, 100)).unwrap();
+    let (measurement, msg) = block_on(tof::poll_tof(&mut vl6180x, &mut clock)).unwrap();
+    assert_eq!(measurement.range_mm, 100);
+    assert_eq!(msg.encode().as_bytes(), [0x00, 0x64, 0x00, 0x00]);
+    assert_eq!(clock.elapsed_ms, 100);
+
+    // reading the sample cleared it, so the next one waits for the next period
+    bus.borrow_mut().vl6180x.as_mut().unwrap().range_raw = 187;
+    let (measurement, _) = block_on(tof::poll_tof(&mut vl6180x, &mut clock)).unwrap();
+    assert_eq!(measurement.range_mm, 187);
+    assert_eq!(clock.elapsed_ms, 200);
+
+    fail.set(true);
+    assert_eq!(
+        block_on(tof::poll_tof(&mut vl6180x, &mut clock)).err(),
+        Some(vl6180x_ner::Error::BusError(SimError::InvalidWrite))
+    );
+}
+
+#[test]
+fn tof_blocking_timeout() {
+    let mut bus = SimBus::new();
+    let mut delay = SimDelay::default();
+
+    // continuous ranging that never gets a period to measure in, given up after three periods
+    let mut vl6180x = block_on(tof::start_tof_continuous(&mut bus, 100)).unwrap();
+    assert_eq!(
+        block_on(tof::poll_tof(&mut vl6180x, &mut delay)).err(),
+        Some(vl6180x_ner::Error::Timeout)
+    );
+    assert_eq!(delay.elapsed_ns, 300_000_000);
+}
+
+#[test]
+fn tof_blocking_timeout_config() {
+    let mut bus = SimBus::new();
+    let mut delay = SimDelay::default();
+
+    let mut config = vl6180x_ner::Config::new();
+    assert!(config.set_poll_interval_ms(0).is_err());
+    config.set_poll_interval_ms(10).unwrap();
+    config.set_poll_timeout_ms(250);
+    let vl6180x = block_on(vl6180x_ner::VL6180X::with_config(&mut bus, &config)).unwrap();
+    let mut vl6180x = block_on(vl6180x.start_range_continuous_mode()).unwrap();
+    assert_eq!(
+        block_on(vl6180x.read_range_mm_blocking(&mut delay)),
+        Err(vl6180x_ner::Error::Timeout)
+    );
+    assert_eq!(delay.elapsed_ns, 250_000_000);
+}
+
+#[test]
+fn tof_continuous_rate() {
+    assert_eq!(tof::continuous_rate(500), (500, 1));
+    assert_eq!(tof::continuous_rate(505), (500, 1));
+    assert_eq!(tof::continuous_rate(95), (90, 1));
+    assert_eq!(tof::continuous_rate(20), (tof::CONTINUOUS_MIN_MS, 1));
+    assert_eq!(tof::continuous_rate(60_000), (tof::CONTINUOUS_MAX_MS, 23));
+}
+
+#[test]
+fn tof_continuous() {
+    let bus = RefCell::new(SimBus::new());
+    let fail = Cell::new(false);
+    let i2c = FlakyBus {
+        bus: &bus,
+        fail: &fail,
+    };
+    let mut gpio1 = SimGpio1 { bus: &bus };
+
+    let mut vl6180x = block_on(tof::start_tof_continuous(i2c, 100)).unwrap();
+    {
+        let bus = bus.borrow();
+        let sim = bus.vl6180x.as_ref().unwrap();
+        assert!(sim.ranging());
+        assert_eq!(sim.range_period_ms(), 100);
+    }
+    assert!(!gpio1.level());
+
+    let (measurement, msg) = block_on(tof::wait_tof(&mut vl6180x, &mut gpio1)).unwrap();
+    assert_eq!(measurement.range_mm, 100);
+    assert_eq!(msg.encode().as_bytes(), [0x00, 0x64, 0x00, 0x00]);
+    // reading the sample released GPIO1
+    block_on(gpio1.wait_for_low()).unwrap();
+
+    bus.borrow_mut().vl6180x.as_mut().unwrap().range_raw = 187;
+    let (measurement, _) = block_on(tof::wait_tof(&mut vl6180x, &mut gpio1)).unwrap();
+    assert_eq!(measurement.range_mm, 187);
+
+    // too much ambient light
+    bus.borrow_mut().vl6180x.as_mut().unwrap().range_error = 0b1011;
+    let (measurement, msg) = block_on(tof::wait_tof(&mut vl6180x, &mut gpio1)).unwrap();
+    assert_eq!(measurement.quality(), RangeQuality::Noisy);
+    assert_eq!(msg.quality, TofQuality::Noisy.to_raw());
+    assert!(!gpio1.level());
+    block_on(gpio1.wait_for_rising_edge()).unwrap();
+    assert!(gpio1.level());
+
+    block_on(vl6180x.stop_range_continuous_mode()).unwrap();
+    assert!(!bus.borrow().vl6180x.as_ref().unwrap().ranging());
+
+    assert_eq!(
+        block_on(tof::start_tof_continuous(&mut SimBus::new(), 55)).err(),
+        Some(vl6180x_ner::Error::InvalidConfigurationValue(55))
+    );
+}
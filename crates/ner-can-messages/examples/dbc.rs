@@ -0,0 +1,7 @@
+//! Print the DBC for every message, see the README for regenerating `ner.dbc`
+
+fn main() {
+    let mut dbc = String::new();
+    ner_can_messages::dbc::write_dbc(&mut dbc).unwrap();
+    print!("{dbc}");
+}
@@ -0,0 +1,53 @@
+//! Messages sent by Cerberus
+
+use crate::{can_message, Signal};
+
+can_message! {
+    /// Most recent fault, sent periodically
+    pub struct CerberusStatus {
+        id: 0x502,
+        dlc: 5,
+        transmitter: Cerberus,
+        per_location: false,
+        signals: {
+            fault_code: u32 = Signal::big_endian(0, 32),
+            /// 1 (Defcon1, most severe) to 5 (Defcon5, faults clear)
+            severity: u8 = Signal::big_endian(4, 8),
+        }
+    }
+}
+
+can_message! {
+    /// Low voltage battery sense
+    pub struct LvSense {
+        id: 0x503,
+        dlc: 4,
+        transmitter: Cerberus,
+        per_location: false,
+        signals: {
+            /// ADC counts multiplied by the divider ratio and 10
+            lv_sense: u32 = Signal::big_endian(0, 32),
+        }
+    }
+}
+
+can_message! {
+    /// Fuse states read from the control expander, true is a good fuse
+    pub struct FuseStatus {
+        id: 0x111,
+        dlc: 2,
+        transmitter: Cerberus,
+        per_location: false,
+        signals: {
+            battbox: bool = Signal::bit(0, 7),
+            lvbox: bool = Signal::bit(0, 6),
+            fan_radiator: bool = Signal::bit(0, 5),
+            mc: bool = Signal::bit(0, 4),
+            fan_battbox: bool = Signal::bit(0, 3),
+            pump: bool = Signal::bit(0, 2),
+            dashboard: bool = Signal::bit(0, 1),
+            brakelight: bool = Signal::bit(0, 0),
+            brb: bool = Signal::bit(1, 7),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! DBC export of [`MESSAGES`], for telemetry and logging tools
+//!
+//! Per location messages are written once for every [`DeviceLocation`], named
+//! `<message>_<location>` and with the location ID offset applied.
+
+use core::fmt::{Result, Write};
+
+use crate::{msb::DeviceLocation, ByteOrder, MessageDef, Signal, MESSAGES};
+
+/// Nodes listed in `BU_`, in the order they are written
+const NODES: [crate::Node; 5] = [
+    crate::Node::Msb,
+    crate::Node::Cerberus,
+    crate::Node::Wheel,
+    crate::Node::Dti,
+    crate::Node::Bms,
+];
+
+/// Write a DBC describing every message in [`MESSAGES`]
+pub fn write_dbc<W: Write>(w: &mut W) -> Result {
+    writeln!(w, "VERSION \"\"")?;
+    writeln!(w)?;
+    writeln!(w, "NS_ :")?;
+    writeln!(w)?;
+    writeln!(w, "BS_:")?;
+    writeln!(w)?;
+    write!(w, "BU_:")?;
+    for node in NODES {
+        write!(w, " {}", node.name())?;
+    }
+    writeln!(w)?;
+
+    for_each_frame(|def, id, loc| {
+        writeln!(w)?;
+        write!(w, "BO_ {} {}", id, def.name)?;
+        if let Some(loc) = loc {
+            write!(w, "_{}", loc.name())?;
+        }
+        writeln!(w, ": {} {}", def.dlc, def.transmitter.name())?;
+        for signal in def.signals {
+            write_signal(w, signal)?;
+        }
+        Ok(())
+    })?;
+
+    writeln!(w)?;
+    for_each_frame(|def, id, _| {
+        if !def.comment.is_empty() {
+            writeln!(w, "CM_ BO_ {} \"{}\";", id, def.comment.trim())?;
+        }
+        for signal in def.signals.iter().filter(|s| !s.comment.is_empty()) {
+            writeln!(
+                w,
+                "CM_ SG_ {} {} \"{}\";",
+                id,
+                signal.name,
+                signal.comment.trim()
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Call `f` with every frame on the bus, expanding per location messages
+fn for_each_frame(mut f: impl FnMut(&MessageDef, u16, Option<DeviceLocation>) -> Result) -> Result {
+    for def in MESSAGES {
+        if def.per_location {
+            for loc in DeviceLocation::ALL {
+                f(def, loc.can_id(def.id), Some(loc))?;
+            }
+        } else {
+            f(def, def.id, None)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_signal<W: Write>(w: &mut W, signal: &Signal) -> Result {
+    write!(
+        w,
+        " SG_ {} : {}|{}@{}{} ({},{}) [",
+        signal.name,
+        signal.start_bit,
+        signal.length,
+        match signal.byte_order {
+            ByteOrder::BigEndian => 0,
+            ByteOrder::LittleEndian => 1,
+        },
+        if signal.signed { '-' } else { '+' },
+        signal.scale,
+        signal.offset,
+    )?;
+    write_physical(w, signal, signal.min_raw())?;
+    write!(w, "|")?;
+    write_physical(w, signal, signal.max_raw())?;
+    writeln!(w, "] \"{}\" Vector__XXX", signal.unit)
+}
+
+/// Write the physical value of a raw value, to as many decimals as the scale and offset have
+fn write_physical<W: Write>(w: &mut W, signal: &Signal, raw: i64) -> Result {
+    let value = raw as f64 * signal.scale as f64 + signal.offset as f64;
+    let decimals = decimals(signal.scale).max(decimals(signal.offset));
+    write!(w, "{:.*}", decimals, value)
+}
+
+/// Decimal places `value` was written with, ignoring the f32 representation error
+fn decimals(value: f32) -> usize {
+    let mut shifted = if value < 0.0 { -value } else { value } as f64;
+    for decimals in 0..9 {
+        let frac = shifted - (shifted as u64) as f64;
+        let tolerance = shifted.max(1.0) * f32::EPSILON as f64;
+        if frac < tolerance || 1.0 - frac < tolerance {
+            return decimals;
+        }
+        shifted *= 10.0;
+    }
+    9
+}
@@ -0,0 +1,28 @@
+//! Messages from boards we don't write the firmware for, that our firmware consumes
+
+use crate::{can_message, Signal};
+
+can_message! {
+    /// DTI motor controller speed, the duty cycle and input voltage that follow are unused
+    pub struct DtiErpm {
+        id: 0x416,
+        dlc: 8,
+        transmitter: Dti,
+        per_location: false,
+        signals: {
+            /// Electrical RPM, divide by the motor pole pairs for mechanical RPM
+            erpm: i32 = Signal::big_endian(0, 32).signed().unit("rpm"),
+        }
+    }
+}
+
+can_message! {
+    /// BMS discharge and charge current limits, Cerberus only monitors its arrival
+    pub struct BmsCurrentLimits {
+        id: 0x156,
+        dlc: 0,
+        transmitter: Bms,
+        per_location: false,
+        signals: {}
+    }
+}
@@ -0,0 +1,192 @@
+#![no_std]
+//! CAN message definitions shared by every NER firmware
+//!
+//! Each message is a typed struct declared with [`can_message!`], which also produces a
+//! [`MessageDef`] describing the exact wire layout. Encoding, decoding and any tooling built on
+//! the definitions therefore always agree.
+
+mod signal;
+
+pub mod cerberus;
+pub mod dbc;
+pub mod external;
+pub mod msb;
+pub mod wheel;
+
+pub use signal::{ByteOrder, Signal, SignalValue};
+
+/// Boards on the bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Node {
+    Msb,
+    Cerberus,
+    Wheel,
+    Dti,
+    Bms,
+}
+
+impl Node {
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Node::Msb => "MSB",
+            Node::Cerberus => "Cerberus",
+            Node::Wheel => "Wheel",
+            Node::Dti => "DTI",
+            Node::Bms => "BMS",
+        }
+    }
+}
+
+/// Wire layout of a message
+#[derive(Debug, Clone, Copy)]
+pub struct MessageDef {
+    pub name: &'static str,
+    pub comment: &'static str,
+    /// Standard 11 bit ID. For per location messages this is the front left ID.
+    pub id: u16,
+    pub dlc: u8,
+    pub transmitter: Node,
+    /// Sent by every MSB, offset by its [`msb::DeviceLocation`]
+    pub per_location: bool,
+    pub signals: &'static [Signal],
+}
+
+/// Every message defined in this crate
+pub const MESSAGES: &[MessageDef] = &[
+    msb::MsbTemperature::DEF,
+    msb::MsbAccel::DEF,
+    msb::MsbGyro::DEF,
+    msb::MsbShockpot::DEF,
+    msb::MsbStrain::DEF,
+    msb::MsbTof::DEF,
+    msb::MsbShockSamples::DEF,
+    msb::MsbShockStats::DEF,
+    msb::MsbShockHistogram::DEF,
+    msb::MsbImuEvent::DEF,
+    msb::MsbDiagnostic::DEF,
+    msb::MsbInventory::DEF,
+    msb::MsbCommand::DEF,
+    cerberus::CerberusStatus::DEF,
+    cerberus::LvSense::DEF,
+    cerberus::FuseStatus::DEF,
+    wheel::WheelButtons::DEF,
+    external::DtiErpm::DEF,
+    external::BmsCurrentLimits::DEF,
+];
+
+/// Encoded payload, at most 8 bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Payload {
+    data: [u8; 8],
+    len: u8,
+}
+
+impl Payload {
+    pub const fn new(len: u8) -> Self {
+        Self { data: [0; 8], len }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..self.len as usize]
+    }
+}
+
+/// Errors decoding a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DecodeError {
+    /// Payload is shorter than the DLC of the message, argument is the received length
+    TooShort(usize),
+}
+
+/// A typed CAN message
+pub trait CanMessage: Sized {
+    const DEF: MessageDef;
+
+    fn encode(&self) -> Payload;
+
+    fn decode(data: &[u8]) -> Result<Self, DecodeError>;
+}
+
+/// Declare a message struct, its [`MessageDef`] and its [`CanMessage`] impl.
+/// Doc comments become the message and signal comments.
+macro_rules! can_message {
+    (
+        $(#[doc = $doc:literal])*
+        pub struct $name:ident {
+            id: $id:expr,
+            dlc: $dlc:expr,
+            transmitter: $tx:ident,
+            per_location: $per_location:expr,
+            signals: {
+                $(
+                    $(#[doc = $sdoc:literal])*
+                    $field:ident: $ty:ty = $signal:expr,
+                )*
+            }
+        }
+    ) => {
+        $(#[doc = $doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, defmt::Format)]
+        pub struct $name {
+            $(
+                $(#[doc = $sdoc])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl $crate::CanMessage for $name {
+            const DEF: $crate::MessageDef = $crate::MessageDef {
+                name: stringify!($name),
+                comment: concat!($($doc),*),
+                id: $id,
+                dlc: $dlc,
+                transmitter: $crate::Node::$tx,
+                per_location: $per_location,
+                signals: &[
+                    $(
+                        $crate::Signal {
+                            name: stringify!($field),
+                            comment: concat!($($sdoc),*),
+                            ..$signal
+                        },
+                    )*
+                ],
+            };
+
+            #[allow(unused_mut, unused_variables)]
+            fn encode(&self) -> $crate::Payload {
+                let mut payload = $crate::Payload::new(Self::DEF.dlc);
+                let mut signals = Self::DEF.signals.iter();
+                $(
+                    let signal = signals.next().unwrap();
+                    signal.insert(
+                        payload.as_bytes_mut(),
+                        $crate::SignalValue::to_raw(self.$field, signal),
+                    );
+                )*
+                payload
+            }
+
+            #[allow(unused_mut, unused_variables)]
+            fn decode(data: &[u8]) -> Result<Self, $crate::DecodeError> {
+                if data.len() < Self::DEF.dlc as usize {
+                    return Err($crate::DecodeError::TooShort(data.len()));
+                }
+                let mut signals = Self::DEF.signals.iter();
+                Ok(Self {
+                    $(
+                        $field: {
+                            let signal = signals.next().unwrap();
+                            <$ty as $crate::SignalValue>::from_raw(signal.extract(data), signal)
+                        },
+                    )*
+                })
+            }
+        }
+    };
+}
+pub(crate) use can_message;
@@ -0,0 +1,614 @@
+//! Messages sent by the mechanical sensor boards
+
+use crate::{can_message, Signal};
+
+/// Corner of the car an MSB is mounted at, set by its address pins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DeviceLocation {
+    FrontLeft,
+    BackLeft,
+    BackRight,
+    FrontRight,
+}
+
+impl DeviceLocation {
+    pub const ALL: [DeviceLocation; 4] = [
+        DeviceLocation::FrontLeft,
+        DeviceLocation::FrontRight,
+        DeviceLocation::BackLeft,
+        DeviceLocation::BackRight,
+    ];
+
+    /// Added to the base ID of every MSB message
+    pub const fn id_offset(&self) -> u16 {
+        match self {
+            DeviceLocation::FrontLeft => 0x00,
+            DeviceLocation::FrontRight => 0x20,
+            DeviceLocation::BackLeft => 0x40,
+            DeviceLocation::BackRight => 0x60,
+        }
+    }
+
+    /// The ID a message with the given base ID is sent with from this location
+    pub const fn can_id(&self, base_id: u16) -> u16 {
+        base_id + self.id_offset()
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            DeviceLocation::FrontLeft => "FrontLeft",
+            DeviceLocation::FrontRight => "FrontRight",
+            DeviceLocation::BackLeft => "BackLeft",
+            DeviceLocation::BackRight => "BackRight",
+        }
+    }
+}
+
+impl From<(bool, bool, bool)> for DeviceLocation {
+    fn from(value: (bool, bool, bool)) -> Self {
+        if value.0 && value.1 {
+            DeviceLocation::FrontLeft
+        } else if value.0 && !value.1 {
+            DeviceLocation::FrontRight
+        } else if !value.1 && value.2 {
+            DeviceLocation::BackLeft
+        } else if !value.0 && !value.2 {
+            DeviceLocation::BackRight
+        } else {
+            DeviceLocation::FrontLeft
+        }
+    }
+}
+
+can_message! {
+    /// SHT30 temperature and humidity
+    pub struct MsbTemperature {
+        id: 0x602,
+        dlc: 4,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            temperature: f32 = Signal::big_endian(0, 16).signed().scale(0.01).unit("degC"),
+            humidity: f32 = Signal::big_endian(2, 16).scale(0.01).unit("%RH"),
+        }
+    }
+}
+
+can_message! {
+    /// LSM6DSO acceleration, vehicle frame with x forward, y left and z up
+    pub struct MsbAccel {
+        id: 0x603,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            accel_x: f32 = Signal::big_endian(0, 16).signed().scale(0.001).unit("m/s^2"),
+            accel_y: f32 = Signal::big_endian(2, 16).signed().scale(0.001).unit("m/s^2"),
+            accel_z: f32 = Signal::big_endian(4, 16).signed().scale(0.001).unit("m/s^2"),
+            /// MSB clock when sampled, from the LSM6DSO timestamp, wrapping. Matches the `MsbGyro` of the
+            /// same sample.
+            time: u16 = Signal::big_endian(6, 16).unit("ms"),
+        }
+    }
+}
+
+can_message! {
+    /// LSM6DSO angular rate, bias corrected, vehicle frame with x forward, y left and z up
+    pub struct MsbGyro {
+        id: 0x604,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            gyro_x: f32 = Signal::big_endian(0, 16).signed().scale(0.001).unit("rad/s"),
+            gyro_y: f32 = Signal::big_endian(2, 16).signed().scale(0.001).unit("rad/s"),
+            gyro_z: f32 = Signal::big_endian(4, 16).signed().scale(0.001).unit("rad/s"),
+            /// MSB clock when sampled, from the LSM6DSO timestamp, wrapping. Matches the `MsbAccel` of
+            /// the same sample.
+            time: u16 = Signal::big_endian(6, 16).unit("ms"),
+        }
+    }
+}
+
+can_message! {
+    /// Shock potentiometer on PA0, averaged over one DMA buffer
+    pub struct MsbShockpot {
+        id: 0x605,
+        dlc: 4,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// Travel from the calibrated zero
+            travel: f32 = Signal::big_endian(0, 16).signed().scale(0.01).unit("mm"),
+            /// Averaged 12 bit ADC counts, for calibrating
+            shockpot_raw: u16 = Signal::big_endian(2, 16),
+        }
+    }
+}
+
+can_message! {
+    /// Strain gauges on PA5 and PA6, averaged over one DMA buffer
+    pub struct MsbStrain {
+        id: 0x606,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            strain_1: f32 = Signal::big_endian(0, 16).signed().unit("ue"),
+            strain_2: f32 = Signal::big_endian(2, 16).signed().unit("ue"),
+            /// Averaged 12 bit ADC counts, for calibrating
+            strain_1_raw: u16 = Signal::big_endian(4, 16),
+            /// Averaged 12 bit ADC counts, for calibrating
+            strain_2_raw: u16 = Signal::big_endian(6, 16),
+        }
+    }
+}
+
+can_message! {
+    /// VL6180X range, sent for every sample with how far it can be trusted
+    pub struct MsbTof {
+        id: 0x607,
+        dlc: 4,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// Only meaningful when `quality` is valid
+            range: u16 = Signal::big_endian(0, 16).unit("mm"),
+            /// 0 valid, 1 no target, 2 noisy, 3 too close, 4 too far, 5 sensor fault
+            quality: u8 = Signal::big_endian(2, 8),
+            /// Share of the returned signal that was the target rather than ambient light, 0
+            /// unless `quality` is valid
+            confidence: u8 = Signal::big_endian(3, 8).unit("%"),
+        }
+    }
+}
+
+/// How far the range in an [`MsbTof`] can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum TofQuality {
+    Valid,
+    /// Too little of the signal came back, nothing is in range or the target is too dark
+    NoTarget,
+    /// Ambient light drowned out the signal
+    Noisy,
+    /// Closer than the sensor can range
+    TooClose,
+    /// Further than the sensor can range
+    TooFar,
+    /// The sensor could not measure, and needs attention if it keeps happening
+    SensorFault,
+}
+
+impl TofQuality {
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(TofQuality::Valid),
+            1 => Some(TofQuality::NoTarget),
+            2 => Some(TofQuality::Noisy),
+            3 => Some(TofQuality::TooClose),
+            4 => Some(TofQuality::TooFar),
+            5 => Some(TofQuality::SensorFault),
+            _ => None,
+        }
+    }
+
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            TofQuality::Valid => 0,
+            TofQuality::NoTarget => 1,
+            TofQuality::Noisy => 2,
+            TofQuality::TooClose => 3,
+            TofQuality::TooFar => 4,
+            TofQuality::SensorFault => 5,
+        }
+    }
+}
+
+can_message! {
+    /// Shock pot travel sampled at the capture rate, sent in raw capture mode
+    pub struct MsbShockSamples {
+        id: 0x608,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// Counts frames since capture started, wrapping. The first sample of a frame is
+            /// sample number `3 * sequence`, so dropped frames show up as gaps.
+            sequence: u16 = Signal::big_endian(0, 16),
+            sample_1: f32 = Signal::big_endian(2, 16).signed().scale(0.01).unit("mm"),
+            sample_2: f32 = Signal::big_endian(4, 16).signed().scale(0.01).unit("mm"),
+            sample_3: f32 = Signal::big_endian(6, 16).signed().scale(0.01).unit("mm"),
+        }
+    }
+}
+
+can_message! {
+    /// Shock travel and damper velocity extremes over a summary window
+    pub struct MsbShockStats {
+        id: 0x609,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            position_min: f32 = Signal::big_endian(0, 16).signed().scale(0.01).unit("mm"),
+            position_max: f32 = Signal::big_endian(2, 16).signed().scale(0.01).unit("mm"),
+            /// Most negative velocity, rebound
+            velocity_min: f32 = Signal::big_endian(4, 16).signed().unit("mm/s"),
+            /// Most positive velocity, bump
+            velocity_max: f32 = Signal::big_endian(6, 16).signed().unit("mm/s"),
+        }
+    }
+}
+
+can_message! {
+    /// Six bins of a shock histogram over a summary window, four frames make up both histograms
+    pub struct MsbShockHistogram {
+        id: 0x60A,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// Counts summary windows, the same in all four frames of a window
+            sequence: u8 = Signal::big_endian(0, 8),
+            /// 0 and 1 position bins 0-5 and 6-11, 2 and 3 velocity bins 0-5 and 6-11
+            part: u8 = Signal::big_endian(1, 8),
+            bin_1: f32 = Signal::big_endian(2, 8).scale(0.5).unit("%"),
+            bin_2: f32 = Signal::big_endian(3, 8).scale(0.5).unit("%"),
+            bin_3: f32 = Signal::big_endian(4, 8).scale(0.5).unit("%"),
+            bin_4: f32 = Signal::big_endian(5, 8).scale(0.5).unit("%"),
+            bin_5: f32 = Signal::big_endian(6, 8).scale(0.5).unit("%"),
+            bin_6: f32 = Signal::big_endian(7, 8).scale(0.5).unit("%"),
+        }
+    }
+}
+
+can_message! {
+    /// An event detected by the LSM6DSO, sent once when it is noticed
+    pub struct MsbImuEvent {
+        id: 0x60B,
+        dlc: 4,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// 0 impact, 1 free fall
+            event: u8 = Signal::big_endian(0, 8),
+            /// Sensor axes the impact was on, bit 0 x, bit 1 y, bit 2 z, and bit 3 set if negative
+            axes: u8 = Signal::big_endian(1, 8),
+            /// MSB clock when noticed, wrapping, the same clock as `MsbAccel`
+            time: u16 = Signal::big_endian(2, 16).unit("ms"),
+        }
+    }
+}
+
+/// What an [`MsbImuEvent`] reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ImuEvent {
+    /// A sharp acceleration spike, such as a curb strike or a crash
+    Impact,
+    /// Every axis near zero g, the car is airborne or tumbling
+    FreeFall,
+}
+
+impl ImuEvent {
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(ImuEvent::Impact),
+            1 => Some(ImuEvent::FreeFall),
+            _ => None,
+        }
+    }
+
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            ImuEvent::Impact => 0,
+            ImuEvent::FreeFall => 1,
+        }
+    }
+}
+
+can_message! {
+    /// Health of the sensors on an MSB, sent every second
+    pub struct MsbDiagnostic {
+        id: 0x60C,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// SHT30, 0 ok, 1 degraded, 2 failed, 3 not present
+            temperature_state: u8 = Signal::little_endian(0, 4),
+            /// SHT30 last fault, 0 none, 1 no acknowledge, 2 bus, 3 CRC, 4 timeout, 5 wrong
+            /// device, 6 self-test, 7 other
+            temperature_fault: u8 = Signal::little_endian(4, 4),
+            /// SHT30 failed readings or initializations in a row, saturating
+            temperature_errors: u8 = Signal::big_endian(1, 8),
+            /// LSM6DSO, as `temperature_state`
+            imu_state: u8 = Signal::little_endian(16, 4),
+            /// LSM6DSO, as `temperature_fault`
+            imu_fault: u8 = Signal::little_endian(20, 4),
+            /// LSM6DSO, as `temperature_errors`
+            imu_errors: u8 = Signal::big_endian(3, 8),
+            /// VL6180X, as `temperature_state`
+            tof_state: u8 = Signal::little_endian(32, 4),
+            /// VL6180X, as `temperature_fault`
+            tof_fault: u8 = Signal::little_endian(36, 4),
+            /// VL6180X, as `temperature_errors`
+            tof_errors: u8 = Signal::big_endian(5, 8),
+            /// Time since boot, wrapping, a jump back means the MSB reset
+            uptime: u16 = Signal::big_endian(6, 16).unit("s"),
+        }
+    }
+}
+
+/// State of a sensor in an [`MsbDiagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SensorState {
+    /// Initialized and reading fine
+    Ok,
+    /// Readings are failing now and then, or the sensor failed its self-test
+    Degraded,
+    /// Could not be initialized, or too many readings in a row failed
+    Failed,
+    /// Not initialized yet, or nothing answers at its address
+    NotPresent,
+}
+
+impl SensorState {
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(SensorState::Ok),
+            1 => Some(SensorState::Degraded),
+            2 => Some(SensorState::Failed),
+            3 => Some(SensorState::NotPresent),
+            _ => None,
+        }
+    }
+
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            SensorState::Ok => 0,
+            SensorState::Degraded => 1,
+            SensorState::Failed => 2,
+            SensorState::NotPresent => 3,
+        }
+    }
+}
+
+/// Why a sensor last failed, in an [`MsbDiagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum SensorFault {
+    /// The sensor did not acknowledge its address or data
+    NoAcknowledge,
+    /// Bus error, arbitration loss or overrun on the I2C bus
+    Bus,
+    /// A data word failed its CRC
+    Crc,
+    /// The sensor did not finish in time
+    Timeout,
+    /// Something else answered at the address
+    WrongDevice,
+    /// The sensor failed its built in self-test
+    SelfTest,
+    Other,
+}
+
+impl SensorFault {
+    /// `None` is sent as 0
+    pub const fn from_raw(raw: u8) -> Option<Option<Self>> {
+        match raw {
+            0 => Some(None),
+            1 => Some(Some(SensorFault::NoAcknowledge)),
+            2 => Some(Some(SensorFault::Bus)),
+            3 => Some(Some(SensorFault::Crc)),
+            4 => Some(Some(SensorFault::Timeout)),
+            5 => Some(Some(SensorFault::WrongDevice)),
+            6 => Some(Some(SensorFault::SelfTest)),
+            7 => Some(Some(SensorFault::Other)),
+            _ => None,
+        }
+    }
+
+    pub const fn to_raw(fault: Option<Self>) -> u8 {
+        match fault {
+            None => 0,
+            Some(SensorFault::NoAcknowledge) => 1,
+            Some(SensorFault::Bus) => 2,
+            Some(SensorFault::Crc) => 3,
+            Some(SensorFault::Timeout) => 4,
+            Some(SensorFault::WrongDevice) => 5,
+            Some(SensorFault::SelfTest) => 6,
+            Some(SensorFault::Other) => 7,
+        }
+    }
+}
+
+can_message! {
+    /// Sensors found on the MSB I2C bus, sent once at boot
+    pub struct MsbInventory {
+        id: 0x60D,
+        dlc: 8,
+        transmitter: Msb,
+        per_location: true,
+        signals: {
+            /// SHT30, 0 absent, 1 present, 2 something else at its address
+            temperature: u8 = Signal::little_endian(0, 2),
+            /// LSM6DSO, as `temperature`
+            imu: u8 = Signal::little_endian(2, 2),
+            /// VL6180X, as `temperature`
+            tof: u8 = Signal::little_endian(4, 2),
+            /// WHO_AM_I read at the LSM6DSO address, 0 if nothing answered
+            imu_id: u8 = Signal::big_endian(1, 8),
+            /// Model ID read at the VL6180X address, 0 if nothing answered
+            tof_id: u8 = Signal::big_endian(2, 8),
+            /// Addresses that answered, including the sensors
+            devices: u8 = Signal::big_endian(3, 8),
+            /// Lowest four addresses that answered and aren't a sensor, 0 for none
+            other_1: u8 = Signal::big_endian(4, 8),
+            other_2: u8 = Signal::big_endian(5, 8),
+            other_3: u8 = Signal::big_endian(6, 8),
+            other_4: u8 = Signal::big_endian(7, 8),
+        }
+    }
+}
+
+/// Whether a sensor is fitted, in an [`MsbInventory`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum DevicePresence {
+    Absent,
+    Present,
+    /// Something answered at the sensor's address, but with the wrong ID
+    WrongDevice,
+}
+
+impl DevicePresence {
+    pub const fn from_raw(raw: u8) -> Option<Self> {
+        match raw {
+            0 => Some(DevicePresence::Absent),
+            1 => Some(DevicePresence::Present),
+            2 => Some(DevicePresence::WrongDevice),
+            _ => None,
+        }
+    }
+
+    pub const fn to_raw(self) -> u8 {
+        match self {
+            DevicePresence::Absent => 0,
+            DevicePresence::Present => 1,
+            DevicePresence::WrongDevice => 2,
+        }
+    }
+}
+
+can_message! {
+    /// Command to a single MSB
+    pub struct MsbCommand {
+        id: 0x610,
+        dlc: 4,
+        transmitter: Cerberus,
+        per_location: true,
+        signals: {
+            /// 0 set refresh time, 1 dump, 2 re-initialize, 3 reboot, 4 set shock capture
+            command: u8 = Signal::big_endian(0, 8),
+            /// 0 temperature, 1 IMU, 2 ToF, 3 ADC, 255 every reader
+            reader: u8 = Signal::big_endian(1, 8),
+            /// Refresh time in ms for set refresh time, 0 off 1 summary 2 raw for set shock capture
+            value: u16 = Signal::big_endian(2, 16),
+        }
+    }
+}
+
+/// A sensor reading task on the MSB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Reader {
+    Temperature,
+    Imu,
+    Tof,
+    Adc,
+}
+
+impl Reader {
+    pub const ALL: [Reader; 4] = [Reader::Temperature, Reader::Imu, Reader::Tof, Reader::Adc];
+
+    const fn from_raw(raw: u8) -> Option<Option<Reader>> {
+        match raw {
+            0 => Some(Some(Reader::Temperature)),
+            1 => Some(Some(Reader::Imu)),
+            2 => Some(Some(Reader::Tof)),
+            3 => Some(Some(Reader::Adc)),
+            ALL_READERS => Some(None),
+            _ => None,
+        }
+    }
+
+    const fn to_raw(reader: Option<Reader>) -> u8 {
+        match reader {
+            Some(Reader::Temperature) => 0,
+            Some(Reader::Imu) => 1,
+            Some(Reader::Tof) => 2,
+            Some(Reader::Adc) => 3,
+            None => ALL_READERS,
+        }
+    }
+}
+
+const ALL_READERS: u8 = 0xFF;
+
+/// An [`MsbCommand`] after validation, `None` as a reader means every reader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Command {
+    /// Time between readings in ms
+    SetRefreshTime(Option<Reader>, u16),
+    /// Take and send a reading immediately
+    Dump(Option<Reader>),
+    /// Initialize the sensor again
+    Reinit(Option<Reader>),
+    /// Reset the whole board
+    Reboot,
+    /// Start or stop high rate shock pot capture
+    SetCapture(CaptureMode),
+}
+
+/// What the ADC reader sends besides its averaged readings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum CaptureMode {
+    /// Only the averaged readings at the refresh time
+    Off,
+    /// Position and velocity histograms and extremes once per summary window
+    Summary,
+    /// Every shock pot sample
+    Raw,
+}
+
+impl CaptureMode {
+    const fn from_raw(raw: u16) -> Option<Self> {
+        match raw {
+            0 => Some(CaptureMode::Off),
+            1 => Some(CaptureMode::Summary),
+            2 => Some(CaptureMode::Raw),
+            _ => None,
+        }
+    }
+
+    const fn to_raw(self) -> u16 {
+        match self {
+            CaptureMode::Off => 0,
+            CaptureMode::Summary => 1,
+            CaptureMode::Raw => 2,
+        }
+    }
+}
+
+impl TryFrom<MsbCommand> for Command {
+    type Error = MsbCommand;
+
+    /// Fails on an unknown command or reader
+    fn try_from(msg: MsbCommand) -> Result<Self, Self::Error> {
+        let reader = Reader::from_raw(msg.reader);
+        match (msg.command, reader) {
+            (0, Some(reader)) => Ok(Command::SetRefreshTime(reader, msg.value)),
+            (1, Some(reader)) => Ok(Command::Dump(reader)),
+            (2, Some(reader)) => Ok(Command::Reinit(reader)),
+            (3, _) => Ok(Command::Reboot),
+            (4, _) => CaptureMode::from_raw(msg.value)
+                .map(Command::SetCapture)
+                .ok_or(msg),
+            _ => Err(msg),
+        }
+    }
+}
+
+impl From<Command> for MsbCommand {
+    fn from(cmd: Command) -> Self {
+        let (command, reader, value) = match cmd {
+            Command::SetRefreshTime(reader, ms) => (0, reader, ms),
+            Command::Dump(reader) => (1, reader, 0),
+            Command::Reinit(reader) => (2, reader, 0),
+            Command::Reboot => (3, None, 0),
+            Command::SetCapture(mode) => (4, Some(Reader::Adc), mode.to_raw()),
+        };
+        MsbCommand {
+            command,
+            reader: Reader::to_raw(reader),
+            value,
+        }
+    }
+}
@@ -0,0 +1,199 @@
+/// Byte order of a signal, named as in DBC files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum ByteOrder {
+    /// Motorola, start bit is the most significant bit
+    BigEndian,
+    /// Intel, start bit is the least significant bit
+    LittleEndian,
+}
+
+/// Placement and scaling of one value inside a CAN payload.
+///
+/// Bits are numbered the DBC way: bit `n` is bit `n % 8` of byte `n / 8`, where bit 0 is the
+/// least significant bit of the byte.
+/// Physical value = raw * `scale` + `offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Signal {
+    pub name: &'static str,
+    pub comment: &'static str,
+    pub start_bit: u8,
+    /// Length in bits, at most 32
+    pub length: u8,
+    pub byte_order: ByteOrder,
+    pub signed: bool,
+    pub scale: f32,
+    pub offset: f32,
+    pub unit: &'static str,
+}
+
+impl Signal {
+    const fn new(start_bit: u8, length: u8, byte_order: ByteOrder) -> Self {
+        Self {
+            name: "",
+            comment: "",
+            start_bit,
+            length,
+            byte_order,
+            signed: false,
+            scale: 1.0,
+            offset: 0.0,
+            unit: "",
+        }
+    }
+
+    /// A big endian value starting on a byte boundary, most significant byte first
+    pub const fn big_endian(start_byte: u8, length: u8) -> Self {
+        Self::new(start_byte * 8 + 7, length, ByteOrder::BigEndian)
+    }
+
+    /// A little endian value, `start_bit` is its least significant bit
+    pub const fn little_endian(start_bit: u8, length: u8) -> Self {
+        Self::new(start_bit, length, ByteOrder::LittleEndian)
+    }
+
+    /// A single bit flag
+    pub const fn bit(byte: u8, bit: u8) -> Self {
+        Self::new(byte * 8 + bit, 1, ByteOrder::LittleEndian)
+    }
+
+    /// Raw value is two's complement
+    pub const fn signed(self) -> Self {
+        Self {
+            signed: true,
+            ..self
+        }
+    }
+
+    pub const fn scale(self, scale: f32) -> Self {
+        Self { scale, ..self }
+    }
+
+    pub const fn offset(self, offset: f32) -> Self {
+        Self { offset, ..self }
+    }
+
+    pub const fn unit(self, unit: &'static str) -> Self {
+        Self { unit, ..self }
+    }
+
+    /// Smallest raw value the signal can hold
+    pub const fn min_raw(&self) -> i64 {
+        if self.signed {
+            -(1i64 << (self.length - 1))
+        } else {
+            0
+        }
+    }
+
+    /// Largest raw value the signal can hold
+    pub const fn max_raw(&self) -> i64 {
+        if self.signed {
+            (1i64 << (self.length - 1)) - 1
+        } else {
+            (1i64 << self.length) - 1
+        }
+    }
+
+    const fn mask(&self) -> u64 {
+        (1u64 << self.length) - 1
+    }
+
+    /// Position of each bit, from the most significant bit of the value to the least
+    fn positions(&self) -> impl Iterator<Item = usize> {
+        let (start, length, byte_order) = (
+            self.start_bit as usize,
+            self.length as usize,
+            self.byte_order,
+        );
+        let mut pos = start;
+        (0..length).map(move |i| {
+            let this = match byte_order {
+                ByteOrder::LittleEndian => start + length - 1 - i,
+                ByteOrder::BigEndian => pos,
+            };
+            // Motorola bits continue at the most significant bit of the next byte
+            pos = if pos % 8 == 0 { pos + 15 } else { pos - 1 };
+            this
+        })
+    }
+
+    /// Highest byte index the signal touches
+    pub fn last_byte(&self) -> usize {
+        self.positions().map(|pos| pos / 8).max().unwrap_or(0)
+    }
+
+    /// Bit mask of the positions the signal occupies, for overlap checks
+    pub fn occupied(&self) -> u64 {
+        self.positions().fold(0, |acc, pos| acc | (1 << pos))
+    }
+
+    /// Write the raw value into the payload
+    pub fn insert(&self, data: &mut [u8], raw: u64) {
+        let raw = raw & self.mask();
+        for (i, pos) in self.positions().enumerate() {
+            let bit = (raw >> (self.length as usize - 1 - i)) & 1;
+            let byte = &mut data[pos / 8];
+            *byte = (*byte & !(1 << (pos % 8))) | ((bit as u8) << (pos % 8));
+        }
+    }
+
+    /// Read the raw value from the payload, sign extended if the signal is signed
+    pub fn extract(&self, data: &[u8]) -> i64 {
+        let raw = self.positions().fold(0u64, |acc, pos| {
+            (acc << 1) | ((data[pos / 8] >> (pos % 8)) & 1) as u64
+        });
+        if self.signed && (raw >> (self.length - 1)) & 1 == 1 {
+            (raw | !self.mask()) as i64
+        } else {
+            raw as i64
+        }
+    }
+}
+
+/// A Rust type a signal can be decoded into
+pub trait SignalValue: Copy {
+    fn to_raw(self, signal: &Signal) -> u64;
+    fn from_raw(raw: i64, signal: &Signal) -> Self;
+}
+
+impl SignalValue for f32 {
+    /// Rounds to the nearest raw value, saturating at the limits of the signal
+    fn to_raw(self, signal: &Signal) -> u64 {
+        let raw = (self - signal.offset) / signal.scale;
+        let raw = (if raw >= 0.0 { raw + 0.5 } else { raw - 0.5 }) as i64;
+        raw.clamp(signal.min_raw(), signal.max_raw()) as u64
+    }
+
+    fn from_raw(raw: i64, signal: &Signal) -> Self {
+        raw as f32 * signal.scale + signal.offset
+    }
+}
+
+impl SignalValue for bool {
+    fn to_raw(self, _signal: &Signal) -> u64 {
+        self as u64
+    }
+
+    fn from_raw(raw: i64, _signal: &Signal) -> Self {
+        raw != 0
+    }
+}
+
+macro_rules! integer_signal_value {
+    ($($ty:ty),*) => {
+        $(
+            impl SignalValue for $ty {
+                /// Integer signals are never scaled
+                fn to_raw(self, _signal: &Signal) -> u64 {
+                    self as u64
+                }
+
+                fn from_raw(raw: i64, _signal: &Signal) -> Self {
+                    raw as $ty
+                }
+            }
+        )*
+    };
+}
+
+integer_signal_value!(u8, u16, u32, i8, i16, i32);
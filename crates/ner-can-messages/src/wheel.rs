@@ -0,0 +1,22 @@
+//! Messages sent by the steering wheel
+
+use crate::{can_message, Signal};
+
+can_message! {
+    /// Button states, sent on every button press
+    pub struct WheelButtons {
+        id: 0x680,
+        dlc: 6,
+        transmitter: Wheel,
+        per_location: false,
+        signals: {
+            /// True while pressed
+            button_1: bool = Signal::big_endian(0, 8),
+            button_2: bool = Signal::big_endian(1, 8),
+            button_3: bool = Signal::big_endian(2, 8),
+            button_4: bool = Signal::big_endian(3, 8),
+            button_5: bool = Signal::big_endian(4, 8),
+            button_6: bool = Signal::big_endian(5, 8),
+        }
+    }
+}
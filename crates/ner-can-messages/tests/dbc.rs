@@ -0,0 +1,30 @@
+use ner_can_messages::dbc::write_dbc;
+
+/// The checked in DBC must match the definitions the firmware encodes with
+#[test]
+fn dbc_up_to_date() {
+    let mut dbc = String::new();
+    write_dbc(&mut dbc).unwrap();
+    assert!(
+        dbc == include_str!("../ner.dbc"),
+        "ner.dbc is stale, regenerate it with \
+         `cargo run -p ner-can-messages --example dbc --target x86_64-unknown-linux-gnu > crates/ner-can-messages/ner.dbc`"
+    );
+}
+
+#[test]
+fn per_location_ids() {
+    let mut dbc = String::new();
+    write_dbc(&mut dbc).unwrap();
+    for line in [
+        "BO_ 1538 MsbTemperature_FrontLeft: 4 MSB",
+        "BO_ 1570 MsbTemperature_FrontRight: 4 MSB",
+        "BO_ 1602 MsbTemperature_BackLeft: 4 MSB",
+        "BO_ 1634 MsbTemperature_BackRight: 4 MSB",
+        "BO_ 1282 CerberusStatus: 5 Cerberus",
+    ] {
+        assert!(dbc.lines().any(|l| l == line), "missing {line}");
+    }
+    assert!(dbc.contains(" SG_ temperature : 7|16@0- (0.01,0) "));
+    assert!(dbc.contains(" SG_ brb : 15|1@1+ (1,0) [0|1] \"\" Vector__XXX"));
+}
@@ -0,0 +1,298 @@
+use ner_can_messages::{
+    cerberus::{CerberusStatus, FuseStatus, LvSense},
+    external::{BmsCurrentLimits, DtiErpm},
+    msb::{
+        CaptureMode, Command, DeviceLocation, DevicePresence, ImuEvent, MsbAccel, MsbCommand,
+        MsbDiagnostic, MsbGyro, MsbImuEvent, MsbInventory, MsbShockHistogram, MsbShockSamples,
+        MsbShockStats, MsbShockpot, MsbStrain, MsbTemperature, MsbTof, Reader, SensorFault,
+        SensorState, TofQuality,
+    },
+    wheel::WheelButtons,
+    ByteOrder, CanMessage, DecodeError, Signal, MESSAGES,
+};
+
+/// Scaled values don't survive the trip back exactly, so compare the re-encoded bytes
+fn roundtrip<M: CanMessage>(msg: M, bytes: &[u8]) {
+    assert_eq!(msg.encode().as_bytes(), bytes, "{}", M::DEF.name);
+    let decoded = M::decode(bytes).unwrap();
+    assert_eq!(decoded.encode().as_bytes(), bytes, "{}", M::DEF.name);
+}
+
+#[test]
+fn msb_messages() {
+    roundtrip(
+        MsbTemperature {
+            temperature: -23.13,
+            humidity: 50.0,
+        },
+        &[0xF6, 0xF7, 0x13, 0x88],
+    );
+    roundtrip(
+        MsbAccel {
+            accel_x: 9.807,
+            accel_y: -0.598,
+            accel_z: 0.0,
+            time: 1234,
+        },
+        &[0x26, 0x4F, 0xFD, 0xAA, 0x00, 0x00, 0x04, 0xD2],
+    );
+    roundtrip(
+        MsbGyro {
+            gyro_x: -32.768,
+            gyro_y: 32.767,
+            gyro_z: 0.001,
+            time: 65535,
+        },
+        &[0x80, 0x00, 0x7F, 0xFF, 0x00, 0x01, 0xFF, 0xFF],
+    );
+    roundtrip(
+        MsbShockpot {
+            travel: -12.34,
+            shockpot_raw: 4095,
+        },
+        &[0xFB, 0x2E, 0x0F, 0xFF],
+    );
+    roundtrip(
+        MsbStrain {
+            strain_1: 291.0,
+            strain_2: -1500.0,
+            strain_1_raw: 0x0123,
+            strain_2_raw: 0x0ABC,
+        },
+        &[0x01, 0x23, 0xFA, 0x24, 0x01, 0x23, 0x0A, 0xBC],
+    );
+    roundtrip(
+        MsbTof {
+            range: 187,
+            quality: TofQuality::Valid.to_raw(),
+            confidence: 96,
+        },
+        &[0x00, 0xBB, 0x00, 0x60],
+    );
+    assert_eq!(TofQuality::from_raw(4), Some(TofQuality::TooFar));
+    assert_eq!(TofQuality::from_raw(6), None);
+    roundtrip(
+        MsbShockSamples {
+            sequence: 0x1234,
+            sample_1: 0.0,
+            sample_2: 25.5,
+            sample_3: -1.0,
+        },
+        &[0x12, 0x34, 0x00, 0x00, 0x09, 0xF6, 0xFF, 0x9C],
+    );
+    roundtrip(
+        MsbShockStats {
+            position_min: 1.0,
+            position_max: 45.0,
+            velocity_min: -350.0,
+            velocity_max: 420.0,
+        },
+        &[0x00, 0x64, 0x11, 0x94, 0xFE, 0xA2, 0x01, 0xA4],
+    );
+    roundtrip(
+        MsbShockHistogram {
+            sequence: 7,
+            part: 2,
+            bin_1: 0.0,
+            bin_2: 0.5,
+            bin_3: 12.5,
+            bin_4: 50.0,
+            bin_5: 100.0,
+            bin_6: 127.5,
+        },
+        &[7, 2, 0, 1, 25, 100, 200, 255],
+    );
+    roundtrip(
+        MsbImuEvent {
+            event: ImuEvent::Impact.to_raw(),
+            axes: 0b1100,
+            time: 1000,
+        },
+        &[0, 0x0C, 0x03, 0xE8],
+    );
+    assert_eq!(ImuEvent::from_raw(1), Some(ImuEvent::FreeFall));
+    assert_eq!(ImuEvent::from_raw(2), None);
+    roundtrip(
+        MsbDiagnostic {
+            temperature_state: SensorState::Ok.to_raw(),
+            temperature_fault: SensorFault::to_raw(None),
+            temperature_errors: 0,
+            imu_state: SensorState::Degraded.to_raw(),
+            imu_fault: SensorFault::to_raw(Some(SensorFault::Crc)),
+            imu_errors: 2,
+            tof_state: SensorState::NotPresent.to_raw(),
+            tof_fault: SensorFault::to_raw(Some(SensorFault::NoAcknowledge)),
+            tof_errors: 255,
+            uptime: 1000,
+        },
+        &[0x00, 0, 0x31, 2, 0x13, 255, 0x03, 0xE8],
+    );
+    assert_eq!(SensorState::from_raw(4), None);
+    assert_eq!(SensorFault::from_raw(6), Some(Some(SensorFault::SelfTest)));
+    assert_eq!(SensorFault::from_raw(8), None);
+    roundtrip(
+        MsbInventory {
+            temperature: DevicePresence::Present.to_raw(),
+            imu: DevicePresence::WrongDevice.to_raw(),
+            tof: DevicePresence::Absent.to_raw(),
+            imu_id: 0x69,
+            tof_id: 0,
+            devices: 3,
+            other_1: 0x50,
+            other_2: 0,
+            other_3: 0,
+            other_4: 0,
+        },
+        &[0x09, 0x69, 0, 3, 0x50, 0, 0, 0],
+    );
+    assert_eq!(DevicePresence::from_raw(3), None);
+}
+
+#[test]
+fn msb_commands() {
+    for (cmd, bytes) in [
+        (
+            Command::SetRefreshTime(Some(Reader::Imu), 100),
+            [0, 1, 0x00, 0x64],
+        ),
+        (Command::Dump(None), [1, 0xFF, 0, 0]),
+        (Command::Reinit(Some(Reader::Tof)), [2, 2, 0, 0]),
+        (Command::Reboot, [3, 0xFF, 0, 0]),
+        (Command::SetCapture(CaptureMode::Raw), [4, 3, 0, 2]),
+    ] {
+        let msg = MsbCommand::from(cmd);
+        roundtrip(msg, &bytes);
+        assert_eq!(Command::try_from(msg), Ok(cmd));
+    }
+
+    // unknown command, unknown reader, unknown capture mode
+    for bytes in [[5, 0, 0, 0], [1, 4, 0, 0], [4, 3, 0, 3]] {
+        let msg = MsbCommand::decode(&bytes).unwrap();
+        assert_eq!(Command::try_from(msg), Err(msg));
+    }
+}
+
+#[test]
+fn cerberus_messages() {
+    roundtrip(
+        CerberusStatus {
+            fault_code: 0x800,
+            severity: 4,
+        },
+        &[0x00, 0x00, 0x08, 0x00, 0x04],
+    );
+    roundtrip(LvSense { lv_sense: 123_456 }, &[0x00, 0x01, 0xE2, 0x40]);
+    roundtrip(
+        FuseStatus {
+            battbox: true,
+            lvbox: false,
+            fan_radiator: false,
+            mc: true,
+            fan_battbox: false,
+            pump: false,
+            dashboard: false,
+            brakelight: true,
+            brb: true,
+        },
+        &[0b1001_0001, 0b1000_0000],
+    );
+}
+
+#[test]
+fn other_messages() {
+    roundtrip(
+        WheelButtons {
+            button_1: true,
+            button_2: false,
+            button_3: false,
+            button_4: true,
+            button_5: false,
+            button_6: true,
+        },
+        &[1, 0, 0, 1, 0, 1],
+    );
+    roundtrip(BmsCurrentLimits {}, &[]);
+
+    // only the ERPM of the 8 byte frame is decoded
+    let dti = DtiErpm::decode(&[0xFF, 0xFF, 0xFC, 0x18, 0x01, 0x02, 0x03, 0x04]).unwrap();
+    assert_eq!(dti.erpm, -1000);
+    assert_eq!(
+        DtiErpm::decode(&[0xFF, 0xFF]),
+        Err(DecodeError::TooShort(2))
+    );
+}
+
+#[test]
+fn scaled_values_decode() {
+    let msg = MsbTemperature::decode(&[0x09, 0xC4, 0x13, 0x88]).unwrap();
+    assert!((msg.temperature - 25.0).abs() < 1e-4);
+    assert!((msg.humidity - 50.0).abs() < 1e-4);
+}
+
+#[test]
+fn scaled_values_saturate() {
+    let payload = MsbAccel {
+        accel_x: 100.0,
+        accel_y: -100.0,
+        accel_z: 0.0004,
+        time: 0,
+    }
+    .encode();
+    assert_eq!(
+        payload.as_bytes(),
+        [0x7F, 0xFF, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn little_endian_signals() {
+    let signal = Signal::little_endian(4, 12).signed();
+    let mut data = [0u8; 2];
+    signal.insert(&mut data, (-2i64) as u64);
+    assert_eq!(data, [0xE0, 0xFF]);
+    assert_eq!(signal.extract(&data), -2);
+}
+
+#[test]
+fn big_endian_signals_cross_bytes() {
+    // 12 bits starting at bit 3 of byte 0, continuing into byte 1
+    let signal = Signal {
+        start_bit: 3,
+        length: 12,
+        byte_order: ByteOrder::BigEndian,
+        ..Signal::big_endian(0, 12)
+    };
+    let mut data = [0u8; 2];
+    signal.insert(&mut data, 0xABC);
+    assert_eq!(data, [0x0A, 0xBC]);
+    assert_eq!(signal.extract(&data), 0xABC);
+}
+
+#[test]
+fn definitions_are_consistent() {
+    for (i, def) in MESSAGES.iter().enumerate() {
+        assert!(def.dlc <= 8, "{}", def.name);
+        let mut occupied = 0u64;
+        for signal in def.signals {
+            assert!(signal.length > 0 && signal.length <= 32, "{}", signal.name);
+            assert!(signal.last_byte() < def.dlc as usize, "{}", signal.name);
+            assert_eq!(occupied & signal.occupied(), 0, "{} overlaps", signal.name);
+            occupied |= signal.occupied();
+        }
+
+        for other in &MESSAGES[i + 1..] {
+            let ids = |d: &ner_can_messages::MessageDef| {
+                let mut ids = [d.id; 4];
+                if d.per_location {
+                    for (id, loc) in ids.iter_mut().zip(DeviceLocation::ALL) {
+                        *id = loc.can_id(d.id);
+                    }
+                }
+                ids
+            };
+            for id in ids(def) {
+                assert!(!ids(other).contains(&id), "{} and {}", def.name, other.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,311 @@
+#![no_std]
+//! Versioned, CRC protected config records in flash
+//!
+//! Records are appended to one of two flash banks, so each erase is spread over many writes.
+//! When the active bank is full the next record goes to the start of the other bank, which is
+//! erased ahead of time by [`ConfigStore::new`] at boot. Loading picks the valid record with the
+//! highest sequence number, and anything corrupt or of another version falls back to defaults.
+//!
+//! Each record is laid out as:
+//!
+//! | bytes | contents                           |
+//! |-------|------------------------------------|
+//! | 4     | magic                              |
+//! | 2     | record version                     |
+//! | 2     | payload length                     |
+//! | 4     | sequence number                    |
+//! | len   | payload, padded to a multiple of 4 |
+//! | 4     | CRC-32 of everything above         |
+//!
+//! All fields are little endian.
+
+use core::marker::PhantomData;
+
+pub mod ram;
+#[cfg(feature = "stm32")]
+pub mod stm32;
+
+/// Writes must start on and be a multiple of this many bytes
+pub const WRITE_SIZE: usize = 4;
+
+/// Largest payload of a [`Record`]
+pub const MAX_RECORD_SIZE: usize = 240;
+
+const MAGIC: u32 = 0x4E45_5243;
+const HEADER_SIZE: usize = 12;
+const CRC_SIZE: usize = 4;
+const ERASED: u8 = 0xFF;
+
+/// One of the two flash regions records are written to, each erased on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Bank {
+    A,
+    B,
+}
+
+impl Bank {
+    pub const fn other(self) -> Bank {
+        match self {
+            Bank::A => Bank::B,
+            Bank::B => Bank::A,
+        }
+    }
+}
+
+/// NOR flash holding the two banks. Offsets are relative to the start of a bank.
+pub trait Flash {
+    type Error;
+
+    /// Bytes in each bank
+    const BANK_SIZE: u32;
+
+    fn read(&mut self, bank: Bank, offset: u32, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Program erased bytes, `offset` and `data.len()` are multiples of [`WRITE_SIZE`]
+    fn write(&mut self, bank: Bank, offset: u32, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Set every byte of the bank back to 0xFF
+    fn erase(&mut self, bank: Bank) -> Result<(), Self::Error>;
+}
+
+/// A config struct that can be stored
+pub trait Record: Default {
+    /// Bump whenever the encoding changes, stored records of other versions are ignored
+    const VERSION: u16;
+    /// Encoded size in bytes, at most [`MAX_RECORD_SIZE`]
+    const SIZE: usize;
+
+    fn encode(&self, buf: &mut [u8]);
+
+    /// `None` if the stored values are out of range
+    fn decode(buf: &[u8]) -> Option<Self>;
+}
+
+/// Location of a valid record
+#[derive(Clone, Copy)]
+struct Stored {
+    bank: Bank,
+    offset: u32,
+    sequence: u32,
+    version: u16,
+    len: u16,
+}
+
+/// Reads and appends records of type `R`
+pub struct ConfigStore<F: Flash, R: Record> {
+    flash: F,
+    active: Bank,
+    /// Offset of the first free byte in the active bank
+    next: u32,
+    newest: Option<Stored>,
+    /// The other bank can be written without erasing it first
+    spare_erased: bool,
+    _record: PhantomData<R>,
+}
+
+impl<F: Flash, R: Record> ConfigStore<F, R> {
+    const FITS: () = assert!(R::SIZE <= MAX_RECORD_SIZE);
+
+    /// Scan both banks for the newest record and erase the spare bank if needed.
+    ///
+    /// Call this at boot before starting a watchdog, erasing a large sector can stall the CPU
+    /// for over a second.
+    pub fn new(flash: F) -> Result<Self, F::Error> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS;
+
+        let mut store = Self {
+            flash,
+            active: Bank::A,
+            next: 0,
+            newest: None,
+            spare_erased: false,
+            _record: PhantomData,
+        };
+
+        let mut free = [0; 2];
+        for (i, bank) in [Bank::A, Bank::B].into_iter().enumerate() {
+            free[i] = store.scan(bank)?;
+        }
+        if let Some(newest) = store.newest {
+            store.active = newest.bank;
+        }
+        store.next = match store.active {
+            Bank::A => free[0],
+            Bank::B => free[1],
+        };
+
+        let spare = store.active.other();
+        if !store.is_erased(spare)? {
+            store.flash.erase(spare)?;
+        }
+        store.spare_erased = true;
+
+        Ok(store)
+    }
+
+    /// The newest valid record, `None` if there is none or it is of another version
+    pub fn load(&mut self) -> Result<Option<R>, F::Error> {
+        let Some(newest) = self.newest else {
+            return Ok(None);
+        };
+        if newest.version != R::VERSION || newest.len as usize != R::SIZE {
+            return Ok(None);
+        }
+
+        let mut buf = [0u8; MAX_RECORD_SIZE];
+        let payload = &mut buf[..R::SIZE];
+        self.flash
+            .read(newest.bank, newest.offset + HEADER_SIZE as u32, payload)?;
+        Ok(R::decode(payload))
+    }
+
+    /// The newest valid record, or defaults if there is none or flash can't be read
+    pub fn load_or_default(&mut self) -> R {
+        self.load().ok().flatten().unwrap_or_default()
+    }
+
+    /// Append a record, moving to the other bank when the active one is full
+    pub fn store(&mut self, record: &R) -> Result<(), F::Error> {
+        let size = slot_size(R::SIZE);
+        if self.next + size as u32 > F::BANK_SIZE {
+            let other = self.active.other();
+            if !self.spare_erased {
+                self.flash.erase(other)?;
+            }
+            // the old bank keeps its newest record until the next switch
+            self.active = other;
+            self.next = 0;
+            self.spare_erased = false;
+        }
+
+        let sequence = self
+            .newest
+            .map_or(0, |newest| newest.sequence.wrapping_add(1));
+        let mut buf = [ERASED; MAX_RECORD_SIZE + HEADER_SIZE + CRC_SIZE];
+        let slot = &mut buf[..size];
+        slot[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        slot[4..6].copy_from_slice(&R::VERSION.to_le_bytes());
+        slot[6..8].copy_from_slice(&(R::SIZE as u16).to_le_bytes());
+        slot[8..12].copy_from_slice(&sequence.to_le_bytes());
+        let payload_end = size - CRC_SIZE;
+        slot[HEADER_SIZE..payload_end].fill(0);
+        record.encode(&mut slot[HEADER_SIZE..HEADER_SIZE + R::SIZE]);
+        let crc = crc32(&slot[..payload_end]);
+        slot[payload_end..].copy_from_slice(&crc.to_le_bytes());
+
+        // advance first so a failed write is never written over
+        let offset = self.next;
+        self.next += size as u32;
+        self.flash.write(self.active, offset, slot)?;
+
+        self.newest = Some(Stored {
+            bank: self.active,
+            offset,
+            sequence,
+            version: R::VERSION,
+            len: R::SIZE as u16,
+        });
+        Ok(())
+    }
+
+    /// Bank currently being appended to
+    pub fn active_bank(&self) -> Bank {
+        self.active
+    }
+
+    /// Give back the flash, for inspecting it in tests
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    /// Track valid records of a bank, returning the offset of its first free byte.
+    /// Anything that isn't a record ends the scan and marks the rest of the bank as used.
+    fn scan(&mut self, bank: Bank) -> Result<u32, F::Error> {
+        let mut offset = 0;
+        let mut buf = [0u8; MAX_RECORD_SIZE + HEADER_SIZE + CRC_SIZE];
+
+        while offset + HEADER_SIZE as u32 <= F::BANK_SIZE {
+            let header = &mut buf[..HEADER_SIZE];
+            self.flash.read(bank, offset, header)?;
+            if header.iter().all(|b| *b == ERASED) {
+                return Ok(offset);
+            }
+
+            let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+            let version = u16::from_le_bytes([header[4], header[5]]);
+            let len = u16::from_le_bytes([header[6], header[7]]);
+            let sequence = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+            let size = slot_size(len as usize);
+            if magic != MAGIC
+                || len as usize > MAX_RECORD_SIZE
+                || offset + size as u32 > F::BANK_SIZE
+            {
+                break;
+            }
+
+            let slot = &mut buf[..size];
+            self.flash
+                .read(bank, offset + HEADER_SIZE as u32, &mut slot[HEADER_SIZE..])?;
+            let payload_end = size - CRC_SIZE;
+            let crc = u32::from_le_bytes([
+                slot[payload_end],
+                slot[payload_end + 1],
+                slot[payload_end + 2],
+                slot[payload_end + 3],
+            ]);
+
+            if crc32(&slot[..payload_end]) == crc
+                && self
+                    .newest
+                    .map_or(true, |newest| sequence > newest.sequence)
+            {
+                self.newest = Some(Stored {
+                    bank,
+                    offset,
+                    sequence,
+                    version,
+                    len,
+                });
+            }
+            offset += size as u32;
+        }
+
+        Ok(F::BANK_SIZE)
+    }
+
+    fn is_erased(&mut self, bank: Bank) -> Result<bool, F::Error> {
+        let mut buf = [0u8; 256];
+        let mut offset = 0;
+        while offset < F::BANK_SIZE {
+            let len = buf.len().min((F::BANK_SIZE - offset) as usize);
+            self.flash.read(bank, offset, &mut buf[..len])?;
+            if buf[..len].iter().any(|b| *b != ERASED) {
+                return Ok(false);
+            }
+            offset += len as u32;
+        }
+        Ok(true)
+    }
+}
+
+/// Bytes taken by a record with a payload of `len` bytes
+const fn slot_size(len: usize) -> usize {
+    HEADER_SIZE + len.div_ceil(WRITE_SIZE) * WRITE_SIZE + CRC_SIZE
+}
+
+/// CRC-32 (IEEE 802.3), as used by zlib
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
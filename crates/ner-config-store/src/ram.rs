@@ -0,0 +1,99 @@
+//! RAM backed [`Flash`] for testing the storage format on the host
+
+use crate::{Bank, Flash, WRITE_SIZE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum RamFlashError {
+    OutOfBounds,
+    Unaligned,
+    /// The simulated power loss cut the write short
+    PowerLoss,
+}
+
+/// Two banks of `N` bytes that behave like NOR flash: writes can only clear bits
+pub struct RamFlash<const N: usize> {
+    pub banks: [[u8; N]; 2],
+    /// Erases of each bank so far
+    pub erases: [u32; 2],
+    /// Bytes that can still be written before power is lost, `None` for no limit
+    pub write_budget: Option<usize>,
+}
+
+impl<const N: usize> RamFlash<N> {
+    /// Erased flash
+    pub const fn new() -> Self {
+        Self {
+            banks: [[0xFF; N]; 2],
+            erases: [0; 2],
+            write_budget: None,
+        }
+    }
+
+    pub fn bank(&self, bank: Bank) -> &[u8; N] {
+        &self.banks[bank as usize]
+    }
+
+    pub fn bank_mut(&mut self, bank: Bank) -> &mut [u8; N] {
+        &mut self.banks[bank as usize]
+    }
+
+    fn range(offset: u32, len: usize) -> Result<core::ops::Range<usize>, RamFlashError> {
+        let start = offset as usize;
+        let end = start + len;
+        if end > N {
+            return Err(RamFlashError::OutOfBounds);
+        }
+        Ok(start..end)
+    }
+}
+
+impl<const N: usize> Default for RamFlash<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Flash for RamFlash<N> {
+    type Error = RamFlashError;
+
+    const BANK_SIZE: u32 = N as u32;
+
+    fn read(&mut self, bank: Bank, offset: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let range = Self::range(offset, buf.len())?;
+        buf.copy_from_slice(&self.bank(bank)[range]);
+        Ok(())
+    }
+
+    fn write(&mut self, bank: Bank, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        if offset as usize % WRITE_SIZE != 0 || data.len() % WRITE_SIZE != 0 {
+            return Err(RamFlashError::Unaligned);
+        }
+        let range = Self::range(offset, data.len())?;
+
+        let len = match self.write_budget {
+            Some(budget) => budget.min(data.len()),
+            None => data.len(),
+        };
+        for (byte, new) in self.bank_mut(bank)[range].iter_mut().zip(&data[..len]) {
+            *byte &= *new;
+        }
+
+        match &mut self.write_budget {
+            Some(budget) if *budget < data.len() => {
+                *budget = 0;
+                Err(RamFlashError::PowerLoss)
+            }
+            Some(budget) => {
+                *budget -= data.len();
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn erase(&mut self, bank: Bank) -> Result<(), Self::Error> {
+        self.bank_mut(bank).fill(0xFF);
+        self.erases[bank as usize] += 1;
+        Ok(())
+    }
+}
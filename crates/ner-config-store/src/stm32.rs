@@ -0,0 +1,50 @@
+//! [`Flash`] on the last two 128K sectors of the STM32F405
+//!
+//! Sectors 10 and 11 are reserved for config, the `memory.x` of each firmware using this gives
+//! the linker only the first 768K of flash. Erasing one stalls the CPU for about a second, see
+//! [`ConfigStore::new`](crate::ConfigStore::new).
+
+use embassy_stm32::flash::{Blocking, Error};
+
+use crate::{Bank, Flash};
+
+/// Sector 10, as an offset from the start of flash
+const BANK_A_OFFSET: u32 = 0xC_0000;
+/// Sector 11, as an offset from the start of flash
+const BANK_B_OFFSET: u32 = 0xE_0000;
+
+pub struct Stm32Flash {
+    flash: embassy_stm32::flash::Flash<'static, Blocking>,
+}
+
+impl Stm32Flash {
+    pub fn new(flash: embassy_stm32::flash::Flash<'static, Blocking>) -> Self {
+        Self { flash }
+    }
+
+    const fn base(bank: Bank) -> u32 {
+        match bank {
+            Bank::A => BANK_A_OFFSET,
+            Bank::B => BANK_B_OFFSET,
+        }
+    }
+}
+
+impl Flash for Stm32Flash {
+    type Error = Error;
+
+    const BANK_SIZE: u32 = 128 * 1024;
+
+    fn read(&mut self, bank: Bank, offset: u32, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.flash.blocking_read(Self::base(bank) + offset, buf)
+    }
+
+    fn write(&mut self, bank: Bank, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+        self.flash.blocking_write(Self::base(bank) + offset, data)
+    }
+
+    fn erase(&mut self, bank: Bank) -> Result<(), Self::Error> {
+        let base = Self::base(bank);
+        self.flash.blocking_erase(base, base + Self::BANK_SIZE)
+    }
+}
@@ -0,0 +1,217 @@
+use ner_config_store::{
+    crc32,
+    ram::{RamFlash, RamFlashError},
+    Bank, ConfigStore, Record,
+};
+
+/// Room for 10 records of [`Settings`] per bank
+const BANK_SIZE: usize = 256;
+const SLOT_SIZE: usize = 24;
+
+type Store = ConfigStore<RamFlash<BANK_SIZE>, Settings>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Settings {
+    id: u32,
+    rate: u16,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self { id: 0, rate: 500 }
+    }
+}
+
+impl Record for Settings {
+    const VERSION: u16 = 1;
+    const SIZE: usize = 6;
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.id.to_le_bytes());
+        buf[4..6].copy_from_slice(&self.rate.to_le_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let rate = u16::from_le_bytes([buf[4], buf[5]]);
+        (rate != 0).then_some(Self {
+            id: u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]),
+            rate,
+        })
+    }
+}
+
+/// Same layout as [`Settings`], after an incompatible change
+#[derive(Debug, Default, PartialEq)]
+struct SettingsV2(Settings);
+
+impl Record for SettingsV2 {
+    const VERSION: u16 = 2;
+    const SIZE: usize = 6;
+
+    fn encode(&self, buf: &mut [u8]) {
+        self.0.encode(buf)
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        Settings::decode(buf).map(SettingsV2)
+    }
+}
+
+fn settings(id: u32) -> Settings {
+    Settings { id, rate: 100 }
+}
+
+/// Flash after storing records with ids `0..count`
+fn stored(count: u32) -> RamFlash<BANK_SIZE> {
+    let mut store = Store::new(RamFlash::new()).unwrap();
+    for id in 0..count {
+        store.store(&settings(id)).unwrap();
+    }
+    store.release()
+}
+
+fn reload(flash: RamFlash<BANK_SIZE>) -> Settings {
+    Store::new(flash).unwrap().load_or_default()
+}
+
+#[test]
+fn crc_check_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+}
+
+#[test]
+fn empty_flash_gives_defaults() {
+    let mut store = Store::new(RamFlash::new()).unwrap();
+    assert_eq!(store.load(), Ok(None));
+    assert_eq!(store.load_or_default(), Settings::default());
+
+    // nothing to erase on a blank chip
+    assert_eq!(store.release().erases, [0, 0]);
+}
+
+#[test]
+fn store_and_reload() {
+    let mut store = Store::new(RamFlash::new()).unwrap();
+    store.store(&settings(7)).unwrap();
+    assert_eq!(store.load(), Ok(Some(settings(7))));
+
+    assert_eq!(reload(store.release()), settings(7));
+}
+
+#[test]
+fn newest_record_wins() {
+    assert_eq!(reload(stored(5)), settings(4));
+}
+
+#[test]
+fn switches_bank_when_full() {
+    let mut store = Store::new(RamFlash::new()).unwrap();
+    for id in 0..(BANK_SIZE / SLOT_SIZE) as u32 {
+        store.store(&settings(id)).unwrap();
+    }
+    assert_eq!(store.active_bank(), Bank::A);
+
+    store.store(&settings(100)).unwrap();
+    assert_eq!(store.active_bank(), Bank::B);
+    assert_eq!(store.load(), Ok(Some(settings(100))));
+
+    let flash = store.release();
+    // bank B was blank, the switch itself doesn't erase
+    assert_eq!(flash.erases, [0, 0]);
+
+    // the next boot clears the old bank ahead of the following switch
+    let store = Store::new(flash).unwrap();
+    assert_eq!(store.active_bank(), Bank::B);
+    let flash = store.release();
+    assert_eq!(flash.erases, [1, 0]);
+    assert_eq!(reload(flash), settings(100));
+}
+
+#[test]
+fn erases_alternate_between_banks() {
+    let per_bank = (BANK_SIZE / SLOT_SIZE) as u32;
+    let mut flash = RamFlash::new();
+    // reboot after every record, as when settings are changed once per power cycle
+    for id in 0..per_bank * 6 {
+        let mut store = Store::new(flash).unwrap();
+        store.store(&settings(id)).unwrap();
+        flash = store.release();
+    }
+
+    // one erase per bank switch, spread evenly
+    assert_eq!(flash.erases[0] + flash.erases[1], 5);
+    assert!(flash.erases[0].abs_diff(flash.erases[1]) <= 1);
+    assert_eq!(reload(flash), settings(per_bank * 6 - 1));
+}
+
+#[test]
+fn corrupt_record_falls_back_to_previous() {
+    let mut flash = stored(3);
+    // flip a payload bit of the newest record
+    flash.bank_mut(Bank::A)[2 * SLOT_SIZE + 12] ^= 0x01;
+    assert_eq!(reload(flash), settings(1));
+}
+
+#[test]
+fn corrupt_only_record_falls_back_to_defaults() {
+    let mut flash = stored(1);
+    flash.bank_mut(Bank::A)[SLOT_SIZE - 1] ^= 0x80;
+    assert_eq!(reload(flash), Settings::default());
+}
+
+#[test]
+fn invalid_values_fall_back_to_defaults() {
+    let mut store = Store::new(RamFlash::new()).unwrap();
+    store.store(&Settings { id: 1, rate: 0 }).unwrap();
+    assert_eq!(store.load(), Ok(None));
+    assert_eq!(reload(store.release()), Settings::default());
+}
+
+#[test]
+fn power_loss_keeps_previous_record() {
+    for budget in [0, 4, 12, 20] {
+        let mut flash = stored(2);
+        flash.write_budget = Some(budget);
+
+        let mut store = Store::new(flash).unwrap();
+        assert_eq!(
+            store.store(&settings(9)),
+            Err(RamFlashError::PowerLoss),
+            "budget {budget}"
+        );
+        let mut flash = store.release();
+        flash.write_budget = None;
+
+        assert_eq!(reload(flash), settings(1), "budget {budget}");
+    }
+}
+
+#[test]
+fn stores_after_power_loss() {
+    for budget in [4, 8, 20] {
+        let mut flash = stored(2);
+        flash.write_budget = Some(budget);
+        let mut store = Store::new(flash).unwrap();
+        assert!(store.store(&settings(9)).is_err());
+        let mut flash = store.release();
+        flash.write_budget = None;
+
+        // the half written slot is never written over
+        let mut store = Store::new(flash).unwrap();
+        store.store(&settings(10)).unwrap();
+        assert_eq!(reload(store.release()), settings(10), "budget {budget}");
+    }
+}
+
+#[test]
+fn other_version_gives_defaults() {
+    let flash = stored(2);
+    let mut store = ConfigStore::<_, SettingsV2>::new(flash).unwrap();
+    assert_eq!(store.load(), Ok(None));
+    assert_eq!(store.load_or_default(), SettingsV2::default());
+
+    // and the new version takes over once stored
+    store.store(&SettingsV2(settings(3))).unwrap();
+    let mut store = ConfigStore::<_, SettingsV2>::new(store.release()).unwrap();
+    assert_eq!(store.load(), Ok(Some(SettingsV2(settings(3)))));
+}
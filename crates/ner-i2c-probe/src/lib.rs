@@ -0,0 +1,171 @@
+#![no_std]
+//! Finding out what is fitted on an I2C bus
+//!
+//! [`scan`] lists every address that acknowledges, and [`identify`] checks one address against
+//! the [`Signature`] of the device expected there, by its ID register where it has one.
+//! [`discover`] does both at once, for boards where the fitted sensors differ.
+
+use embedded_hal_async::i2c::{Error, ErrorKind, I2c};
+
+/// Lowest 7 bit address that isn't reserved
+pub const FIRST_ADDRESS: u8 = 0x08;
+
+/// Highest 7 bit address that isn't reserved
+pub const LAST_ADDRESS: u8 = 0x77;
+
+/// A set of 7 bit addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, defmt::Format)]
+pub struct Addresses(u128);
+
+impl Addresses {
+    pub const EMPTY: Addresses = Addresses(0);
+
+    pub fn insert(&mut self, address: u8) {
+        self.0 |= 1 << (address & 0x7F);
+    }
+
+    pub fn remove(&mut self, address: u8) {
+        self.0 &= !(1 << (address & 0x7F));
+    }
+
+    pub fn contains(&self, address: u8) -> bool {
+        address <= 0x7F && self.0 & (1 << address) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Lowest address first
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=0x7F).filter(|address| self.contains(*address))
+    }
+}
+
+/// How to tell the device expected at an address from anything else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Id {
+    /// For devices without an ID register, present if the device acknowledges a write of these
+    /// bytes, which must be harmless to it
+    Ack(&'static [u8]),
+    /// Present if reading a byte after writing `register` gives `value`
+    Register { register: &'static [u8], value: u8 },
+}
+
+/// A device expected at a fixed address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    /// For logging
+    pub name: &'static str,
+    pub address: u8,
+    pub id: Id,
+}
+
+/// Whether the device of a [`Signature`] is fitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub enum Presence {
+    /// Nothing acknowledged the address, or the ID check
+    Absent,
+    Present,
+    /// Something answered at the address, but its ID register read this
+    WrongId(u8),
+}
+
+impl Presence {
+    /// Something answered at the address, whether or not it was the expected device
+    pub fn answered(&self) -> bool {
+        *self != Presence::Absent
+    }
+}
+
+fn nack(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::NoAcknowledge(_))
+}
+
+/// Whether anything acknowledges `address`.
+///
+/// Tries a write of no bytes first, which targets acknowledge without doing anything, and reads
+/// a byte instead on controllers that refuse empty writes. Some devices NACK reads while they
+/// have nothing to send, so may only be found by [`identify`] on those controllers.
+pub async fn probe<I: I2c>(i2c: &mut I, address: u8) -> Result<bool, I::Error> {
+    match i2c.write(address, &[]).await {
+        Ok(()) => return Ok(true),
+        Err(err) if nack(err.kind()) => return Ok(false),
+        Err(_) => {}
+    }
+    match i2c.read(address, &mut [0]).await {
+        Ok(()) => Ok(true),
+        Err(err) if nack(err.kind()) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Every address from [`FIRST_ADDRESS`] to [`LAST_ADDRESS`] that acknowledges a [`probe`]. Stops
+/// at the first error other than a NACK, as the bus is not working.
+pub async fn scan<I: I2c>(i2c: &mut I) -> Result<Addresses, I::Error> {
+    let mut found = Addresses::EMPTY;
+    for address in FIRST_ADDRESS..=LAST_ADDRESS {
+        if probe(i2c, address).await? {
+            found.insert(address);
+        }
+    }
+    Ok(found)
+}
+
+/// Check whether the device of `signature` is fitted, any NACK counts as absent
+pub async fn identify<I: I2c>(i2c: &mut I, signature: &Signature) -> Result<Presence, I::Error> {
+    let address = signature.address;
+    let result = match signature.id {
+        Id::Ack(bytes) => i2c.write(address, bytes).await.map(|()| Presence::Present),
+        Id::Register { register, value } => {
+            let mut id = [0];
+            i2c.write_read(address, register, &mut id)
+                .await
+                .map(|()| match id[0] {
+                    read if read == value => Presence::Present,
+                    read => Presence::WrongId(read),
+                })
+        }
+    };
+    match result {
+        Err(err) if nack(err.kind()) => Ok(Presence::Absent),
+        result => result,
+    }
+}
+
+/// What was found on a bus by [`discover`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, defmt::Format)]
+pub struct Inventory<const N: usize> {
+    /// Every address that answered, including devices only found by their signature
+    pub addresses: Addresses,
+    /// Whether each signature's device is fitted, in the order the signatures were given
+    pub devices: [Presence; N],
+    /// Addresses that answered but aren't the address of any signature
+    pub unknown: Addresses,
+}
+
+/// Scan the bus and identify each of `signatures`
+pub async fn discover<I: I2c, const N: usize>(
+    i2c: &mut I,
+    signatures: &[Signature; N],
+) -> Result<Inventory<N>, I::Error> {
+    let mut addresses = scan(i2c).await?;
+    let mut unknown = addresses;
+    let mut devices = [Presence::Absent; N];
+    for (presence, signature) in devices.iter_mut().zip(signatures) {
+        *presence = identify(i2c, signature).await?;
+        if presence.answered() {
+            addresses.insert(signature.address);
+        }
+        unknown.remove(signature.address);
+    }
+    Ok(Inventory {
+        addresses,
+        devices,
+        unknown,
+    })
+}
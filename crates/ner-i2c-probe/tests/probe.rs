@@ -0,0 +1,190 @@
+use embassy_futures::block_on;
+use embedded_hal_async::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+use ner_i2c_probe::{discover, identify, probe, scan, Addresses, Id, Presence, Signature};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MockError {
+    Nack,
+    ZeroLength,
+    Bus,
+}
+
+impl embedded_hal_async::i2c::Error for MockError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            MockError::Nack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            MockError::ZeroLength => ErrorKind::Other,
+            MockError::Bus => ErrorKind::Bus,
+        }
+    }
+}
+
+/// A target that answers any register read with `id`, and NACKs reads without a register
+/// written first if `quiet`
+struct MockDevice {
+    address: u8,
+    id: u8,
+    quiet: bool,
+}
+
+#[derive(Default)]
+struct MockBus {
+    devices: Vec<MockDevice>,
+    /// The controller can't send a write of no bytes
+    no_empty_writes: bool,
+    stuck: bool,
+}
+
+impl ErrorType for MockBus {
+    type Error = MockError;
+}
+
+impl I2c for MockBus {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if self.stuck {
+            return Err(MockError::Bus);
+        }
+        let empty = matches!(operations, [Operation::Write(data)] if data.is_empty());
+        if empty && self.no_empty_writes {
+            return Err(MockError::ZeroLength);
+        }
+        let dev = self
+            .devices
+            .iter()
+            .find(|d| d.address == address)
+            .ok_or(MockError::Nack)?;
+        let mut written = false;
+        for op in operations {
+            match op {
+                Operation::Write(data) => written |= !data.is_empty(),
+                Operation::Read(_) if dev.quiet && !written => return Err(MockError::Nack),
+                Operation::Read(buf) => buf.fill(dev.id),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn msb_bus() -> MockBus {
+    MockBus {
+        devices: vec![
+            MockDevice {
+                address: 0x45,
+                id: 0,
+                quiet: true,
+            },
+            MockDevice {
+                address: 0x6A,
+                id: 0x6C,
+                quiet: false,
+            },
+            MockDevice {
+                address: 0x50,
+                id: 0xFF,
+                quiet: false,
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+const SIGNATURES: [Signature; 3] = [
+    Signature {
+        name: "sht30",
+        address: 0x45,
+        id: Id::Ack(&[0x30, 0x41]),
+    },
+    Signature {
+        name: "lsm6dso",
+        address: 0x6A,
+        id: Id::Register {
+            register: &[0x0F],
+            value: 0x6C,
+        },
+    },
+    Signature {
+        name: "vl6180x",
+        address: 0x29,
+        id: Id::Register {
+            register: &[0x00, 0x00],
+            value: 0xB4,
+        },
+    },
+];
+
+#[test]
+fn addresses() {
+    let mut addresses = Addresses::EMPTY;
+    assert!(addresses.is_empty());
+    addresses.insert(0x77);
+    addresses.insert(0x08);
+    addresses.insert(0x29);
+    assert_eq!(addresses.len(), 3);
+    assert!(addresses.contains(0x29) && !addresses.contains(0x2A));
+    assert!(!addresses.contains(0xFF));
+    assert_eq!(addresses.iter().collect::<Vec<_>>(), [0x08, 0x29, 0x77]);
+    addresses.remove(0x29);
+    assert_eq!(addresses.iter().collect::<Vec<_>>(), [0x08, 0x77]);
+}
+
+#[test]
+fn scan_bus() {
+    let found = block_on(scan(&mut msb_bus())).unwrap();
+    assert_eq!(found.iter().collect::<Vec<_>>(), [0x45, 0x50, 0x6A]);
+
+    // a device that NACKs reads is missed when the scan has to read
+    let mut bus = msb_bus();
+    bus.no_empty_writes = true;
+    assert_eq!(block_on(probe(&mut bus, 0x6A)), Ok(true));
+    assert_eq!(block_on(probe(&mut bus, 0x45)), Ok(false));
+    assert_eq!(block_on(probe(&mut bus, 0x29)), Ok(false));
+
+    let mut bus = msb_bus();
+    bus.stuck = true;
+    assert_eq!(block_on(scan(&mut bus)), Err(MockError::Bus));
+}
+
+#[test]
+fn identify_signatures() {
+    let mut bus = msb_bus();
+    assert_eq!(
+        block_on(identify(&mut bus, &SIGNATURES[0])),
+        Ok(Presence::Present)
+    );
+    assert_eq!(
+        block_on(identify(&mut bus, &SIGNATURES[1])),
+        Ok(Presence::Present)
+    );
+    assert_eq!(
+        block_on(identify(&mut bus, &SIGNATURES[2])),
+        Ok(Presence::Absent)
+    );
+    // an LSM6DS3 where the LSM6DSO should be
+    bus.devices[1].id = 0x69;
+    assert_eq!(
+        block_on(identify(&mut bus, &SIGNATURES[1])),
+        Ok(Presence::WrongId(0x69))
+    );
+}
+
+#[test]
+fn discover_bus() {
+    let mut bus = msb_bus();
+    bus.no_empty_writes = true;
+    let inventory = block_on(discover(&mut bus, &SIGNATURES)).unwrap();
+    assert_eq!(
+        inventory.devices,
+        [Presence::Present, Presence::Present, Presence::Absent]
+    );
+    // the SHT30 only turns up by its signature
+    assert_eq!(
+        inventory.addresses.iter().collect::<Vec<_>>(),
+        [0x45, 0x50, 0x6A]
+    );
+    assert_eq!(inventory.unknown.iter().collect::<Vec<_>>(), [0x50]);
+    assert!(!inventory.devices[2].answered());
+}
@@ -5,8 +5,13 @@
 // this makes them eternally portable yet still easy to use from embassy
 // therefore, no import should EVER mention
 
+use core::marker::PhantomData;
+
 use defmt::bitflags;
-use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+use embedded_hal_async::{
+    delay::DelayNs,
+    i2c::{Error as _, ErrorKind, I2c, NoAcknowledgeSource},
+};
 
 // 2.2 Timing Specification for the Sensor System
 // Table 4
@@ -16,21 +21,25 @@ const SOFT_RESET_TIME_MS: u8 = 1;
 // 4: Operation and Communication
 const COMMAND_WAIT_TIME_MS: u8 = 1;
 
+/// The sensor idles between measurements and measures on command
+#[derive(Debug, Clone, Copy)]
+pub struct SingleShot;
+
+/// The sensor measures on its own at a [`Rate`] and holds the latest result until fetched
+#[derive(Debug, Clone, Copy)]
+pub struct Periodic;
+
 #[derive(Debug, Clone)]
-pub struct Sht3x<I2C> {
+pub struct Sht3x<I2C, MODE = SingleShot> {
     i2c: I2C,
     address: Address,
+    mode: PhantomData<MODE>,
 }
 
-impl<I2C: I2c, E> Sht3x<I2C>
+impl<I2C: I2c, E, MODE> Sht3x<I2C, MODE>
 where
     I2C: I2c<Error = E>,
 {
-    /// Creates a new driver.
-    pub const fn new(i2c: I2C, address: Address) -> Self {
-        Self { i2c, address }
-    }
-
     /// Send an I2C command.
     async fn command<D: DelayNs>(
         &mut self,
@@ -51,6 +60,120 @@ where
         Ok(())
     }
 
+    /// Send a command that changes the acquisition mode, handing the driver back on failure as
+    /// the sensor stays in the mode it was in.
+    async fn change_mode<D: DelayNs, M>(
+        mut self,
+        command: Command,
+        delay: &mut D,
+    ) -> Result<Sht3x<I2C, M>, (Self, Error<E>)> {
+        match self.command(command, delay, None).await {
+            Ok(()) => Ok(Sht3x {
+                i2c: self.i2c,
+                address: self.address,
+                mode: PhantomData,
+            }),
+            Err(err) => Err((self, err)),
+        }
+    }
+
+    /// Read the status register.
+    pub async fn status<D: DelayNs>(&mut self, delay: &mut D) -> Result<Status, Error<E>> {
+        self.command(Command::Status, delay, None).await?;
+        let mut buf = [0; 3];
+        self.i2c
+            .read(self.address as u8, &mut buf)
+            .await
+            .map_err(Error::I2c)?;
+
+        let status = check_crc([buf[0], buf[1]], buf[2])?;
+        Ok(Status::from_bits_truncate(status))
+    }
+
+    /// Clear the status register.
+    pub async fn clear_status<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        self.command(Command::ClearStatus, delay, None).await
+    }
+
+    /// Switch the heater on or off.
+    pub async fn set_heater<D: DelayNs>(
+        &mut self,
+        enabled: bool,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        let command = if enabled {
+            Command::HeaterEnable
+        } else {
+            Command::HeaterDisable
+        };
+        self.command(command, delay, None).await
+    }
+
+    /// Read one of the alert limits.
+    pub async fn alert_limit<D: DelayNs>(
+        &mut self,
+        kind: AlertLimitKind,
+        delay: &mut D,
+    ) -> Result<AlertLimit, Error<E>> {
+        self.command(Command::ReadAlertLimit(kind), delay, None)
+            .await?;
+        let mut buf = [0; 3];
+        self.i2c
+            .read(self.address as u8, &mut buf)
+            .await
+            .map_err(Error::I2c)?;
+
+        check_crc([buf[0], buf[1]], buf[2]).map(AlertLimit::from_word)
+    }
+
+    /// Write one of the alert limits, rounded to the 9 temperature and 7 humidity bits the sensor
+    /// keeps. A write with a bad CRC is ignored by the sensor and flagged in [`Status`].
+    pub async fn set_alert_limit<D: DelayNs>(
+        &mut self,
+        kind: AlertLimitKind,
+        limit: AlertLimit,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        let [cmd_hi, cmd_lo] = Command::WriteAlertLimit(kind).value().to_be_bytes();
+        let data = limit.to_word().to_be_bytes();
+        self.i2c
+            .write(
+                self.address as u8,
+                &[cmd_hi, cmd_lo, data[0], data[1], crc8(data)],
+            )
+            .await
+            .map_err(Error::I2c)?;
+
+        delay.delay_ms(COMMAND_WAIT_TIME_MS.into()).await;
+        Ok(())
+    }
+
+    /// Write all four alert limits.
+    pub async fn set_alert_limits<D: DelayNs>(
+        &mut self,
+        limits: &AlertLimits,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        for (kind, limit) in limits.iter() {
+            self.set_alert_limit(kind, limit, delay).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C: I2c, E> Sht3x<I2C, SingleShot>
+where
+    I2C: I2c<Error = E>,
+{
+    /// Creates a new driver.
+    pub const fn new(i2c: I2C, address: Address) -> Self {
+        Self {
+            i2c,
+            address,
+            mode: PhantomData,
+        }
+    }
+
     /// Take a temperature and humidity measurement.
     pub async fn measure<D: DelayNs>(
         &mut self,
@@ -70,13 +193,7 @@ where
             .await
             .map_err(Error::I2c)?;
 
-        let temperature = check_crc([buf[0], buf[1]], buf[2]).map(convert_temperature)?;
-        let humidity = check_crc([buf[3], buf[4]], buf[5]).map(convert_humidity)?;
-
-        Ok(Measurement {
-            temperature,
-            humidity,
-        })
+        parse_measurement(buf)
     }
 
     /// Soft reset the sensor.
@@ -85,25 +202,72 @@ where
             .await
     }
 
-    /// Read the status register.
-    pub async fn status<D: DelayNs>(&mut self, delay: &mut D) -> Result<Status, Error<E>> {
-        self.command(Command::Status, delay, None).await?;
-        let mut buf = [0; 3];
+    /// Start periodic data acquisition. The first measurement is ready one period later.
+    pub async fn start_periodic<D: DelayNs>(
+        self,
+        rate: Rate,
+        rpt: Repeatability,
+        delay: &mut D,
+    ) -> Result<Sht3x<I2C, Periodic>, (Self, Error<E>)> {
+        self.change_mode(Command::Periodic(rate, rpt), delay).await
+    }
+
+    /// Start periodic data acquisition with accelerated response time, measuring at 4 Hz.
+    pub async fn start_art<D: DelayNs>(
+        self,
+        delay: &mut D,
+    ) -> Result<Sht3x<I2C, Periodic>, (Self, Error<E>)> {
+        self.change_mode(Command::PeriodicWithART, delay).await
+    }
+}
+
+impl<I2C: I2c> Sht3x<I2C, Periodic> {
+    /// Read the latest measurement without waiting. Returns `None` if the sensor has not measured
+    /// since the last fetch, as it then NACKs the read header.
+    pub async fn fetch(&mut self) -> Result<Option<Measurement>, Error<I2C::Error>> {
+        let cmd_bytes = Command::FetchData.value().to_be_bytes();
         self.i2c
-            .read(self.address as u8, &mut buf)
+            .write(self.address as u8, &cmd_bytes)
             .await
             .map_err(Error::I2c)?;
 
-        let status = check_crc([buf[0], buf[1]], buf[2])?;
-        Ok(Status::from_bits_truncate(status))
+        let mut buf = [0; 6];
+        match self.i2c.read(self.address as u8, &mut buf).await {
+            Ok(()) => parse_measurement(buf).map(Some),
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::NoAcknowledge(
+                        NoAcknowledgeSource::Address | NoAcknowledgeSource::Unknown
+                    )
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(err) => Err(Error::I2c(err)),
+        }
     }
 
-    /// Clear the status register.
-    pub async fn clear_status<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
-        self.command(Command::ClearStatus, delay, None).await
+    /// Stop periodic data acquisition and go back to single shot mode.
+    pub async fn stop<D: DelayNs>(
+        self,
+        delay: &mut D,
+    ) -> Result<Sht3x<I2C, SingleShot>, (Self, Error<I2C::Error>)> {
+        self.change_mode(Command::Break, delay).await
     }
 }
 
+/// Check the CRC of both words of a measurement and convert them.
+fn parse_measurement<E>(buf: [u8; 6]) -> Result<Measurement, Error<E>> {
+    let temperature = check_crc([buf[0], buf[1]], buf[2]).map(convert_temperature)?;
+    let humidity = check_crc([buf[3], buf[4]], buf[5]).map(convert_humidity)?;
+
+    Ok(Measurement {
+        temperature,
+        humidity,
+    })
+}
+
 const fn convert_temperature(raw: u16) -> i32 {
     -4500 + (17500 * raw as i32) / 65535
 }
@@ -168,8 +332,9 @@ pub enum ClockStretch {
 }
 
 /// Periodic data acquisition rate
-#[allow(non_camel_case_types, unused)]
-enum Rate {
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Rate {
     /// 0.5 measurements per second
     R0_5,
     /// 1 measurement per second
@@ -182,6 +347,19 @@ enum Rate {
     R10,
 }
 
+impl Rate {
+    /// Time between measurements in milliseconds
+    pub const fn period_ms(&self) -> u16 {
+        match *self {
+            Rate::R0_5 => 2000,
+            Rate::R1 => 1000,
+            Rate::R2 => 500,
+            Rate::R4 => 250,
+            Rate::R10 => 100,
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub enum Repeatability {
     High,
@@ -200,7 +378,6 @@ impl Repeatability {
     }
 }
 
-#[allow(unused)]
 enum Command {
     SingleShot(ClockStretch, Repeatability),
     Periodic(Rate, Repeatability),
@@ -212,6 +389,8 @@ enum Command {
     HeaterDisable,
     Status,
     ClearStatus,
+    ReadAlertLimit(AlertLimitKind),
+    WriteAlertLimit(AlertLimitKind),
 }
 
 impl Command {
@@ -274,6 +453,17 @@ impl Command {
             Command::Status => 0xF32D,
             // Table 18
             Command::ClearStatus => 0x3041,
+
+            // Application note SHT3x-DIS Alert Mode
+            // Table 3
+            Command::ReadAlertLimit(AlertLimitKind::HighSet) => 0xE11F,
+            Command::ReadAlertLimit(AlertLimitKind::HighClear) => 0xE114,
+            Command::ReadAlertLimit(AlertLimitKind::LowClear) => 0xE109,
+            Command::ReadAlertLimit(AlertLimitKind::LowSet) => 0xE102,
+            Command::WriteAlertLimit(AlertLimitKind::HighSet) => 0x611D,
+            Command::WriteAlertLimit(AlertLimitKind::HighClear) => 0x6116,
+            Command::WriteAlertLimit(AlertLimitKind::LowClear) => 0x610B,
+            Command::WriteAlertLimit(AlertLimitKind::LowSet) => 0x6100,
         }
     }
 }
@@ -284,6 +474,152 @@ pub struct Measurement {
     pub humidity: u16,
 }
 
+/// One of the four alert limits. The ALERT pin is raised when a periodic measurement goes
+/// above the high set or below the low set limit, and cleared once it is back between the clear
+/// limits.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AlertLimitKind {
+    HighSet,
+    HighClear,
+    LowClear,
+    LowSet,
+}
+
+/// An alert limit in centi degC and centi %RH, like [`Measurement`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AlertLimit {
+    pub temperature: i32,
+    pub humidity: u16,
+}
+
+impl AlertLimit {
+    /// Pack into the limit word, the 7 MSBs of raw humidity followed by the 9 MSBs of raw
+    /// temperature
+    fn to_word(self) -> u16 {
+        let temperature = self.temperature.clamp(-4500, 13000);
+        let raw_temperature = ((temperature + 4500) * 65535 / 17500) as u16;
+        let raw_humidity = (self.humidity.min(10000) as u32 * 65535 / 10000) as u16;
+        (raw_humidity & 0xFE00) | (raw_temperature >> 7)
+    }
+
+    fn from_word(word: u16) -> Self {
+        Self {
+            temperature: convert_temperature((word & 0x01FF) << 7),
+            humidity: convert_humidity(word & 0xFE00),
+        }
+    }
+}
+
+/// All four alert limits
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AlertLimits {
+    pub high_set: AlertLimit,
+    pub high_clear: AlertLimit,
+    pub low_clear: AlertLimit,
+    pub low_set: AlertLimit,
+}
+
+impl AlertLimits {
+    /// The limits after a reset, 60 degC or 80 %RH to set and 58 degC or 79 %RH to clear the high
+    /// alert, -10 degC or 20 %RH to set and -9 degC or 22 %RH to clear the low alert
+    pub const DEFAULT: AlertLimits = AlertLimits {
+        high_set: AlertLimit {
+            temperature: 6000,
+            humidity: 8000,
+        },
+        high_clear: AlertLimit {
+            temperature: 5800,
+            humidity: 7900,
+        },
+        low_clear: AlertLimit {
+            temperature: -900,
+            humidity: 2200,
+        },
+        low_set: AlertLimit {
+            temperature: -1000,
+            humidity: 2000,
+        },
+    };
+
+    fn iter(&self) -> impl Iterator<Item = (AlertLimitKind, AlertLimit)> {
+        [
+            (AlertLimitKind::HighSet, self.high_set),
+            (AlertLimitKind::HighClear, self.high_clear),
+            (AlertLimitKind::LowClear, self.low_clear),
+            (AlertLimitKind::LowSet, self.low_set),
+        ]
+        .into_iter()
+    }
+}
+
+impl Default for AlertLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Condensation recovery, runs the heater for a while whenever the humidity reads near
+/// saturation and then lets the sensor cool before its readings are trusted again
+#[derive(Debug, Clone)]
+pub struct CondensationRecovery {
+    /// Humidity that starts a heating cycle in centi %RH
+    threshold: u16,
+    heat_ms: u64,
+    cool_ms: u64,
+    state: RecoveryState,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum RecoveryState {
+    Idle,
+    Heating { since_ms: u64 },
+    Cooling { since_ms: u64 },
+}
+
+impl CondensationRecovery {
+    /// Heat for `heat_ms` whenever the humidity reaches `threshold` centi %RH, then cool for
+    /// `cool_ms`
+    pub const fn new(threshold: u16, heat_ms: u64, cool_ms: u64) -> Self {
+        Self {
+            threshold,
+            heat_ms,
+            cool_ms,
+            state: RecoveryState::Idle,
+        }
+    }
+
+    /// Step the cycle with the latest measurement, taken at `now_ms` on any clock that counts up
+    /// in ms. Afterwards [`Self::heater`] says whether the heater should be on.
+    pub fn update(&mut self, measurement: &Measurement, now_ms: u64) {
+        self.state = match self.state {
+            RecoveryState::Idle if measurement.humidity >= self.threshold => {
+                RecoveryState::Heating { since_ms: now_ms }
+            }
+            RecoveryState::Heating { since_ms }
+                if now_ms.saturating_sub(since_ms) >= self.heat_ms =>
+            {
+                RecoveryState::Cooling { since_ms: now_ms }
+            }
+            RecoveryState::Cooling { since_ms }
+                if now_ms.saturating_sub(since_ms) >= self.cool_ms =>
+            {
+                RecoveryState::Idle
+            }
+            state => state,
+        };
+    }
+
+    /// Whether the heater should be on
+    pub fn heater(&self) -> bool {
+        matches!(self.state, RecoveryState::Heating { .. })
+    }
+
+    /// Whether a cycle is running, when the heater skews the readings
+    pub fn recovering(&self) -> bool {
+        self.state != RecoveryState::Idle
+    }
+}
+
 bitflags! {
     /// Status register
     pub struct Status: u16 {
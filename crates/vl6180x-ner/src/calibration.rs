@@ -0,0 +1,80 @@
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+
+use crate::{
+    error::Error,
+    register::{Register16Bit, Register8Bit},
+    VL6180X,
+};
+
+/// Range measurements averaged by each calibration, as in AN4545
+const CALIBRATION_SAMPLES: u32 = 10;
+
+impl<MODE, I2C, E> VL6180X<MODE, I2C>
+where
+    I2C: I2c<Error = E>,
+{
+    /// AN4545 section 2.12.3 "Offset calibration procedure"
+    pub(crate) async fn calibrate_range_offset_direct<D: DelayNs>(
+        &mut self,
+        target_mm: u16,
+        delay: &mut D,
+    ) -> Result<i8, Error<E>> {
+        self.apply_range_offset(0).await?;
+
+        let mut sum = 0;
+        for _ in 0..CALIBRATION_SAMPLES {
+            sum += self.poll_range_mm_single_blocking_direct(delay).await? as u32;
+        }
+        let average = (sum / CALIBRATION_SAMPLES) as i32;
+        let offset = (target_mm as i32 - average).clamp(i8::MIN as i32, i8::MAX as i32) as i8;
+
+        self.apply_range_offset(offset).await?;
+        self.config.range_offset = Some(offset);
+        Ok(offset)
+    }
+
+    /// AN4545 section 2.12.4 "Cross-talk calibration procedure"
+    pub(crate) async fn calibrate_crosstalk_direct<D: DelayNs>(
+        &mut self,
+        target_mm: u16,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        if target_mm == 0 {
+            return Err(Error::InvalidConfigurationValue(target_mm));
+        }
+        self.apply_crosstalk_compensation_rate(0).await?;
+
+        let mut range_sum = 0;
+        let mut rate_sum = 0;
+        for _ in 0..CALIBRATION_SAMPLES {
+            range_sum += self.poll_range_mm_single_blocking_direct(delay).await? as u64;
+            rate_sum += self
+                .read_named_register_16bit(Register16Bit::RESULT__RANGE_RETURN_RATE)
+                .await? as u64;
+        }
+        // average rate * (1 - average range / target), nothing to compensate if the target reads
+        // at or past where it is
+        let short = (CALIBRATION_SAMPLES as u64 * target_mm as u64).saturating_sub(range_sum);
+        let rate = rate_sum * short / (CALIBRATION_SAMPLES as u64).pow(2) / target_mm as u64;
+        let rate = rate.min(u16::MAX as u64) as u16;
+
+        self.apply_crosstalk_compensation_rate(rate).await?;
+        Ok(rate)
+    }
+
+    /// Write the part-to-part offset in mm, scaled to the current range scaling
+    pub(crate) async fn apply_range_offset(&mut self, offset_mm: i8) -> Result<(), E> {
+        self.config.ptp_offset = offset_mm;
+        self.write_named_register(
+            Register8Bit::SYSRANGE__PART_TO_PART_RANGE_OFFSET,
+            (offset_mm / self.config.range_scaling as i8) as u8,
+        )
+        .await
+    }
+
+    pub(crate) async fn apply_crosstalk_compensation_rate(&mut self, rate: u16) -> Result<(), E> {
+        self.config.crosstalk_compensation_rate = rate;
+        self.write_named_register_16bit(Register16Bit::SYSRANGE__CROSSTALK_COMPENSATION_RATE, rate)
+            .await
+    }
+}
@@ -30,15 +30,54 @@ pub enum RangeInterruptMode {
     NewSampleReady = 0b00_000_100,
 }
 
+/// I2C address of a VL6180X after it powers up
+pub const DEFAULT_ADDRESS: u8 = 0x29;
+
+/// Range offset and crosstalk calibration of one sensor, from
+/// [`calibrate_range_offset`](crate::VL6180X::calibrate_range_offset) and
+/// [`calibrate_crosstalk`](crate::VL6180X::calibrate_crosstalk).
+///
+/// Neither survives a power cycle of the sensor, so store the calibration and apply it at init
+/// with [`Config::set_range_calibration`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeCalibration {
+    /// Part-to-part range offset in mm, added to every range
+    pub offset_mm: i8,
+    /// Crosstalk compensation rate in Mcps, 9.7 fixed point
+    pub crosstalk_rate: u16,
+}
+
+impl RangeCalibration {
+    /// Size of [`to_bytes`](RangeCalibration::to_bytes)
+    pub const SIZE: usize = 3;
+
+    /// The offset then the little endian crosstalk rate, for storing
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let [rate_lo, rate_hi] = self.crosstalk_rate.to_le_bytes();
+        [self.offset_mm as u8, rate_lo, rate_hi]
+    }
+
+    /// Read back [`to_bytes`](RangeCalibration::to_bytes)
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        Self {
+            offset_mm: bytes[0] as i8,
+            crosstalk_rate: u16::from_le_bytes([bytes[1], bytes[2]]),
+        }
+    }
+}
+
 /// Config information for the driver.
 #[derive(Debug, Clone, Copy)]
 pub struct Config {
-    pub(super) ptp_offset: u8,
+    pub(super) ptp_offset: i8,
+    pub(super) range_offset: Option<i8>,
+    pub(super) crosstalk_compensation_rate: u16,
 
     pub(super) address: u8,
     pub(super) range_scaling: u8,
     pub(super) ambient_scaling: u8,
-    pub(super) poll_max_loop: u16,
+    pub(super) poll_interval_ms: u16,
+    pub(super) poll_timeout_ms: u16,
 
     // Performance tuning
     pub(super) readout_averaging_period_multiplier: u8,
@@ -72,9 +111,12 @@ impl Config {
     /// Defaults are based on values from [ST application note AN4545](https://www.st.com/resource/en/application_note/an4545-vl6180x-basic-ranging-application-note-stmicroelectronics.pdf)
     pub fn new() -> Self {
         Config {
-            address: 0x29,
+            address: DEFAULT_ADDRESS,
             ptp_offset: 0,
-            poll_max_loop: 500,
+            range_offset: None,
+            crosstalk_compensation_rate: 0,
+            poll_interval_ms: 1,
+            poll_timeout_ms: 1000,
 
             range_scaling: 1,
             ambient_scaling: 1,
@@ -103,12 +145,54 @@ impl Config {
         }
     }
 
-    /// Set the max number of loops during polling measurement.
+    /// Set the time between polls of the interrupt status during a blocking read.
+    ///
+    /// Min = 1ms; Default = 1ms
+    pub fn set_poll_interval_ms(&mut self, time_ms: u16) -> Result<(), Error<()>> {
+        if time_ms == 0 {
+            return Err(Error::InvalidConfigurationValue(time_ms));
+        }
+        self.poll_interval_ms = time_ms;
+        Ok(())
+    }
+
+    /// Set how long a blocking read waits for its result before returning [Error::Timeout].
+    ///
+    /// Default = 1000ms
+    ///
+    /// Only the delays between polls are counted, so the time spent polling on the bus comes on
+    /// top. In continuous mode it should be longer than the inter-measurement period.
+    pub fn set_poll_timeout_ms(&mut self, time_ms: u16) {
+        self.poll_timeout_ms = time_ms;
+    }
+
+    /// Set the part-to-part range offset in mm, in place of the one programmed at the factory.
+    ///
+    /// Default = the factory offset
+    ///
+    /// Find it with [calibrate_range_offset](crate::VL6180X::calibrate_range_offset) once the
+    /// sensor is behind its cover window.
+    pub fn set_range_offset(&mut self, offset_mm: i8) {
+        self.range_offset = Some(offset_mm);
+    }
+
+    /// Set the crosstalk compensation rate in Mcps, 9.7 fixed point.
     ///
-    /// Default = 500;
-    pub fn set_poll_max_loop(&mut self, max_loop: u16) {
-        self.poll_max_loop = max_loop;
+    /// Default = 0, no compensation
+    ///
+    /// A cover window reflects some of the emitted light straight back, which makes ranges read
+    /// short, more so the further and darker the target. Find the rate with
+    /// [calibrate_crosstalk](crate::VL6180X::calibrate_crosstalk).
+    pub fn set_crosstalk_compensation_rate(&mut self, rate: u16) {
+        self.crosstalk_compensation_rate = rate;
     }
+
+    /// Set both the range offset and the crosstalk compensation rate from a stored calibration.
+    pub fn set_range_calibration(&mut self, calibration: RangeCalibration) {
+        self.set_range_offset(calibration.offset_mm);
+        self.set_crosstalk_compensation_rate(calibration.crosstalk_rate);
+    }
+
     /// The range max convergence time (ms) is made up of the convergence time and sampling period.
     ///
     /// Min = 2ms; Max = 63ms; Default = 49ms
@@ -333,6 +417,9 @@ impl Config {
     }
 
     /// Set the i2c address for the initial connection
+    ///
+    /// After [power_on_and_init](crate::VL6180X::power_on_and_init) the sensor is moved to
+    /// this address from [DEFAULT_ADDRESS], where it always boots.
     pub fn set_i2c_address(&mut self, address: u8) {
         self.address = address;
     }
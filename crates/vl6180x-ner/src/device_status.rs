@@ -1,10 +1,11 @@
 use embedded_hal::digital::OutputPin;
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
 use super::VL6180X;
 use crate::{
     error::{Error, Error2},
     register::{Register8Bit::*, SysInterruptClearCode},
+    DEFAULT_ADDRESS,
 };
 
 impl<MODE, I2C, E> VL6180X<MODE, I2C>
@@ -77,30 +78,48 @@ where
         x_shutdown_pin.set_low().map_err(|e| Error::GpioPinError(e))
     }
 
-    pub(crate) async fn power_on_and_init_direct<PE, P: OutputPin<Error = PE>>(
+    pub(crate) async fn power_on_and_init_direct<PE, P: OutputPin<Error = PE>, D: DelayNs>(
         &mut self,
         x_shutdown_pin: &mut P,
+        delay: &mut D,
     ) -> Result<(), Error2<E, PE>> {
         x_shutdown_pin
             .set_high()
             .map_err(|e| Error2::GpioPinError(e))?;
-        self.wait_device_booted()
-            .await
-            .map_err(|e| Error2::<E, PE>::BusError(e))?;
+        // the sensor always boots at the default address, so is moved back to its own
+        let address = self.config.address;
+        self.config.address = DEFAULT_ADDRESS;
+        let booted = self.wait_device_booted(delay).await;
+        let moved = match booted {
+            Ok(()) if address != DEFAULT_ADDRESS => self
+                .write_only_named_register(I2C_SLAVE__DEVICE_ADDRESS, address)
+                .await
+                .map_err(|e| Error2::BusError(e)),
+            booted => booted,
+        };
+        self.config.address = address;
+        moved?;
         self.init_hardware()
             .await
             .map_err(|e| Error2::<E, PE>::BusError(e))?;
         Ok(())
     }
 
-    async fn wait_device_booted(&mut self) -> Result<(), E> {
+    /// The sensor NACKs until it has booted, then reads fresh out of reset
+    async fn wait_device_booted<PE, D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<(), Error2<E, PE>> {
+        let mut waited_ms: u32 = 0;
         loop {
-            if let Ok(result) = self.read_named_register(SYSTEM__FRESH_OUT_OF_RESET).await {
-                if result == 0x01 {
-                    break;
-                }
+            if let Ok(0x01) = self.read_named_register(SYSTEM__FRESH_OUT_OF_RESET).await {
+                return Ok(());
+            }
+            if waited_ms >= self.config.poll_timeout_ms as u32 {
+                return Err(Error2::Timeout);
             }
+            delay.delay_ms(self.config.poll_interval_ms as u32).await;
+            waited_ms += self.config.poll_interval_ms as u32;
         }
-        Ok(())
     }
 }
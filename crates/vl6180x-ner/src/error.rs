@@ -7,7 +7,8 @@ pub enum Error<E> {
     InvalidDevice(u8),
     /// Underlying bus error.
     BusError(E),
-    /// Timeout.
+    /// A blocking read polled for longer than the
+    /// [poll timeout](crate::Config::set_poll_timeout_ms) without the result becoming ready.
     Timeout,
     /// I2C address not valid, needs to be between 0x08 and 0x77.
     /// It is a 7 bit address thus the range is 0x00 - 0x7F but
@@ -37,6 +38,8 @@ pub enum Error2<E, F> {
     InvalidMethod(mode::dynamic::OperatingMode),
     /// Error when setting pin output state.
     GpioPinError(F),
+    /// The sensor did not boot within the [poll timeout](crate::Config::set_poll_timeout_ms).
+    Timeout,
 }
 
 impl<E, F> From<E> for Error2<E, F> {
@@ -23,6 +23,18 @@ where
         Ok(data[0])
     }
 
+    /// Reads consecutive registers starting at a named 8-bit register into `data`, the sensor
+    /// moves on to the next register after each byte
+    pub(crate) async fn read_named_registers(
+        &mut self,
+        reg: Register8Bit,
+        data: &mut [u8],
+    ) -> Result<(), E> {
+        let reg: [u8; 2] = (reg as u16).to_be_bytes();
+
+        self.com.write_read(self.config.address, &reg, data).await
+    }
+
     /// Reads a named 16-bit register
     pub(crate) async fn read_named_register_16bit(&mut self, reg: Register16Bit) -> Result<u16, E> {
         self.read_register_16bit(reg as u16).await
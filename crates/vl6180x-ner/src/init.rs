@@ -13,10 +13,15 @@ where
     /// Initialize sensor with settings from ST application note AN4545,
     /// section "SR03 settings" - "Mandatory : private registers"
     pub(crate) async fn init_hardware(&mut self) -> Result<(), E> {
-        // Store part-to-part range offset so it can be adjusted if scaling is changed
-        self.config.ptp_offset = self
-            .read_named_register(SYSRANGE__PART_TO_PART_RANGE_OFFSET)
-            .await?;
+        // Store part-to-part range offset so it can be adjusted if scaling is changed, a
+        // calibrated offset replaces the one programmed at the factory
+        self.config.ptp_offset = match self.config.range_offset {
+            Some(offset) => offset,
+            None => {
+                self.read_named_register(SYSRANGE__PART_TO_PART_RANGE_OFFSET)
+                    .await? as i8
+            }
+        };
 
         self.write_register(0x207, 0x01).await?;
         self.write_register(0x208, 0x01).await?;
@@ -119,6 +124,9 @@ where
 
         self.set_range_scaling(self.config.range_scaling).await?;
 
+        self.apply_crosstalk_compensation_rate(self.config.crosstalk_compensation_rate)
+            .await?;
+
         Ok(())
     }
 
@@ -176,11 +184,7 @@ where
             .await?;
 
         // apply scaling on part-to-part offset
-        self.write_named_register(
-            SYSRANGE__PART_TO_PART_RANGE_OFFSET,
-            self.config.ptp_offset / scaling,
-        )
-        .await?;
+        self.apply_range_offset(self.config.ptp_offset).await?;
 
         // apply scaling on CrossTalkValidHeight
         self.write_named_register(
@@ -3,7 +3,7 @@
 //!
 //! for more examples please see [vl6180x_stm32f401_examples](https://github.com/shaoyuancc/vl6180x_stm32f401_examples)
 //!
-//! ```rust
+//! ```rust,ignore
 //! #![no_std]
 //! #![no_main]
 //!
@@ -17,12 +17,13 @@
 //!
 //! #[entry]
 //! fn main() -> ! {
-//!     if let (Some(dp), Some(_cp)) = (
+//!     if let (Some(dp), Some(cp)) = (
 //!         pac::Peripherals::take(),
 //!         cortex_m::peripheral::Peripherals::take(),
 //!     ) {
 //!         let rcc = dp.RCC.constrain();
 //!         let clocks = rcc.cfgr.sysclk(48.MHz()).freeze();
+//!         let mut delay = cp.SYST.delay(&clocks);
 //!
 //!         let gpiob = dp.GPIOB.split();
 //!         let scl = gpiob
@@ -41,7 +42,7 @@
 //!         let mut tof = vl6180x::VL6180X::new(i2c).expect("vl");
 //!
 //!         loop {
-//!             match tof.poll_range_mm_single_blocking() {
+//!             match tof.poll_range_mm_single_blocking(&mut delay) {
 //!                 Ok(range) => hprintln!("Range Single Poll: {}mm", range).unwrap(),
 //!                 Err(e) => hprintln!("Error reading TOF sensor Single Poll! {:?}", e).unwrap(),
 //!             }
@@ -75,14 +76,19 @@
 pub use crate::register::ResultInterruptStatusGpioCode;
 pub use config::*;
 use embedded_hal::digital::{InputPin, OutputPin};
-pub use error::Error;
+pub use error::{AmbientStatusErrorCode, Error, Error2, RangeStatusErrorCode};
 pub use mode::*;
+pub use multi::MultiVL6180X;
+pub use range_measurement::{RangeMeasurement, RangeQuality};
+mod calibration;
 mod config;
 mod device_status;
 mod error;
 mod i2c_interface;
 mod init;
 mod mode;
+mod multi;
+mod range_measurement;
 mod read_measurements;
 mod register;
 mod start_stop_measurements;
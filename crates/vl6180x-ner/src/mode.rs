@@ -6,12 +6,12 @@ mod ready;
 pub use continuous::*;
 pub use dynamic::*;
 use embedded_hal::digital::OutputPin;
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 pub use powered_off::*;
 pub use ready::*;
 
 use crate::error::Error;
-use crate::VL6180X;
+use crate::{RangeCalibration, RangeMeasurement, VL6180X};
 
 impl<MODE, I2C, E> VL6180X<MODE, I2C>
 where
@@ -47,8 +47,13 @@ where
 {
     /// Blocking read of the range mesurement.
     /// The reading (whether single or continuous) must already have been started.
-    pub async fn read_range_mm_blocking(&mut self) -> Result<u16, Error<E>> {
-        self.read_range_mm_blocking_direct().await
+    /// Polls for the result with `delay` between polls, and returns [Error::Timeout] if it is not
+    /// ready within the [poll timeout](crate::Config::set_poll_timeout_ms).
+    pub async fn read_range_mm_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.read_range_mm_blocking_direct(delay).await
     }
 
     /// Non-blocking read of the range measurement.
@@ -58,10 +63,33 @@ where
         self.read_range_mm_direct().await
     }
 
+    /// Blocking read of the range measurement with its signal rates, counts and convergence
+    /// times. Like [`read_range_mm_blocking`](VL6180X::read_range_mm_blocking), but a range
+    /// status error is kept in the [RangeMeasurement] instead of being returned as an error.
+    pub async fn read_range_measurement_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<RangeMeasurement, Error<E>> {
+        self.read_range_measurement_blocking_direct(delay).await
+    }
+
+    /// Non-blocking read of the range measurement with its signal rates, counts and convergence
+    /// times. Like [`read_range_mm`](VL6180X::read_range_mm), but a range status error is kept in
+    /// the [RangeMeasurement] instead of being returned as an error.
+    /// Returns [Error::ResultNotReady] if the result is not ready.
+    pub async fn read_range_measurement(&mut self) -> Result<RangeMeasurement, Error<E>> {
+        self.read_range_measurement_direct().await
+    }
+
     /// Blocking read of the ambient light mesurement.
     /// The reading (whether single or continuous) must already have been started.
-    pub async fn read_ambient_lux_blocking(&mut self) -> Result<f32, Error<E>> {
-        self.read_ambient_lux_blocking_direct().await
+    /// Polls for the result with `delay` between polls, and returns [Error::Timeout] if it is not
+    /// ready within the [poll timeout](crate::Config::set_poll_timeout_ms).
+    pub async fn read_ambient_lux_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<f32, Error<E>> {
+        self.read_ambient_lux_blocking_direct(delay).await
     }
 
     /// Non-blocking read of the ambient light measurement.
@@ -73,8 +101,13 @@ where
 
     /// Blocking read of the raw ambient light mesurement.
     /// The reading (whether single or continuous) must already have been started.
-    pub async fn read_ambient_blocking(&mut self) -> Result<u16, Error<E>> {
-        self.read_ambient_blocking_direct().await
+    /// Polls for the result with `delay` between polls, and returns [Error::Timeout] if it is not
+    /// ready within the [poll timeout](crate::Config::set_poll_timeout_ms).
+    pub async fn read_ambient_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.read_ambient_blocking_direct(delay).await
     }
 
     /// Non-blocking read of the raw ambient light measurement.
@@ -130,6 +163,15 @@ where
     I2C: I2c<Error = E>,
     MODE: AllowCommunication,
 {
+    /// The range offset and crosstalk compensation the sensor is using, to store and apply at
+    /// the next init with [`Config::set_range_calibration`](crate::Config::set_range_calibration)
+    pub fn range_calibration(&self) -> RangeCalibration {
+        RangeCalibration {
+            offset_mm: self.config.ptp_offset,
+            crosstalk_rate: self.config.crosstalk_compensation_rate,
+        }
+    }
+
     /// Read the model id of the sensor. Should return 0xB4.
     pub async fn read_model_id(&mut self) -> Result<u8, Error<E>> {
         self.read_model_id_direct().await
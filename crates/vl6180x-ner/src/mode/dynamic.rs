@@ -1,7 +1,7 @@
 use crate::error::{Error, Error2};
-use crate::VL6180X;
+use crate::{RangeMeasurement, VL6180X};
 use embedded_hal::digital::OutputPin;
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 use OperatingMode::*;
 
 /// A mode where the state is kept track of at runtime, instead of being
@@ -35,30 +35,89 @@ impl DynamicMode {
             operating_mode: Ready,
         }
     }
+
+    pub(crate) fn powered_off() -> Self {
+        Self {
+            operating_mode: PoweredOff,
+        }
+    }
 }
 
 impl<I2C, E> VL6180X<DynamicMode, I2C>
 where
     I2C: I2c<Error = E>,
 {
+    /// The mode the sensor is in, which decides the methods that are valid
+    pub fn operating_mode(&self) -> OperatingMode {
+        self.mode.operating_mode
+    }
+
     /// Same functionality as [`poll_range_mm_single_blocking()`](VL6180X::poll_range_mm_single_blocking)
     /// but with a check on the current [OperatingMode].
     /// Valid when OperatingMode is [Ready], otherwise returns [Error::InvalidMethod]
-    pub async fn try_poll_range_mm_single_blocking(&mut self) -> Result<u16, Error<E>> {
+    pub async fn try_poll_range_mm_single_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        if self.mode.operating_mode != Ready {
+            return Err(Error::InvalidMethod(self.mode.operating_mode));
+        }
+        self.poll_range_mm_single_blocking_direct(delay).await
+    }
+
+    /// Same functionality as [`poll_range_measurement_single_blocking()`](VL6180X::poll_range_measurement_single_blocking)
+    /// but with a check on the current [OperatingMode].
+    /// Valid when OperatingMode is [Ready], otherwise returns [Error::InvalidMethod]
+    pub async fn try_poll_range_measurement_single_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<RangeMeasurement, Error<E>> {
         if self.mode.operating_mode != Ready {
             return Err(Error::InvalidMethod(self.mode.operating_mode));
         }
-        self.poll_range_mm_single_blocking_direct().await
+        self.poll_range_measurement_single_blocking_direct(delay)
+            .await
     }
 
     /// Same functionality as [`poll_ambient_lux_single_blocking()`](VL6180X::poll_ambient_lux_single_blocking)
     /// but with a check on the current [OperatingMode].
     /// Valid when OperatingMode is [Ready], otherwise returns [Error::InvalidMethod]
-    pub async fn try_poll_ambient_lux_single_blocking(&mut self) -> Result<f32, Error<E>> {
+    pub async fn try_poll_ambient_lux_single_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<f32, Error<E>> {
+        if self.mode.operating_mode != Ready {
+            return Err(Error::InvalidMethod(self.mode.operating_mode));
+        }
+        self.poll_ambient_lux_single_blocking_direct(delay).await
+    }
+
+    /// Same functionality as [`calibrate_range_offset()`](VL6180X::calibrate_range_offset)
+    /// but with a check on the current [OperatingMode].
+    /// Valid when OperatingMode is [Ready], otherwise returns [Error::InvalidMethod]
+    pub async fn try_calibrate_range_offset<D: DelayNs>(
+        &mut self,
+        target_mm: u16,
+        delay: &mut D,
+    ) -> Result<i8, Error<E>> {
+        if self.mode.operating_mode != Ready {
+            return Err(Error::InvalidMethod(self.mode.operating_mode));
+        }
+        self.calibrate_range_offset_direct(target_mm, delay).await
+    }
+
+    /// Same functionality as [`calibrate_crosstalk()`](VL6180X::calibrate_crosstalk)
+    /// but with a check on the current [OperatingMode].
+    /// Valid when OperatingMode is [Ready], otherwise returns [Error::InvalidMethod]
+    pub async fn try_calibrate_crosstalk<D: DelayNs>(
+        &mut self,
+        target_mm: u16,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
         if self.mode.operating_mode != Ready {
             return Err(Error::InvalidMethod(self.mode.operating_mode));
         }
-        self.poll_ambient_lux_single_blocking_direct().await
+        self.calibrate_crosstalk_direct(target_mm, delay).await
     }
 
     /// Same functionality as [`start_range_continuous_mode()`](VL6180X::start_range_continuous_mode)
@@ -153,11 +212,14 @@ where
     /// but with a check on the current [OperatingMode].
     /// Valid in all OperatingModes except [PoweredOff],
     /// in which case will return [Error::InvalidMethod]
-    pub async fn try_read_range_mm_blocking(&mut self) -> Result<u16, Error<E>> {
+    pub async fn try_read_range_mm_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
         if self.mode.operating_mode == PoweredOff {
             return Err(Error::InvalidMethod(self.mode.operating_mode));
         }
-        self.read_range_mm_blocking_direct().await
+        self.read_range_mm_blocking_direct(delay).await
     }
 
     /// Same functionality as [`read_range_mm()`](VL6180X::read_range_mm)
@@ -171,15 +233,43 @@ where
         self.read_range_mm_direct().await
     }
 
+    /// Same functionality as [`read_range_measurement_blocking()`](VL6180X::read_range_measurement_blocking)
+    /// but with a check on the current [OperatingMode].
+    /// Valid in all OperatingModes except [PoweredOff],
+    /// in which case will return [Error::InvalidMethod]
+    pub async fn try_read_range_measurement_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<RangeMeasurement, Error<E>> {
+        if self.mode.operating_mode == PoweredOff {
+            return Err(Error::InvalidMethod(self.mode.operating_mode));
+        }
+        self.read_range_measurement_blocking_direct(delay).await
+    }
+
+    /// Same functionality as [`read_range_measurement()`](VL6180X::read_range_measurement)
+    /// but with a check on the current [OperatingMode].
+    /// Valid in all OperatingModes except [PoweredOff],
+    /// in which case will return [Error::InvalidMethod]
+    pub async fn try_read_range_measurement(&mut self) -> Result<RangeMeasurement, Error<E>> {
+        if self.mode.operating_mode == PoweredOff {
+            return Err(Error::InvalidMethod(self.mode.operating_mode));
+        }
+        self.read_range_measurement_direct().await
+    }
+
     /// Same functionality as [`read_ambient_lux_blocking()`](VL6180X::read_ambient_lux_blocking)
     /// but with a check on the current [OperatingMode].
     /// Valid in all OperatingModes except [PoweredOff],
     /// in which case will return [Error::InvalidMethod]
-    pub async fn try_read_ambient_lux_blocking(&mut self) -> Result<f32, Error<E>> {
+    pub async fn try_read_ambient_lux_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<f32, Error<E>> {
         if self.mode.operating_mode == PoweredOff {
             return Err(Error::InvalidMethod(self.mode.operating_mode));
         }
-        self.read_ambient_lux_blocking_direct().await
+        self.read_ambient_lux_blocking_direct(delay).await
     }
 
     /// Same functionality as [`read_ambient_lux()`](VL6180X::read_ambient_lux)
@@ -197,11 +287,14 @@ where
     /// but with a check on the current [OperatingMode].
     /// Valid in all OperatingModes except [PoweredOff],
     /// in which case will return [Error::InvalidMethod]
-    pub async fn try_read_ambient_blocking(&mut self) -> Result<u16, Error<E>> {
+    pub async fn try_read_ambient_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
         if self.mode.operating_mode == PoweredOff {
             return Err(Error::InvalidMethod(self.mode.operating_mode));
         }
-        self.read_ambient_blocking_direct().await
+        self.read_ambient_blocking_direct(delay).await
     }
 
     /// Same functionality as [`read_ambient()`](VL6180X::read_ambient)
@@ -290,14 +383,15 @@ where
     /// but with a check on the current [OperatingMode].
     /// Valid when OperatingMode is [PoweredOff],
     /// otherwise returns [Error::InvalidMethod]
-    pub async fn try_power_on_and_init<PE, P: OutputPin<Error = PE>>(
+    pub async fn try_power_on_and_init<PE, P: OutputPin<Error = PE>, D: DelayNs>(
         &mut self,
         x_shutdown_pin: &mut P,
+        delay: &mut D,
     ) -> Result<(), Error2<E, PE>> {
         if self.mode.operating_mode != PoweredOff {
             return Err(Error2::InvalidMethod(self.mode.operating_mode));
         }
-        self.power_on_and_init_direct(x_shutdown_pin).await?;
+        self.power_on_and_init_direct(x_shutdown_pin, delay).await?;
         self.mode.operating_mode = Ready;
         Ok(())
     }
@@ -1,5 +1,5 @@
 use embedded_hal::digital::OutputPin;
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
 use crate::{error::Error2, VL6180X};
 
@@ -14,12 +14,16 @@ where
     I2C: I2c<Error = E>,
 {
     /// Powers on the sensor by setting the `x_shutdown_pin` high.
-    /// It then busy waits for the device to be booted and initializes the device.
-    pub async fn power_on_and_init<PE, P: OutputPin<Error = PE>>(
+    /// It then polls for the device to be booted, moves it from [DEFAULT_ADDRESS](crate::DEFAULT_ADDRESS) to the
+    /// configured address and initializes the device.
+    /// Returns [Error2::Timeout] if it has not booted within the
+    /// [poll timeout](crate::Config::set_poll_timeout_ms).
+    pub async fn power_on_and_init<PE, P: OutputPin<Error = PE>, D: DelayNs>(
         mut self,
         x_shutdown_pin: &mut P,
+        delay: &mut D,
     ) -> Result<VL6180X<ReadyMode, I2C>, Error2<E, PE>> {
-        self.power_on_and_init_direct(x_shutdown_pin).await?;
+        self.power_on_and_init_direct(x_shutdown_pin, delay).await?;
         Ok(self.into_mode(ReadyMode))
     }
 }
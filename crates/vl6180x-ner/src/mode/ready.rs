@@ -1,7 +1,7 @@
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
 use crate::{error::Error, Config};
-use crate::{AllowCommunication, VL6180X};
+use crate::{AllowCommunication, RangeMeasurement, VL6180X};
 
 use super::{
     AllowReadMeasurement, AllowStartAmbientSingle, AllowStartRangeSingle, AmbientContinuousMode,
@@ -60,15 +60,60 @@ where
     /// Poll the sensor for a single range measurement.
     /// Starts a single range measurement then calls [`read_range_mm_blocking`](VL6180X::read_range_mm_blocking)
     /// to wait for the result.
-    pub async fn poll_range_mm_single_blocking(&mut self) -> Result<u16, Error<E>> {
-        self.poll_range_mm_single_blocking_direct().await
+    pub async fn poll_range_mm_single_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.poll_range_mm_single_blocking_direct(delay).await
+    }
+
+    /// Poll the sensor for a single range measurement with its signal rates, counts and
+    /// convergence times.
+    /// Starts a single range measurement then calls [`read_range_measurement_blocking`](VL6180X::read_range_measurement_blocking)
+    /// to wait for the result.
+    pub async fn poll_range_measurement_single_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<RangeMeasurement, Error<E>> {
+        self.poll_range_measurement_single_blocking_direct(delay)
+            .await
     }
 
     /// Poll the sensor for a single ambient light measurement.
     /// Starts a single ambient measurement then calls [`read_ambient_lux_blocking`](VL6180X::read_ambient_lux_blocking)
     /// to wait for the result.
-    pub async fn poll_ambient_lux_single_blocking(&mut self) -> Result<f32, Error<E>> {
-        self.poll_ambient_lux_single_blocking_direct().await
+    pub async fn poll_ambient_lux_single_blocking<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<f32, Error<E>> {
+        self.poll_ambient_lux_single_blocking_direct(delay).await
+    }
+
+    /// Calibrate the part-to-part range offset, as in ST application note AN4545.
+    ///
+    /// Place a white target (88% reflectance) at `target_mm`, 50mm in AN4545, in front of the
+    /// sensor and any cover window. Ten single ranges are taken with no offset, and the offset
+    /// that brings their average to `target_mm` is applied and returned. Run it before
+    /// [`calibrate_crosstalk`](VL6180X::calibrate_crosstalk).
+    pub async fn calibrate_range_offset<D: DelayNs>(
+        &mut self,
+        target_mm: u16,
+        delay: &mut D,
+    ) -> Result<i8, Error<E>> {
+        self.calibrate_range_offset_direct(target_mm, delay).await
+    }
+
+    /// Calibrate the crosstalk compensation of a cover window, as in ST application note AN4545.
+    ///
+    /// Place a black target (3% reflectance) at `target_mm`, 100mm in AN4545, in front of the
+    /// cover window. Ten single ranges are taken with no compensation, and the rate that makes
+    /// up for them reading short is applied and returned.
+    pub async fn calibrate_crosstalk<D: DelayNs>(
+        &mut self,
+        target_mm: u16,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.calibrate_crosstalk_direct(target_mm, delay).await
     }
 
     /// Starts continuous operation mode for reading range measurements.
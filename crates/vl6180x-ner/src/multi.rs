@@ -0,0 +1,148 @@
+use embedded_hal::digital::OutputPin;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
+
+use crate::{
+    error::{Error, Error2},
+    mode::{DynamicMode, OperatingMode},
+    Config, DEFAULT_ADDRESS, VL6180X,
+};
+
+/// Time the `x_shutdown_pin` is held low to reset a sensor
+const RESET_MS: u32 = 1;
+
+/// Several VL6180X on one bus, each with its own `x_shutdown_pin`.
+///
+/// Every VL6180X boots at [DEFAULT_ADDRESS], so they are powered on one at a time with the rest
+/// held in shutdown, and each is moved to its own address before the next (AN4478: Using
+/// multiple VL6180X's in a single design). A sensor is back at the default address whenever it
+/// is power cycled, [restart](MultiVL6180X::restart) moves it back.
+///
+/// The sensors are kept in [DynamicMode], so a reader can switch each between single and
+/// continuous measurements in place.
+#[derive(Debug)]
+pub struct MultiVL6180X<I2C, P, const N: usize> {
+    sensors: [VL6180X<DynamicMode, I2C>; N],
+    x_shutdown_pins: [P; N],
+}
+
+impl<I2C, E, P, PE, const N: usize> MultiVL6180X<I2C, P, N>
+where
+    I2C: I2c<Error = E>,
+    P: OutputPin<Error = PE>,
+{
+    /// Sensors to be moved to `addresses`, in the order they are powered on, all with `config`.
+    /// Nothing is sent until [power_on_all](MultiVL6180X::power_on_all).
+    ///
+    /// Returns [Error::InvalidAddress] for an address that is reserved, the default address, or
+    /// used twice.
+    pub fn new(
+        i2c: [I2C; N],
+        x_shutdown_pins: [P; N],
+        addresses: [u8; N],
+        config: &Config,
+    ) -> Result<Self, Error<()>> {
+        for (i, address) in addresses.iter().enumerate() {
+            if !(0x08..=0x77).contains(address)
+                || *address == DEFAULT_ADDRESS
+                || addresses[..i].contains(address)
+            {
+                return Err(Error::InvalidAddress(*address));
+            }
+        }
+        let mut addresses = addresses.into_iter();
+        let sensors = i2c.map(|com| {
+            let mut config = *config;
+            config.address = addresses.next().unwrap_or(DEFAULT_ADDRESS);
+            VL6180X {
+                mode: DynamicMode::powered_off(),
+                com,
+                config,
+            }
+        });
+        Ok(Self {
+            sensors,
+            x_shutdown_pins,
+        })
+    }
+
+    /// Hold every sensor in shutdown, then power each on and move it to its address in turn.
+    ///
+    /// A sensor that fails is left in shutdown, so it can't answer at the default address
+    /// alongside the next one. The rest are still powered on, and the first error is returned.
+    pub async fn power_on_all<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error2<E, PE>> {
+        for index in 0..N {
+            self.shutdown(index)?;
+        }
+        delay.delay_ms(RESET_MS).await;
+        let mut result = Ok(());
+        for index in 0..N {
+            result = result.and(self.power_on(index, delay).await);
+        }
+        result
+    }
+
+    /// Power cycle one sensor and move it back to its address, for a sensor that stopped
+    /// answering, as one that reset is back at the default address. Left in shutdown if it
+    /// fails.
+    ///
+    /// Panics if `index` is not below `N`.
+    pub async fn restart<D: DelayNs>(
+        &mut self,
+        index: usize,
+        delay: &mut D,
+    ) -> Result<(), Error2<E, PE>> {
+        self.shutdown(index)?;
+        delay.delay_ms(RESET_MS).await;
+        self.power_on(index, delay).await
+    }
+
+    /// Hold one sensor in shutdown until it is restarted.
+    ///
+    /// Panics if `index` is not below `N`.
+    pub fn shutdown(&mut self, index: usize) -> Result<(), Error2<E, PE>> {
+        self.x_shutdown_pins[index]
+            .set_low()
+            .map_err(|e| Error2::GpioPinError(e))?;
+        self.sensors[index].mode = DynamicMode::powered_off();
+        Ok(())
+    }
+
+    async fn power_on<D: DelayNs>(
+        &mut self,
+        index: usize,
+        delay: &mut D,
+    ) -> Result<(), Error2<E, PE>> {
+        let sensor = &mut self.sensors[index];
+        let x_shutdown_pin = &mut self.x_shutdown_pins[index];
+        let result = sensor.try_power_on_and_init(x_shutdown_pin, delay).await;
+        if result.is_err() {
+            // it may have booted, and be answering at the default address
+            let _ = x_shutdown_pin.set_low();
+        }
+        result
+    }
+
+    /// Whether a sensor is powered on at its address.
+    ///
+    /// Panics if `index` is not below `N`.
+    pub fn is_powered(&self, index: usize) -> bool {
+        self.sensors[index].operating_mode() != OperatingMode::PoweredOff
+    }
+
+    /// The address a sensor is moved to.
+    ///
+    /// Panics if `index` is not below `N`.
+    pub fn address(&self, index: usize) -> u8 {
+        self.sensors[index].config.address
+    }
+
+    /// One sensor, `None` if `index` is not below `N`
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut VL6180X<DynamicMode, I2C>> {
+        self.sensors.get_mut(index)
+    }
+
+    /// Every sensor in the order they are powered on, including any left in shutdown
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut VL6180X<DynamicMode, I2C>> {
+        self.sensors.iter_mut()
+    }
+}
@@ -0,0 +1,98 @@
+use crate::error::RangeStatusErrorCode;
+
+/// A range measurement with the results the sensor reports alongside it.
+/// See VL6180X datasheet section 6.2.49 RESULT__RANGE_VAL onwards.
+///
+/// Unlike [`read_range_mm`](crate::VL6180X::read_range_mm), a measurement the sensor flags with a
+/// range status error is returned rather than turned into an error, use
+/// [quality](RangeMeasurement::quality) to decide whether to trust it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RangeMeasurement {
+    /// RESULT__RANGE_VAL scaled to mm by the range scaling.
+    /// Only meaningful when the [quality](RangeMeasurement::quality) is [RangeQuality::Valid].
+    pub range_mm: u16,
+    /// Error code from RESULT__RANGE_STATUS
+    pub status: RangeStatusErrorCode,
+    /// RESULT__RANGE_RAW, the range in mm before crosstalk compensation and range scaling
+    pub raw_range: u8,
+    /// RESULT__RANGE_RETURN_RATE, the return signal rate in Mcps 9.7 fixed point
+    pub return_rate: u16,
+    /// RESULT__RANGE_REFERENCE_RATE, the reference signal rate in Mcps 9.7 fixed point
+    pub reference_rate: u16,
+    /// RESULT__RANGE_RETURN_SIGNAL_COUNT, photons counted by the return array
+    pub return_signal_count: u32,
+    /// RESULT__RANGE_REFERENCE_SIGNAL_COUNT, photons counted by the reference array
+    pub reference_signal_count: u32,
+    /// RESULT__RANGE_RETURN_AMB_COUNT, ambient light counted by the return array
+    pub return_ambient_count: u32,
+    /// RESULT__RANGE_REFERENCE_AMB_COUNT, ambient light counted by the reference array
+    pub reference_ambient_count: u32,
+    /// RESULT__RANGE_RETURN_CONV_TIME, time the return array took to converge in us
+    pub return_conv_time_us: u32,
+    /// RESULT__RANGE_REFERENCE_CONV_TIME, time the reference array took to converge in us
+    pub reference_conv_time_us: u32,
+}
+
+impl RangeMeasurement {
+    /// How far the range can be trusted, from its [status](RangeMeasurement::status)
+    pub fn quality(&self) -> RangeQuality {
+        RangeQuality::from(self.status)
+    }
+
+    /// Estimated confidence in the range from 0 to 100, the share of the return array counts that
+    /// were signal rather than ambient light. 0 when the [quality](RangeMeasurement::quality) is
+    /// not [RangeQuality::Valid].
+    pub fn confidence(&self) -> u8 {
+        let total = self.return_signal_count as u64 + self.return_ambient_count as u64;
+        if self.quality() != RangeQuality::Valid || total == 0 {
+            return 0;
+        }
+        (self.return_signal_count as u64 * 100 / total) as u8
+    }
+}
+
+/// How far a [RangeMeasurement] can be trusted.
+///
+/// The [RangeStatusErrorCode]s grouped by what they mean for the range. Only
+/// [SensorFault](RangeQuality::SensorFault) means something is wrong with the sensor, the rest
+/// describe the scene.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RangeQuality {
+    /// Valid measurement
+    Valid,
+    /// Too little signal returned to converge in time, there is no target in range or it is
+    /// too dark.
+    /// [EarlyConvergenceEstimate](RangeStatusErrorCode::EarlyConvergenceEstimate),
+    /// [MaxConvergence](RangeStatusErrorCode::MaxConvergence) and
+    /// [RangeIgnore](RangeStatusErrorCode::RangeIgnore)
+    NoTarget,
+    /// Ambient light too high for the signal to be told apart from it.
+    /// [MaxSignalToNoiseRatio](RangeStatusErrorCode::MaxSignalToNoiseRatio)
+    Noisy,
+    /// The target is closer than the sensor can range, or the offset took the range below 0.
+    /// [RawRangingAlgoUnderflow](RangeStatusErrorCode::RawRangingAlgoUnderflow) and
+    /// [RangingAlgoUnderflow](RangeStatusErrorCode::RangingAlgoUnderflow)
+    TooClose,
+    /// The target is further than the sensor can range, typically around 200 mm.
+    /// [RawRangingAlgoOverflow](RangeStatusErrorCode::RawRangingAlgoOverflow) and
+    /// [RangingAlgoOverflow](RangeStatusErrorCode::RangingAlgoOverflow)
+    TooFar,
+    /// The sensor could not measure, a VCSEL or PLL check failed
+    SensorFault,
+}
+
+impl From<RangeStatusErrorCode> for RangeQuality {
+    fn from(status: RangeStatusErrorCode) -> Self {
+        use RangeStatusErrorCode::*;
+        match status {
+            NoError => RangeQuality::Valid,
+            EarlyConvergenceEstimate | MaxConvergence | RangeIgnore => RangeQuality::NoTarget,
+            MaxSignalToNoiseRatio => RangeQuality::Noisy,
+            RawRangingAlgoUnderflow | RangingAlgoUnderflow => RangeQuality::TooClose,
+            RawRangingAlgoOverflow | RangingAlgoOverflow => RangeQuality::TooFar,
+            VcselContinuityTest | VcselWatchdogTest | VcselWatchdog | Pll1Lock | Pll2Lock => {
+                RangeQuality::SensorFault
+            }
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use core::convert::TryFrom;
 
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
 use crate::{
     error::Error,
+    range_measurement::RangeMeasurement,
     register::{
         self, AmbientStatusErrorCode, RangeStatusErrorCode, Register16Bit, Register8Bit,
         ResultInterruptStatusGpioCode,
@@ -11,22 +12,42 @@ use crate::{
     VL6180X,
 };
 
+/// RESULT__RANGE_VAL up to the end of RESULT__RANGE_REFERENCE_CONV_TIME, read in one go
+const RANGE_RESULTS_LEN: usize = 0x084 - 0x062;
+
 impl<MODE, I2C, E> VL6180X<MODE, I2C>
 where
     I2C: I2c<Error = E>,
 {
-    pub(crate) async fn read_range_mm_blocking_direct(&mut self) -> Result<u16, Error<E>> {
-        let mut c = 0;
+    /// Poll the interrupt status every [poll interval](crate::Config::set_poll_interval_ms) while
+    /// it shows `not_ready`. Gives up with [Error::Timeout] once the delays between polls add up
+    /// to the [poll timeout](crate::Config::set_poll_timeout_ms), after one last poll.
+    async fn wait_for_result<D: DelayNs>(
+        &mut self,
+        not_ready: ResultInterruptStatusGpioCode,
+        delay: &mut D,
+    ) -> Result<(), Error<E>> {
+        let mut waited_ms: u32 = 0;
         while ResultInterruptStatusGpioCode::has_status(
-            ResultInterruptStatusGpioCode::NoRangeEvents,
+            not_ready,
             self.read_named_register(Register8Bit::RESULT__INTERRUPT_STATUS_GPIO)
                 .await?,
         ) {
-            c += 1;
-            if c == self.config.poll_max_loop {
+            if waited_ms >= self.config.poll_timeout_ms as u32 {
                 return Err(Error::Timeout);
             }
+            delay.delay_ms(self.config.poll_interval_ms as u32).await;
+            waited_ms += self.config.poll_interval_ms as u32;
         }
+        Ok(())
+    }
+
+    pub(crate) async fn read_range_mm_blocking_direct<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.wait_for_result(ResultInterruptStatusGpioCode::NoRangeEvents, delay)
+            .await?;
 
         self.get_range_val_and_status().await
     }
@@ -60,22 +81,77 @@ where
         Ok(self.convert_raw_range_to_mm(raw_range))
     }
 
-    fn convert_raw_range_to_mm(&self, raw_range: u8) -> u16 {
-        self.config.range_scaling as u16 * raw_range as u16
+    pub(crate) async fn read_range_measurement_blocking_direct<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<RangeMeasurement, Error<E>> {
+        self.wait_for_result(ResultInterruptStatusGpioCode::NoRangeEvents, delay)
+            .await?;
+
+        self.get_range_measurement().await
     }
 
-    pub(crate) async fn read_ambient_lux_blocking_direct(&mut self) -> Result<f32, Error<E>> {
-        let mut c = 0;
-        while ResultInterruptStatusGpioCode::has_status(
-            ResultInterruptStatusGpioCode::NoAmbientEvents,
+    pub(crate) async fn read_range_measurement_direct(
+        &mut self,
+    ) -> Result<RangeMeasurement, Error<E>> {
+        if ResultInterruptStatusGpioCode::has_status(
+            ResultInterruptStatusGpioCode::NoRangeEvents,
             self.read_named_register(Register8Bit::RESULT__INTERRUPT_STATUS_GPIO)
                 .await?,
         ) {
-            c += 1;
-            if c == self.config.poll_max_loop {
-                return Err(Error::Timeout);
-            }
+            return Err(Error::ResultNotReady);
         }
+        self.get_range_measurement().await
+    }
+
+    /// Read the range status and every range result register, then clear the interrupt so the
+    /// next measurement can't change the results part way through
+    async fn get_range_measurement(&mut self) -> Result<RangeMeasurement, Error<E>> {
+        let status = self
+            .read_named_register(Register8Bit::RESULT__RANGE_STATUS)
+            .await?;
+        let mut results = [0u8; RANGE_RESULTS_LEN];
+        self.read_named_registers(Register8Bit::RESULT__RANGE_VAL, &mut results)
+            .await?;
+        self.clear_range_interrupt_direct().await?;
+        let status = RangeStatusErrorCode::try_from(status)
+            .map_err(|_| Error::UnknownRegisterCode(status))?;
+
+        let u16_at = |at: usize| u16::from_be_bytes([results[at], results[at + 1]]);
+        let u32_at = |at: usize| {
+            u32::from_be_bytes([
+                results[at],
+                results[at + 1],
+                results[at + 2],
+                results[at + 3],
+            ])
+        };
+        // offsets from RESULT__RANGE_VAL (0x062)
+        Ok(RangeMeasurement {
+            range_mm: self.convert_raw_range_to_mm(results[0]),
+            status,
+            raw_range: results[0x064 - 0x062],
+            return_rate: u16_at(0x066 - 0x062),
+            reference_rate: u16_at(0x068 - 0x062),
+            return_signal_count: u32_at(0x06C - 0x062),
+            reference_signal_count: u32_at(0x070 - 0x062),
+            return_ambient_count: u32_at(0x074 - 0x062),
+            reference_ambient_count: u32_at(0x078 - 0x062),
+            return_conv_time_us: u32_at(0x07C - 0x062),
+            reference_conv_time_us: u32_at(0x080 - 0x062),
+        })
+    }
+
+    fn convert_raw_range_to_mm(&self, raw_range: u8) -> u16 {
+        self.config.range_scaling as u16 * raw_range as u16
+    }
+
+    pub(crate) async fn read_ambient_lux_blocking_direct<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<f32, Error<E>> {
+        self.wait_for_result(ResultInterruptStatusGpioCode::NoAmbientEvents, delay)
+            .await?;
         let raw_ambient = self.get_ambient_val_and_status().await?;
         Ok(self.convert_raw_ambient_to_lux(raw_ambient))
     }
@@ -92,18 +168,12 @@ where
         Ok(self.convert_raw_ambient_to_lux(raw_ambient))
     }
 
-    pub(crate) async fn read_ambient_blocking_direct(&mut self) -> Result<u16, Error<E>> {
-        let mut c = 0;
-        while ResultInterruptStatusGpioCode::has_status(
-            ResultInterruptStatusGpioCode::NoAmbientEvents,
-            self.read_named_register(Register8Bit::RESULT__INTERRUPT_STATUS_GPIO)
-                .await?,
-        ) {
-            c += 1;
-            if c == self.config.poll_max_loop {
-                return Err(Error::Timeout);
-            }
-        }
+    pub(crate) async fn read_ambient_blocking_direct<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.wait_for_result(ResultInterruptStatusGpioCode::NoAmbientEvents, delay)
+            .await?;
         self.get_ambient_val_and_status().await
     }
 
@@ -1,7 +1,8 @@
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
 use crate::{
     error::Error,
+    range_measurement::RangeMeasurement,
     register::{InterleavedModeEnableCode, Register8Bit, SysAmbientStartCode, SysRangeStartCode},
     VL6180X,
 };
@@ -10,24 +11,40 @@ impl<MODE, I2C, E> VL6180X<MODE, I2C>
 where
     I2C: I2c<Error = E>,
 {
-    pub(crate) async fn poll_range_mm_single_blocking_direct(&mut self) -> Result<u16, Error<E>> {
+    pub(crate) async fn poll_range_mm_single_blocking_direct<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<u16, Error<E>> {
+        self.write_named_register(
+            Register8Bit::SYSRANGE__START,
+            SysRangeStartCode::SingleStart as u8,
+        )
+        .await?;
+        self.read_range_mm_blocking_direct(delay).await
+    }
+
+    pub(crate) async fn poll_range_measurement_single_blocking_direct<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<RangeMeasurement, Error<E>> {
         self.write_named_register(
             Register8Bit::SYSRANGE__START,
             SysRangeStartCode::SingleStart as u8,
         )
         .await?;
-        self.read_range_mm_blocking_direct().await
+        self.read_range_measurement_blocking_direct(delay).await
     }
 
-    pub(crate) async fn poll_ambient_lux_single_blocking_direct(
+    pub(crate) async fn poll_ambient_lux_single_blocking_direct<D: DelayNs>(
         &mut self,
+        delay: &mut D,
     ) -> Result<f32, Error<E>> {
         self.write_named_register(
             Register8Bit::SYSALS__START,
             SysAmbientStartCode::SingleStart as u8,
         )
         .await?;
-        self.read_ambient_lux_blocking_direct().await
+        self.read_ambient_lux_blocking_direct(delay).await
     }
 
     pub(crate) async fn start_ambient_single_direct(&mut self) -> Result<(), E> {
@@ -0,0 +1,177 @@
+use defmt::{info, unwrap, warn};
+use embassy_stm32::{
+    bind_interrupts,
+    gpio::{Level, OutputOpenDrain, Speed},
+    i2c::{self, I2c},
+    mode::Async,
+    peripherals::{DMA1_CH2, DMA1_CH4, I2C3, PA8, PC9},
+    time::Hertz,
+    Peripheral,
+};
+use embassy_time::{with_timeout, Delay, Duration, TimeoutError};
+use embedded_hal_async::i2c::{Error as _, ErrorType, Operation};
+use msb_readers::bus::{
+    clock_out, stuck_error, BusMonitor, TRANSACTION_RETRIES, TRANSACTION_TIMEOUT_MS,
+};
+
+bind_interrupts!(struct IrqsI2c {
+    I2C3_EV => i2c::EventInterruptHandler<I2C3>;
+    I2C3_ER => i2c::ErrorInterruptHandler<I2C3>;
+});
+
+const TIMEOUT: Duration = Duration::from_millis(TRANSACTION_TIMEOUT_MS);
+
+/// The I2C3 bus shared by the sensors, which times out every transaction, and recovers the bus
+/// and the peripheral once it looks stuck, as a sensor browning out mid read can hold SDA low
+/// until it is clocked out
+pub struct I2c3Bus {
+    /// Only `None` while the bus is recovered
+    i2c: Option<I2c<'static, Async>>,
+    peri: I2C3,
+    scl: PA8,
+    sda: PC9,
+    tx_dma: DMA1_CH4,
+    rx_dma: DMA1_CH2,
+    monitor: BusMonitor,
+}
+
+impl I2c3Bus {
+    pub fn new(peri: I2C3, scl: PA8, sda: PC9, tx_dma: DMA1_CH4, rx_dma: DMA1_CH2) -> Self {
+        let mut bus = Self {
+            i2c: None,
+            peri,
+            scl,
+            sda,
+            tx_dma,
+            rx_dma,
+            monitor: BusMonitor::new(),
+        };
+        bus.i2c = Some(bus.create());
+        bus
+    }
+
+    fn create(&mut self) -> I2c<'static, Async> {
+        // Safety: only one I2c exists at a time, recover drops it before using the pins
+        unsafe {
+            I2c::new(
+                self.peri.clone_unchecked(),
+                self.scl.clone_unchecked(),
+                self.sda.clone_unchecked(),
+                IrqsI2c,
+                self.tx_dma.clone_unchecked(),
+                self.rx_dma.clone_unchecked(),
+                Hertz(100_000),
+                i2c::Config::default(),
+            )
+        }
+    }
+
+    fn i2c(&mut self) -> &mut I2c<'static, Async> {
+        unwrap!(self.i2c.as_mut())
+    }
+
+    /// Take the pins from the peripheral, clock out whichever sensor is holding the bus, and
+    /// start the peripheral over from reset
+    async fn recover(&mut self) {
+        self.i2c = None;
+        // Safety: the I2c using the pins was just dropped, and these are dropped before the next
+        let (scl, sda) = unsafe { (self.scl.clone_unchecked(), self.sda.clone_unchecked()) };
+        let mut scl = OutputOpenDrain::new(scl, Level::High, Speed::Low);
+        let mut sda = OutputOpenDrain::new(sda, Level::High, Speed::Low);
+        let result = clock_out(&mut scl, &mut sda, &mut Delay).await;
+        drop((scl, sda));
+        match result {
+            Ok(()) => info!("Recovered I2C3, {} recoveries", self.monitor.recoveries),
+            Err(err) => warn!("Could not free I2C3, resetting it anyway: {}", err),
+        }
+        self.i2c = Some(self.create());
+    }
+
+    /// Sort out the result of one attempt at a transaction, `None` to try it again
+    async fn check<T>(
+        &mut self,
+        result: Result<Result<T, i2c::Error>, TimeoutError>,
+        retries: &mut u8,
+    ) -> Option<Result<T, i2c::Error>> {
+        let err = match result {
+            Ok(Ok(value)) => {
+                self.monitor.ok();
+                return Some(Ok(value));
+            }
+            Ok(Err(err)) => err,
+            Err(TimeoutError) => i2c::Error::Timeout,
+        };
+        // a bus scan probing with an empty write the peripheral can't send says nothing about the bus
+        if matches!(err, i2c::Error::ZeroLengthTransfer) {
+            return Some(Err(err));
+        }
+        if self.monitor.error(err.kind()) {
+            warn!("I2C3 looks stuck after {}, recovering", err);
+            self.recover().await;
+        }
+        if stuck_error(err.kind()) && *retries < TRANSACTION_RETRIES {
+            *retries += 1;
+            return None;
+        }
+        Some(Err(err))
+    }
+}
+
+impl ErrorType for I2c3Bus {
+    type Error = i2c::Error;
+}
+
+impl embedded_hal_async::i2c::I2c for I2c3Bus {
+    async fn read(&mut self, address: u8, read: &mut [u8]) -> Result<(), Self::Error> {
+        let mut retries = 0;
+        loop {
+            let result = with_timeout(TIMEOUT, self.i2c().read(address, read)).await;
+            if let Some(result) = self.check(result, &mut retries).await {
+                return result;
+            }
+        }
+    }
+
+    async fn write(&mut self, address: u8, write: &[u8]) -> Result<(), Self::Error> {
+        let mut retries = 0;
+        loop {
+            let result = with_timeout(TIMEOUT, self.i2c().write(address, write)).await;
+            if let Some(result) = self.check(result, &mut retries).await {
+                return result;
+            }
+        }
+    }
+
+    async fn write_read(
+        &mut self,
+        address: u8,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let mut retries = 0;
+        loop {
+            let result = with_timeout(TIMEOUT, self.i2c().write_read(address, write, read)).await;
+            if let Some(result) = self.check(result, &mut retries).await {
+                return result;
+            }
+        }
+    }
+
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut retries = 0;
+        loop {
+            let result = with_timeout(
+                TIMEOUT,
+                embedded_hal_async::i2c::I2c::transaction(self.i2c(), address, operations),
+            )
+            .await;
+            if let Some(result) = self.check(result, &mut retries).await {
+                return result;
+            }
+        }
+    }
+}
@@ -1,30 +1,92 @@
-use defmt::{trace, unwrap, warn};
-use embassy_stm32::can::{Can, Frame};
+use defmt::{info, trace, unwrap, warn};
+use embassy_futures::select::{self, select};
+use embassy_stm32::can::{
+    filter::{BankConfig, Mask32},
+    Can, Fifo, Frame, Id, StandardId,
+};
 use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Receiver};
+use ner_can_messages::{
+    msb::{Command, MsbCommand},
+    CanMessage,
+};
 
-use crate::DeviceLocation;
+use crate::{DeviceLocation, MsbConfig, ReaderCommand};
 
 const CAN_BITRATE: u32 = 500_000;
 
 #[embassy_executor::task]
+/// Sends frames from the channel with the ID offset by location,
+/// and receives commands addressed to this location
 pub async fn can_handler(
     mut can: Can<'static>,
     recv: Receiver<'static, ThreadModeRawMutex, Frame, 25>,
     loc: DeviceLocation,
+    config: &'static MsbConfig,
 ) {
     can.set_bitrate(CAN_BITRATE);
+    let command_id = unwrap!(StandardId::new(loc.can_id(MsbCommand::DEF.id)));
+    can.modify_filters().enable_bank(
+        0,
+        Fifo::Fifo0,
+        BankConfig::Mask32(Mask32::frames_with_std_id(command_id, StandardId::MAX)),
+    );
     trace!("Attempting to enable CAN..");
     can.enable().await;
     trace!("CAN enabled");
 
     loop {
-        let frame = recv.receive().await;
-        let frame_fixed = unwrap!(Frame::new_data(loc.get_can_id(frame.id()), frame.data()));
-        trace!("Sending frame: {}", frame_fixed);
-        if can.write(&frame_fixed).await.dequeued_frame().is_some() {
-            warn!("Dequeing can frames!");
+        match select(recv.receive(), can.read()).await {
+            select::Either::First(frame) => {
+                let frame_fixed =
+                    unwrap!(Frame::new_data(get_can_id(loc, frame.id()), frame.data()));
+                trace!("Sending frame: {}", frame_fixed);
+                if can.write(&frame_fixed).await.dequeued_frame().is_some() {
+                    warn!("Dequeing can frames!");
+                }
+            }
+            select::Either::Second(res) => match res {
+                Ok(can_recv) => match MsbCommand::decode(can_recv.frame.data()) {
+                    Ok(msg) => handle_command(msg, config),
+                    Err(err) => warn!("Bad command frame: {}", err),
+                },
+                Err(err) => warn!("Bus error! {}", err),
+            },
         }
 
         //Timer::after_millis(5).await;
     }
 }
+
+/// Offset the base ID of a frame by the location of this MSB
+fn get_can_id(loc: DeviceLocation, base_id: &Id) -> StandardId {
+    let id = match base_id {
+        Id::Standard(id) => *id,
+        Id::Extended(id) => id.standard_id(),
+    };
+    unwrap!(StandardId::new(loc.can_id(id.as_raw())))
+}
+
+/// Apply a command to the config or pass it on to the readers it targets, or reboot
+fn handle_command(msg: MsbCommand, config: &'static MsbConfig) {
+    let Ok(cmd) = Command::try_from(msg) else {
+        warn!("Unknown command: {}", msg);
+        return;
+    };
+    info!("Received command: {}", cmd);
+
+    match cmd {
+        Command::SetRefreshTime(target, ms) => {
+            if let Err(err) = config.set_refresh_time(target, ms) {
+                warn!("Rejected refresh time: {}", err);
+            }
+        }
+        Command::Dump(target) => config.commands.signal(target, ReaderCommand::Dump),
+        Command::Reinit(target) => config.commands.signal(target, ReaderCommand::Reinit),
+        Command::Reboot => cortex_m::peripheral::SCB::sys_reset(),
+        Command::SetCapture(mode) => {
+            if let Err(err) = config.set_capture(mode) {
+                warn!("Rejected capture mode: {}", err);
+            }
+        }
+    }
+}
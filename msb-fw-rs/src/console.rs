@@ -0,0 +1,118 @@
+use core::fmt::Write;
+
+use defmt::{info, warn};
+use embassy_stm32::{mode::Async, usart::Uart};
+use heapless::String;
+use msb_readers::config::{capture_frames, corner_name, ConsoleCommand};
+
+use crate::{DeviceLocation, MsbConfig};
+
+/// Serial console for changing the config without a debugger, one command per line:
+/// `rates`, `rate <temperature|imu|tof|adc|all> <ms>`, `capture <off|summary|raw>`, `cal`,
+/// `cal <shockpot|strain1|strain2> <zero counts> <units per count>`, `mount`, or
+/// `mount <frontleft|frontright|backleft|backright> <x> <y> <z>`
+#[embassy_executor::task]
+pub async fn console(mut usart: Uart<'static, Async>, config: &'static MsbConfig) {
+    let mut buf = [0u8; 64];
+
+    loop {
+        let line = match usart.read_until_idle(&mut buf).await {
+            Ok(len) => core::str::from_utf8(&buf[..len]).unwrap_or(""),
+            Err(err) => {
+                warn!("Console read failed: {}", err);
+                continue;
+            }
+        };
+
+        let mut reply: String<128> = String::new();
+        match ConsoleCommand::parse(line) {
+            Some(ConsoleCommand::ShowRates) => {
+                let times = config.refresh_times();
+                let capture = config.capture_mode();
+                let _ = core::write!(
+                    &mut reply,
+                    "temperature {} imu {} tof {} adc {} ms, capture {:?}, i2c {} bit/s, can {} frame/s\r\n",
+                    times.temperature,
+                    times.imu,
+                    times.tof,
+                    times.adc,
+                    capture,
+                    times.i2c_load(),
+                    times.can_load() + capture_frames(capture),
+                );
+            }
+            Some(ConsoleCommand::SetRate(reader, ms)) => {
+                match config.set_refresh_time(reader, ms) {
+                    Ok(()) => {
+                        info!("Console set refresh time of {} to {} ms", reader, ms);
+                        let _ = core::write!(&mut reply, "ok\r\n");
+                    }
+                    Err(err) => {
+                        let _ = core::write!(&mut reply, "rejected: {:?}\r\n", err);
+                    }
+                }
+            }
+            Some(ConsoleCommand::SetCapture(mode)) => match config.set_capture(mode) {
+                Ok(()) => {
+                    info!("Console set capture mode to {}", mode);
+                    let _ = core::write!(&mut reply, "ok\r\n");
+                }
+                Err(err) => {
+                    let _ = core::write!(&mut reply, "rejected: {:?}\r\n", err);
+                }
+            },
+            Some(ConsoleCommand::ShowCalibration) => {
+                let cal = config.adc_calibration();
+                let _ = core::write!(
+                    &mut reply,
+                    "shockpot {} {} mm, strain1 {} {} ue, strain2 {} {} ue\r\n",
+                    cal.shockpot.zero,
+                    cal.shockpot.gain,
+                    cal.strain_1.zero,
+                    cal.strain_1.gain,
+                    cal.strain_2.zero,
+                    cal.strain_2.gain,
+                );
+            }
+            Some(ConsoleCommand::SetCalibration(channel, linear)) => {
+                match config.set_adc_calibration(channel, linear) {
+                    Ok(()) => {
+                        info!("Console set calibration of {} to {}", channel, linear);
+                        let _ = core::write!(&mut reply, "ok\r\n");
+                    }
+                    Err(err) => {
+                        let _ = core::write!(&mut reply, "rejected: {:?}\r\n", err);
+                    }
+                }
+            }
+            Some(ConsoleCommand::ShowMounting) => {
+                for location in DeviceLocation::ALL {
+                    let [x, y, z] = config.mounting(location).axes();
+                    let _ = core::write!(
+                        &mut reply,
+                        "{} {} {} {}\r\n",
+                        corner_name(location),
+                        x.name(),
+                        y.name(),
+                        z.name()
+                    );
+                }
+            }
+            Some(ConsoleCommand::SetMounting(location, mounting)) => {
+                config.set_mounting(location, mounting);
+                info!("Console set IMU mounting of {} to {}", location, mounting);
+                let _ = core::write!(&mut reply, "ok, applied after a reboot\r\n");
+            }
+            None => {
+                let _ = core::write!(
+                    &mut reply,
+                    "usage: rates | rate <reader|all> <ms> | capture <mode> | cal | cal <channel> <zero> <per count> | mount | mount <corner> <x> <y> <z>\r\n"
+                );
+            }
+        }
+
+        if let Err(err) = usart.write(reply.as_bytes()).await {
+            warn!("Console write failed: {}", err);
+        }
+    }
+}
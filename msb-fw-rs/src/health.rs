@@ -0,0 +1,40 @@
+use defmt::{trace, warn};
+use embassy_stm32::can::Frame;
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Sender};
+use embassy_time::{Duration, Instant, Ticker};
+use msb_readers::health::DIAGNOSTIC_PERIOD_MS;
+use ner_can_messages::msb::SensorState;
+
+use crate::{message_frame, HealthRegistry};
+
+/// Send the health of every sensor as an `MsbDiagnostic` once a period, so the logger learns
+/// about a dead sensor even though its reader has stopped sending
+#[embassy_executor::task]
+pub async fn health_reporter(
+    can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    health: &'static HealthRegistry,
+) {
+    let mut ticker = Ticker::every(Duration::from_millis(DIAGNOSTIC_PERIOD_MS));
+    let mut last = health.get();
+    loop {
+        ticker.next().await;
+        let now = health.get();
+        for (name, before, after) in [
+            ("sht30", last.temperature, now.temperature),
+            ("lsm6dso", last.imu, now.imu),
+            ("vl6180x", last.tof, now.tof),
+        ] {
+            if before.state != after.state && after.state != SensorState::Ok {
+                warn!(
+                    "{} is now {}, last fault {}",
+                    name, after.state, after.last_fault
+                );
+            }
+        }
+        last = now;
+
+        trace!("Sending health: {}", now);
+        let msg = now.message(Instant::now().as_secs());
+        can_send.send(message_frame(&msg)).await;
+    }
+}
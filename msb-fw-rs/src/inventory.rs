@@ -0,0 +1,59 @@
+use defmt::{info, warn};
+use embassy_stm32::can::Frame;
+use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Sender};
+use msb_readers::{
+    health::Sensor,
+    inventory::{self, MsbDevices},
+};
+use ner_can_messages::msb::SensorFault;
+use ner_i2c_probe::Presence;
+
+use crate::{message_frame, HealthRegistry, SharedI2c3};
+
+/// Scan the I2C bus for the sensors fitted to this MSB and send what was found as an
+/// `MsbInventory`. A sensor with the wrong ID is marked failed, as its reader won't be started.
+/// Returns `None` if the bus could not be scanned, in which case every reader should be started
+/// and left to find out for itself.
+pub async fn discover_sensors(
+    i2c: &'static SharedI2c3,
+    can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    health: &'static HealthRegistry,
+) -> Option<MsbDevices> {
+    let devices = match inventory::discover(&mut *i2c.lock().await).await {
+        Ok(devices) => devices,
+        Err(err) => {
+            warn!("Could not scan the I2C bus: {}", err);
+            return None;
+        }
+    };
+
+    for sensor in [Sensor::Temperature, Sensor::Imu, Sensor::Tof] {
+        let name = inventory::signature(sensor).name;
+        match inventory::presence(&devices, sensor) {
+            Presence::Present => info!("Found {}", name),
+            Presence::Absent => info!("No {} fitted", name),
+            Presence::WrongId(id) => {
+                warn!(
+                    "Something other than a {} answered, with ID {:#x}",
+                    name, id
+                );
+                health.init_failed(sensor, &SensorFault::WrongDevice);
+            }
+        }
+    }
+    for address in devices.unknown.iter() {
+        info!("Unknown device at {:#x}", address);
+    }
+
+    can_send
+        .send(message_frame(&inventory::inventory_message(&devices)))
+        .await;
+    Some(devices)
+}
+
+/// Whether to start the reader of `sensor`, which is always when the bus could not be scanned
+pub fn fitted(devices: Option<&MsbDevices>, sensor: Sensor) -> bool {
+    devices.map_or(true, |devices| {
+        inventory::presence(devices, sensor) == Presence::Present
+    })
+}
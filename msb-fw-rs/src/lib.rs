@@ -2,55 +2,324 @@
 #![feature(impl_trait_in_assoc_type)]
 #![feature(const_option)]
 
-
 // declare all files in this project except main
+pub mod bus;
 pub mod can_handler;
+pub mod console;
 pub mod controllers;
+pub mod health;
+pub mod inventory;
 pub mod readers;
+pub mod storage;
 
 // include below any shared types or structs across the project
 // make sure to define these in a workspace crate if they are shared across multiple projects
 
 // dont import anything in a lib.rs file, instead use fully resolved definitions
-pub type SharedI2c3 = embassy_sync::mutex::Mutex<
-    embassy_sync::blocking_mutex::raw::NoopRawMutex,
-    embassy_stm32::i2c::I2c<'static, embassy_stm32::mode::Async>,
+pub type SharedI2c3 =
+    embassy_sync::mutex::Mutex<embassy_sync::blocking_mutex::raw::NoopRawMutex, bus::I2c3Bus>;
+pub use ner_can_messages::msb::DeviceLocation;
+
+/// Build a frame for a message using its base ID, the CAN handler offsets it by the device location
+pub fn message_frame<M: ner_can_messages::CanMessage>(msg: &M) -> embassy_stm32::can::Frame {
+    defmt::unwrap!(embassy_stm32::can::Frame::new_data(
+        defmt::unwrap!(embassy_stm32::can::StandardId::new(M::DEF.id)),
+        msg.encode().as_bytes()
+    ))
+}
+
+/// Sent to a reader task by the CAN handler or the console
+#[derive(Clone, Copy, defmt::Format)]
+pub enum ReaderCommand {
+    /// Pick up the new refresh time from [`MsbConfig`]
+    RefreshTimeChanged,
+    /// Read and send immediately
+    Dump,
+    /// Drop and initialize the sensor driver again
+    Reinit,
+    /// ADC only, start over in the new capture mode from [`MsbConfig`]
+    CaptureChanged,
+}
+
+pub type ReaderSignal = embassy_sync::signal::Signal<
+    embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+    ReaderCommand,
+>;
+
+/// Command signal of every reader task
+pub struct ReaderSignals {
+    pub temperature: ReaderSignal,
+    pub imu: ReaderSignal,
+    pub tof: ReaderSignal,
+    pub adc: ReaderSignal,
+}
+
+impl ReaderSignals {
+    pub const fn new() -> Self {
+        Self {
+            temperature: ReaderSignal::new(),
+            imu: ReaderSignal::new(),
+            tof: ReaderSignal::new(),
+            adc: ReaderSignal::new(),
+        }
+    }
+
+    /// Signal one reader, or every reader for `None`
+    pub fn signal(&self, reader: Option<ner_can_messages::msb::Reader>, cmd: ReaderCommand) {
+        match reader {
+            Some(reader) => self.get(reader).signal(cmd),
+            None => ner_can_messages::msb::Reader::ALL
+                .iter()
+                .for_each(|reader| self.get(*reader).signal(cmd)),
+        }
+    }
+
+    pub fn get(&self, reader: ner_can_messages::msb::Reader) -> &ReaderSignal {
+        match reader {
+            ner_can_messages::msb::Reader::Temperature => &self.temperature,
+            ner_can_messages::msb::Reader::Imu => &self.imu,
+            ner_can_messages::msb::Reader::Tof => &self.tof,
+            ner_can_messages::msb::Reader::Adc => &self.adc,
+        }
+    }
+}
+
+impl Default for ReaderSignals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Flash storage of the settings, in the last two sectors
+pub type MsbConfigStore = ner_config_store::ConfigStore<
+    ner_config_store::stm32::Stm32Flash,
+    msb_readers::config::MsbSettings,
 >;
-#[derive(Clone, defmt::Format)]
-pub enum DeviceLocation {
-    FrontLeft,
-    BackLeft,
-    BackRight,
-    FrontRight,
-}
-
-impl From<(bool, bool, bool)> for DeviceLocation {
-    fn from(value: (bool, bool, bool)) -> Self {
-        if value.0 && value.1 {
-            DeviceLocation::FrontLeft
-        } else if value.0 && !value.1 {
-            DeviceLocation::FrontRight
-        } else if !value.1 && value.2 {
-            DeviceLocation::BackLeft
-        } else if !value.0 && !value.2 {
-            DeviceLocation::BackRight
-        } else {
-            DeviceLocation::FrontLeft
+
+/// Runtime configuration watched by the reader tasks, changed over CAN or the serial console
+pub struct MsbConfig {
+    refresh_times: embassy_sync::blocking_mutex::Mutex<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        core::cell::Cell<msb_readers::config::RefreshTimes>,
+    >,
+    adc_calibration: embassy_sync::blocking_mutex::Mutex<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        core::cell::Cell<msb_readers::adc::AdcCalibration>,
+    >,
+    capture: embassy_sync::blocking_mutex::Mutex<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        core::cell::Cell<ner_can_messages::msb::CaptureMode>,
+    >,
+    mountings: embassy_sync::blocking_mutex::Mutex<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        core::cell::Cell<[msb_readers::imu::Mounting; 4]>,
+    >,
+    pub commands: ReaderSignals,
+    /// Signalled whenever the config changes, so it can be saved to flash
+    pub changed: embassy_sync::signal::Signal<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        (),
+    >,
+}
+
+impl MsbConfig {
+    pub const fn new() -> Self {
+        Self {
+            refresh_times: embassy_sync::blocking_mutex::Mutex::new(core::cell::Cell::new(
+                msb_readers::config::RefreshTimes::DEFAULT,
+            )),
+            adc_calibration: embassy_sync::blocking_mutex::Mutex::new(core::cell::Cell::new(
+                msb_readers::adc::AdcCalibration::DEFAULT,
+            )),
+            capture: embassy_sync::blocking_mutex::Mutex::new(core::cell::Cell::new(
+                ner_can_messages::msb::CaptureMode::Off,
+            )),
+            mountings: embassy_sync::blocking_mutex::Mutex::new(core::cell::Cell::new(
+                [msb_readers::imu::Mounting::IDENTITY; 4],
+            )),
+            commands: ReaderSignals::new(),
+            changed: embassy_sync::signal::Signal::new(),
+        }
+    }
+
+    /// Replace the config with settings loaded from flash, before the readers start
+    pub fn restore(&self, settings: msb_readers::config::MsbSettings) {
+        self.refresh_times
+            .lock(|current| current.set(settings.refresh_times));
+        self.adc_calibration
+            .lock(|current| current.set(settings.adc_calibration));
+        self.mountings
+            .lock(|current| current.set(settings.mountings));
+    }
+
+    /// Everything that is saved to flash
+    pub fn settings(&self) -> msb_readers::config::MsbSettings {
+        msb_readers::config::MsbSettings {
+            refresh_times: self.refresh_times(),
+            adc_calibration: self.adc_calibration(),
+            mountings: self.mountings.lock(|mountings| mountings.get()),
+        }
+    }
+
+    pub fn refresh_times(&self) -> msb_readers::config::RefreshTimes {
+        self.refresh_times.lock(|times| times.get())
+    }
+
+    pub fn refresh_time(&self, reader: ner_can_messages::msb::Reader) -> embassy_time::Duration {
+        embassy_time::Duration::from_millis(self.refresh_times().get(reader) as u64)
+    }
+
+    /// Change the refresh time of one reader, or every reader for `None`, and wake the readers
+    /// affected. Nothing changes if the new times are out of bounds.
+    pub fn set_refresh_time(
+        &self,
+        reader: Option<ner_can_messages::msb::Reader>,
+        ms: u16,
+    ) -> Result<(), msb_readers::config::ConfigError> {
+        self.refresh_times.lock(|times| {
+            let new = times.get().with(reader, ms)?;
+            new.validate_capture(self.capture_mode())?;
+            times.set(new);
+            Ok(())
+        })?;
+        self.commands
+            .signal(reader, ReaderCommand::RefreshTimeChanged);
+        self.changed.signal(());
+        Ok(())
+    }
+
+    pub fn capture_mode(&self) -> ner_can_messages::msb::CaptureMode {
+        self.capture.lock(|mode| mode.get())
+    }
+
+    /// Switch the shock capture mode, rejected if it would overload CAN with the current refresh
+    /// times. Capture always starts off after a reboot.
+    pub fn set_capture(
+        &self,
+        mode: ner_can_messages::msb::CaptureMode,
+    ) -> Result<(), msb_readers::config::ConfigError> {
+        self.refresh_times().validate_capture(mode)?;
+        self.capture.lock(|current| current.set(mode));
+        self.commands.adc.signal(ReaderCommand::CaptureChanged);
+        Ok(())
+    }
+
+    pub fn adc_calibration(&self) -> msb_readers::adc::AdcCalibration {
+        self.adc_calibration.lock(|calibration| calibration.get())
+    }
+
+    /// Change the conversion of one ADC channel, used from the next reading on
+    pub fn set_adc_calibration(
+        &self,
+        channel: msb_readers::adc::AdcChannel,
+        linear: msb_readers::adc::Linear,
+    ) -> Result<(), msb_readers::config::ConfigError> {
+        self.adc_calibration.lock(|calibration| {
+            let new = calibration
+                .get()
+                .with(channel, linear)
+                .ok_or(msb_readers::config::ConfigError::BadCalibration(channel))?;
+            calibration.set(new);
+            Ok(())
+        })?;
+        self.changed.signal(());
+        Ok(())
+    }
+
+    /// How the IMU sits at a corner of the car
+    pub fn mounting(&self, location: DeviceLocation) -> msb_readers::imu::Mounting {
+        self.mountings
+            .lock(|mountings| mountings.get()[msb_readers::config::corner(location)])
+    }
+
+    /// Change how the IMU sits at one corner. Only saved, the IMU reader picks up the one of its
+    /// own corner when it calibrates after the next reboot.
+    pub fn set_mounting(&self, location: DeviceLocation, mounting: msb_readers::imu::Mounting) {
+        self.mountings.lock(|current| {
+            let mut mountings = current.get();
+            mountings[msb_readers::config::corner(location)] = mounting;
+            current.set(mountings);
+        });
+        self.changed.signal(());
+    }
+}
+
+impl Default for MsbConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Health of every sensor, updated by the readers and sent on CAN by [`health::health_reporter`]
+pub struct HealthRegistry {
+    health: embassy_sync::blocking_mutex::Mutex<
+        embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex,
+        core::cell::Cell<msb_readers::health::BoardHealth>,
+    >,
+}
+
+impl HealthRegistry {
+    pub const fn new() -> Self {
+        Self {
+            health: embassy_sync::blocking_mutex::Mutex::new(core::cell::Cell::new(
+                msb_readers::health::BoardHealth::NEW,
+            )),
         }
     }
+
+    pub fn get(&self) -> msb_readers::health::BoardHealth {
+        self.health.lock(|health| health.get())
+    }
+
+    /// Change the health of one sensor, returning it after the change
+    fn update(
+        &self,
+        sensor: msb_readers::health::Sensor,
+        f: impl FnOnce(&mut msb_readers::health::SensorHealth),
+    ) -> msb_readers::health::SensorHealth {
+        self.health.lock(|health| {
+            let mut new = health.get();
+            f(new.get_mut(sensor));
+            health.set(new);
+            *new.get(sensor)
+        })
+    }
+
+    pub fn init_ok(&self, sensor: msb_readers::health::Sensor) {
+        self.update(sensor, |health| health.init_ok());
+    }
+
+    /// Record a failed initialization, returning how long to wait before trying again
+    pub fn init_failed(
+        &self,
+        sensor: msb_readers::health::Sensor,
+        err: &impl msb_readers::health::Fault,
+    ) -> embassy_time::Duration {
+        let health = self.update(sensor, |health| health.init_failed(err.fault()));
+        embassy_time::Duration::from_millis(health.retry_delay_ms())
+    }
+
+    pub fn self_test_failed(&self, sensor: msb_readers::health::Sensor) {
+        self.update(sensor, |health| health.self_test_failed());
+    }
+
+    pub fn reading_ok(&self, sensor: msb_readers::health::Sensor) {
+        self.update(sensor, |health| health.reading_ok());
+    }
+
+    /// Record a failed reading, returning whether the sensor should be initialized again
+    pub fn reading_failed(
+        &self,
+        sensor: msb_readers::health::Sensor,
+        err: &impl msb_readers::health::Fault,
+    ) -> bool {
+        self.update(sensor, |health| health.reading_failed(err.fault()))
+            .failed()
+    }
 }
 
-impl DeviceLocation {
-    fn get_can_id(&self, base_id: &embassy_stm32::can::Id) -> embassy_stm32::can::StandardId {
-        let id = match base_id {
-            embassy_stm32::can::Id::Standard(id) => id,
-            embassy_stm32::can::Id::Extended(id) => &id.standard_id(),
-        };
-        defmt::unwrap!(embassy_stm32::can::StandardId::new(match self {
-            DeviceLocation::FrontLeft => id.as_raw(),
-            DeviceLocation::BackLeft => id.as_raw() + 0x40,
-            DeviceLocation::BackRight => id.as_raw() + 0x60,
-            DeviceLocation::FrontRight => id.as_raw() + 0x20,
-        }))
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new()
     }
 }
@@ -4,18 +4,18 @@
 
 use core::fmt::Write;
 
-use defmt::{debug, info, unwrap};
+use cortex_m::singleton;
+use defmt::{debug, info, unwrap, warn};
 use embassy_executor::Spawner;
 use embassy_stm32::{
-    adc::Adc,
+    adc::{Adc, SampleTime, Sequence},
     bind_interrupts,
     can::{Can, Rx0InterruptHandler, Rx1InterruptHandler, SceInterruptHandler, TxInterruptHandler},
-    i2c::{self, I2c},
     peripherals::CAN1,
-    time::Hertz,
 };
 use embassy_stm32::{
     can::Frame,
+    flash::Flash,
     gpio::{Input, Level, Output, Pull, Speed},
     peripherals,
     usart::{self, Uart},
@@ -25,7 +25,12 @@ use embassy_stm32::{
 use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Channel, mutex::Mutex};
 use embassy_time::Timer;
 use heapless::String;
-use msb_fw_rs::{can_handler, controllers, readers, DeviceLocation, SharedI2c3};
+use msb_fw_rs::{
+    bus::I2c3Bus, can_handler, console, controllers, health, inventory, readers, storage,
+    DeviceLocation, HealthRegistry, MsbConfig, SharedI2c3,
+};
+use msb_readers::health::Sensor;
+use ner_config_store::{stm32::Stm32Flash, ConfigStore};
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
@@ -42,14 +47,15 @@ bind_interrupts!(struct IrqsUsart {
     USART2 => usart::InterruptHandler<peripherals::USART2>;
 });
 
-bind_interrupts!(struct IrqsI2c {
-    I2C3_EV => i2c::EventInterruptHandler<peripherals::I2C3>;
-    I2C3_ER => i2c::ErrorInterruptHandler<peripherals::I2C3>;
-});
-
 // channels are like RTOS queues, with a limit.  They are MPMC easy to pass around in threads.
 static CAN_CHANNEL: Channel<ThreadModeRawMutex, Frame, 25> = Channel::new();
 
+// runtime config and commands for the readers, changed over CAN or the serial console
+static CONFIG: MsbConfig = MsbConfig::new();
+
+// health of every sensor, kept by the readers and sent on CAN by the health reporter
+static HEALTH: HealthRegistry = HealthRegistry::new();
+
 // main should be where the peripheral object is used, and then peripherals are init-ed and sent to the threads
 // periph. obj sent to threads should not be mut, they can be edited in threads
 // the loop at the end of main should be to refresh the watchdog, however main can return if needed
@@ -58,7 +64,19 @@ static CAN_CHANNEL: Channel<ThreadModeRawMutex, Frame, 25> = Channel::new();
 async fn main(spawner: Spawner) -> ! {
     info!("Initializing MSB-FW...");
     // initialize the project, ensure we can debug during sleep
-    let p = embassy_stm32::init(Config::default());
+    let mut p = embassy_stm32::init(Config::default());
+
+    // load the saved config before anything reads it, and before the watchdog as opening the
+    // store may erase a flash sector
+    match ConfigStore::new(Stm32Flash::new(Flash::new_blocking(p.FLASH))) {
+        Ok(mut store) => {
+            let settings = store.load_or_default();
+            info!("Loaded settings {}", settings);
+            CONFIG.restore(settings);
+            spawner.must_spawn(storage::config_storage(store, &CONFIG));
+        }
+        Err(err) => warn!("Could not open config storage, using defaults: {}", err),
+    }
 
     // create some GPIO on input mode and read from them
     let pin0 = Input::new(p.PC10, Pull::None);
@@ -80,44 +98,86 @@ async fn main(spawner: Spawner) -> ! {
     spawner.must_spawn(controllers::control_leds(
         // note that most types have an internal generic holding the pin or bus itself, this can be removed by degrade
         // this makes types more generic and should be done for all pins, but is not necessary for multi-bus i2c or whatnot
-        led1,
-        led2,
-        loc.clone(),
+        led1, led2, loc,
     ));
     // embassy enforces pin mappings to their correct functions for the most at compile time
     let can = Can::new(p.CAN1, p.PA11, p.PA12, IrqsCAN);
     // pass in a can channel consumer to get the frames from any producer
-    spawner.must_spawn(can_handler::can_handler(can, CAN_CHANNEL.receiver(), loc));
+    spawner.must_spawn(can_handler::can_handler(
+        can,
+        CAN_CHANNEL.receiver(),
+        loc,
+        &CONFIG,
+    ));
+    spawner.must_spawn(health::health_reporter(CAN_CHANNEL.sender(), &HEALTH));
 
     // checkout this fuckery, the official way to have two things use one i2c bus
     // see here: https://github.com/embassy-rs/embassy/blob/main/examples/rp/src/bin/shared_bus.rs
     // this uses the embassy_embedded_hal extension, which basically converts these wierd ass types to embedded_hal compatable traits
     static I2C_BUS: StaticCell<SharedI2c3> = StaticCell::new();
-    let i2c = I2c::new(
-        p.I2C3,
-        p.PA8,
-        p.PC9,
-        IrqsI2c,
+    // the bus times out every transaction, and clocks out and resets itself if a sensor holds it
+    let i2c = I2c3Bus::new(
+        p.I2C3, p.PA8, p.PC9,
         p.DMA1_CH4, // for must things embassy is DMA by default, allowing for bet use of the async executer.  NoDma can be passed to disable that
         p.DMA1_CH2,
-        Hertz(100_000),
-        i2c::Config::default(),
     );
     let i2c_bus = I2C_BUS.init(Mutex::new(i2c));
-    spawner.must_spawn(readers::temperature_reader(i2c_bus, CAN_CHANNEL.sender()));
-
-    // this pretty much straight from docs, adc dma is very new in embassy stm32 hal
-    // const ADC_BUF_SIZE: usize = 1024;
-    // let adc1 = Adc::new(p.ADC1);
-    // let adc_data = singleton!(ADCDAT : [u16; ADC_BUF_SIZE] = [0u16; ADC_BUF_SIZE])
-    //     .expect("Could not init adc buffer");
-    //let mut adc1 = adc1.into_ring_buffered(p.DMA2_CH0, adc_data);
-    // adc1.set_sample_sequence(Sequence::One, &mut p.PA0, SampleTime::CYCLES112); // SHOCKPOT
-    // adc1.set_sample_sequence(Sequence::Two, &mut p.PA5, SampleTime::CYCLES112); // STRAIN 1
-    // adc1.set_sample_sequence(Sequence::Three, &mut p.PA6, SampleTime::CYCLES112); // STRAIN 2
-    // if let Err(err) = spawner.spawn(readers::adc1_reader(adc1, CAN_CHANNEL.sender())) {
-    //     warn!("Could not spawn ADC1 task: {}", err);
-    // }
+    // not every MSB has every sensor, only start the readers of those that answer
+    let devices = inventory::discover_sensors(i2c_bus, CAN_CHANNEL.sender(), &HEALTH).await;
+    if inventory::fitted(devices.as_ref(), Sensor::Temperature) {
+        spawner.must_spawn(readers::temperature_reader(
+            i2c_bus,
+            CAN_CHANNEL.sender(),
+            &CONFIG,
+            &HEALTH,
+        ));
+    }
+    if inventory::fitted(devices.as_ref(), Sensor::Imu) {
+        // drains the FIFO on a timer. Once the EXTI line the LSM6DSO INT1 is routed to is
+        // confirmed on the board, drain it on the watermark interrupt instead with
+        // let imu_int1 = ExtiInput::new(p.<INT1 pin>, p.<its EXTI line>, Pull::Down);
+        // spawner.must_spawn(readers::imu_reader_interrupt(
+        //     i2c_bus,
+        //     imu_int1,
+        //     CAN_CHANNEL.sender(),
+        //     loc,
+        //     &CONFIG,
+        //     &HEALTH,
+        // ));
+        spawner.must_spawn(readers::imu_reader(
+            i2c_bus,
+            CAN_CHANNEL.sender(),
+            loc,
+            &CONFIG,
+            &HEALTH,
+        ));
+    }
+    if inventory::fitted(devices.as_ref(), Sensor::Tof) {
+        // polls the VL6180X until the EXTI line its GPIO1 is routed to is confirmed on the board,
+        // then pass Some(ExtiInput::new(p.<GPIO1 pin>, p.<its EXTI line>, Pull::Up)) to have the
+        // reader wait on it instead
+        let tof_gpio1 = None;
+        spawner.must_spawn(readers::tof_reader(
+            i2c_bus,
+            tof_gpio1,
+            CAN_CHANNEL.sender(),
+            &CONFIG,
+            &HEALTH,
+        ));
+    }
+
+    // the DMA fills the buffer continuously in sequence order, and holds two reads worth so the
+    // reader can't be overrun while it copies one out
+    const ADC_BUF_SIZE: usize = 2 * readers::ADC_READ_SIZE;
+    let adc1 = Adc::new(p.ADC1);
+    let adc_data = singleton!(ADCDAT : [u16; ADC_BUF_SIZE] = [0u16; ADC_BUF_SIZE])
+        .expect("Could not init adc buffer");
+    let mut adc1 = adc1.into_ring_buffered(p.DMA2_CH0, adc_data);
+    // must match msb_readers::adc::SEQUENCE
+    adc1.set_sample_sequence(Sequence::One, &mut p.PA0, SampleTime::CYCLES112); // SHOCKPOT
+    adc1.set_sample_sequence(Sequence::Two, &mut p.PA5, SampleTime::CYCLES112); // STRAIN 1
+    adc1.set_sample_sequence(Sequence::Three, &mut p.PA6, SampleTime::CYCLES112); // STRAIN 2
+    spawner.must_spawn(readers::adc1_reader(adc1, CAN_CHANNEL.sender(), &CONFIG));
 
     let mut usart = Uart::new(
         p.USART2,
@@ -130,8 +190,13 @@ async fn main(spawner: Spawner) -> ! {
     )
     .unwrap();
     let mut s: String<128> = String::new();
-    core::write!(&mut s, "MSB-FW.rs prints in RTT, not UART!\r\n",).unwrap();
+    core::write!(
+        &mut s,
+        "MSB-FW.rs prints in RTT, the UART is only a config console!\r\n",
+    )
+    .unwrap();
     unwrap!(usart.write(s.as_bytes()).await);
+    spawner.must_spawn(console::console(usart, &CONFIG));
 
     let mut watchdog = IndependentWatchdog::new(p.IWDG, 1000000);
     watchdog.unleash();
@@ -1,168 +1,601 @@
-use defmt::{trace, unwrap, warn};
+use defmt::{info, trace, unwrap, warn};
 use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice;
-use embassy_stm32::{
-    adc::RingBufferedAdc,
-    can::{Frame, StandardId},
-    peripherals::ADC1,
-};
+use embassy_futures::select::{select, Either};
+use embassy_stm32::{adc::RingBufferedAdc, can::Frame, exti::ExtiInput, peripherals::ADC1};
 use embassy_sync::{blocking_mutex::raw::ThreadModeRawMutex, channel::Sender};
-use embassy_time::{Delay, Duration, Timer};
-use sht3x_ner::Repeatability;
+use embassy_time::{with_timeout, Delay, Duration, Instant, Ticker, Timer};
+use msb_readers::{
+    adc::{self, AdcCounts},
+    capture::{ShockCapture, CAPTURE_RATE_HZ, SUMMARY_WINDOW_MS},
+    health::Sensor,
+    imu::{self, ImuAligner, ImuCorrection, ImuRate, ImuReading},
+    temperature, tof,
+};
+use ner_can_messages::msb::{CaptureMode, DeviceLocation, Reader, SensorFault};
+use vl6180x_ner::RangeQuality;
+
+use crate::{message_frame, HealthRegistry, MsbConfig, ReaderCommand, SharedI2c3};
+
+/// What a reader should do once it is done waiting
+enum Next {
+    Read,
+    Reinit,
+}
+
+/// Wait out the configured refresh time, restarting the wait when it changes and returning early
+/// on a dump or re-init
+async fn wait_refresh(reader: Reader, config: &MsbConfig) -> Next {
+    let cmd = config.commands.get(reader);
+    loop {
+        match select(Timer::after(config.refresh_time(reader)), cmd.wait()).await {
+            Either::First(_) | Either::Second(ReaderCommand::Dump) => return Next::Read,
+            Either::Second(ReaderCommand::RefreshTimeChanged) => (),
+            // the ADC reader starts over in the new mode like a re-init
+            Either::Second(ReaderCommand::Reinit | ReaderCommand::CaptureChanged) => {
+                return Next::Reinit
+            }
+        }
+    }
+}
 
-use crate::SharedI2c3;
+/// Park a reader whose sensor failed to initialize until it is told to try again
+async fn wait_reinit(reader: Reader, config: &MsbConfig) {
+    let cmd = config.commands.get(reader);
+    while !matches!(cmd.wait().await, ReaderCommand::Reinit) {}
+}
 
-const TEMPERATURE_REFRESH_TIME: Duration = Duration::from_millis(500);
-const TEMPERATURE_SEND_MSG_ID: StandardId = StandardId::new(0x602).expect("Could not parse ID");
+/// Wait out the backoff before retrying a failed initialization, or until told to try again
+async fn wait_retry(reader: Reader, config: &MsbConfig, backoff: Duration) {
+    select(Timer::after(backoff), wait_reinit(reader, config)).await;
+}
 
+/// Read the SHT30 in periodic mode, so each reading is a fetch of what it last measured instead
+/// of a blocking single shot. Condensation on the sensor is dried out with its heater, and
+/// nothing is sent until it has cooled again.
 #[embassy_executor::task]
 pub async fn temperature_reader(
     i2c: &'static SharedI2c3,
     can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    config: &'static MsbConfig,
+    health: &'static HealthRegistry,
 ) {
-    let i2c_dev = I2cDevice::new(i2c);
-    let mut sht30 = sht3x_ner::Sht3x::new(i2c_dev, sht3x_ner::Address::High);
-
     loop {
-        Timer::after(TEMPERATURE_REFRESH_TIME).await;
-        let Ok(res) = sht30
-            .measure(
-                sht3x_ner::ClockStretch::Disabled,
-                Repeatability::High,
-                &mut Delay,
-            )
-            .await
-        else {
-            warn!("Could not get temperature");
-            continue;
+        let i2c_dev = I2cDevice::new(i2c);
+        let sht30 = temperature::init_temperature(i2c_dev);
+        let rate = temperature::periodic_rate(config.refresh_times().temperature);
+        let mut sht30 = match temperature::start_temperature(sht30, rate, &mut Delay).await {
+            Ok(sht30) => sht30,
+            Err((_, err)) => {
+                warn!("Could not start sht30 periodic mode!");
+                let backoff = health.init_failed(Sensor::Temperature, &err);
+                wait_retry(Reader::Temperature, config, backoff).await;
+                continue;
+            }
         };
-        let temp: [u8; 2] = (res.temperature as i16).to_be_bytes();
-        let humidity: [u8; 2] = (res.humidity).to_be_bytes();
-        let mut bits: [u8; 4] = [0; 4];
-        bits[..2].copy_from_slice(&temp);
-        bits[2..].copy_from_slice(&humidity);
-
-        trace!("Sending temp: {}, humidity {}", res.temperature, res.humidity);
-        let frame =
-            Frame::new_data(TEMPERATURE_SEND_MSG_ID, &bits).expect("Could not create frame");
-        can_send.send(frame).await;
+        health.init_ok(Sensor::Temperature);
+
+        // the heater is switched off on every start
+        let mut recovery = temperature::condensation_recovery();
+        let mut heater = false;
+        // a SHT30 that reset has dropped out of periodic mode and never measures again
+        let stale = Duration::from_millis(3 * rate.period_ms() as u64);
+        let mut last_new = Instant::now();
+
+        while let Next::Read = wait_refresh(Reader::Temperature, config).await {
+            // a new refresh time may need a different rate, which means starting over
+            if temperature::periodic_rate(config.refresh_times().temperature) != rate {
+                break;
+            }
+
+            let (res, msg) = match temperature::fetch_temperature(&mut sht30).await {
+                Ok(Some(reading)) => reading,
+                Ok(None) if last_new.elapsed() > stale => {
+                    warn!(
+                        "No new temperature for {} ms",
+                        last_new.elapsed().as_millis()
+                    );
+                    last_new = Instant::now();
+                    if health.reading_failed(Sensor::Temperature, &SensorFault::Timeout) {
+                        break;
+                    }
+                    continue;
+                }
+                Ok(None) => {
+                    trace!("No new temperature yet");
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Could not get temperature");
+                    if health.reading_failed(Sensor::Temperature, &err) {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            health.reading_ok(Sensor::Temperature);
+            last_new = Instant::now();
+
+            // a failed heater command is tried again on the next reading
+            recovery.update(&res, Instant::now().as_millis());
+            if recovery.heater() != heater {
+                match sht30.set_heater(recovery.heater(), &mut Delay).await {
+                    Ok(()) => {
+                        heater = recovery.heater();
+                        info!("sht30 heater on: {}", heater);
+                    }
+                    Err(_) => warn!("Could not switch the sht30 heater"),
+                }
+            }
+            if recovery.recovering() {
+                trace!("Drying out sht30, humidity {}", res.humidity);
+                continue;
+            }
+
+            trace!(
+                "Sending temp: {}, humidity {}",
+                res.temperature,
+                res.humidity
+            );
+            can_send.send(message_frame(&msg)).await;
+        }
+        if sht30.stop(&mut Delay).await.is_err() {
+            warn!("Could not stop sht30 periodic mode");
+        }
+        info!("Re-initializing sht30");
     }
 }
 
-const LSM6DSO_ADDR: u8 = 0x6A;
-const IMU_REFRESH_TIME: Duration = Duration::from_millis(500);
-const IMU_SEND_MSG_ID: StandardId = StandardId::new(0x603).expect("Could not parse ID");
-const GYRO_SEND_MSG_ID: StandardId = StandardId::new(0x604).expect("Could not parse ID");
+/// FIFO readings drained at a time, a drain period at the fastest rate fits with room to spare
+const IMU_READINGS: usize = 16;
+
+/// Longest wait for the IMU watermark interrupt before draining anyway, in case INT1 is not
+/// wired up or the interrupt was missed
+const IMU_INTERRUPT_TIMEOUT: Duration = Duration::from_millis(4 * imu::FIFO_DRAIN_MS);
 
+/// Wait until the FIFO is worth draining, either one drain period or until INT1 shows the
+/// watermark was reached, returning early on a dump. A new refresh time may change the FIFO rate,
+/// so it restarts the reader like a re-init.
+async fn wait_drain(config: &MsbConfig, int1: Option<&mut ExtiInput<'static>>) -> Next {
+    let cmd = config.commands.get(Reader::Imu);
+    let ready = async {
+        match int1 {
+            // the watermark interrupt is a level, so a drain that ran late still sees it
+            Some(int1) => {
+                if with_timeout(IMU_INTERRUPT_TIMEOUT, int1.wait_for_high())
+                    .await
+                    .is_err()
+                {
+                    warn!("No lsm6dso FIFO interrupt, draining anyway");
+                }
+            }
+            None => Timer::after_millis(imu::FIFO_DRAIN_MS).await,
+        }
+    };
+    match select(ready, cmd.wait()).await {
+        Either::First(()) | Either::Second(ReaderCommand::Dump) => Next::Read,
+        Either::Second(_) => Next::Reinit,
+    }
+}
+
+/// Drain the IMU FIFO on a timer
 #[embassy_executor::task]
 pub async fn imu_reader(
     i2c: &'static SharedI2c3,
     can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    location: DeviceLocation,
+    config: &'static MsbConfig,
+    health: &'static HealthRegistry,
 ) {
-    let i2c_dev = I2cDevice::new(i2c);
-    let Ok(mut lsm6dso) = lsm6dso_ner::Lsm6dso::new(i2c_dev, LSM6DSO_ADDR).await else {
-        warn!("Could not initialize lsm6dso!");
-        return;
-    };
+    run_imu(i2c, None, can_send, location, config, health).await
+}
 
-    let mut accel_bits: [u8; 6] = [0; 6];
-    let mut gyro_bits: [u8; 6] = [0; 6];
+/// Drain the IMU FIFO when the LSM6DSO raises INT1 at its watermark
+#[embassy_executor::task]
+pub async fn imu_reader_interrupt(
+    i2c: &'static SharedI2c3,
+    mut int1: ExtiInput<'static>,
+    can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    location: DeviceLocation,
+    config: &'static MsbConfig,
+    health: &'static HealthRegistry,
+) {
+    run_imu(i2c, Some(&mut int1), can_send, location, config, health).await
+}
 
+async fn run_imu(
+    i2c: &'static SharedI2c3,
+    mut int1: Option<&mut ExtiInput<'static>>,
+    can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    location: DeviceLocation,
+    config: &'static MsbConfig,
+    health: &'static HealthRegistry,
+) -> ! {
+    // the one of the corner the board is strapped to, changed from the console and taken from
+    // the next boot like the calibration below
+    let mounting = config.mounting(location);
+    // self-tested and calibrated on the first init after boot, when the car is still on the stand.
+    // A re-init can happen while driving, so it only puts the accelerometer offset back and keeps
+    // the gyro bias estimate.
+    let mut calibrate = true;
+    let mut bias = None;
+    let mut correction = ImuCorrection::new(mounting, None);
     loop {
-        Timer::after(IMU_REFRESH_TIME).await;
-        let Ok(accel) = lsm6dso.read_accelerometer().await else {
-            warn!("Could not read lsm6dso accel");
-            continue;
+        let i2c_dev = I2cDevice::new(i2c);
+        let mut lsm6dso = match imu::init_imu(i2c_dev).await {
+            Ok(lsm6dso) => lsm6dso,
+            Err(err) => {
+                warn!("Could not initialize lsm6dso!");
+                let backoff = health.init_failed(Sensor::Imu, &err);
+                wait_retry(Reader::Imu, config, backoff).await;
+                continue;
+            }
         };
-        let Ok(gyro) = lsm6dso.read_gyro().await else {
-            warn!("Could not read lsm6dso gyro");
-            continue;
+
+        let rate = ImuRate::for_refresh_time(config.refresh_times().imu);
+        match bias {
+            None if calibrate => {
+                calibrate = false;
+                match lsm6dso.self_test(&mut Delay).await {
+                    Ok(test) if test.passed() => info!("lsm6dso self-test passed"),
+                    Ok(test) => {
+                        warn!(
+                            "lsm6dso self-test failed, accel moved {} mg, gyro moved {} dps",
+                            test.accelerometer_positive.change, test.gyroscope_positive.change
+                        );
+                        health.self_test_failed(Sensor::Imu);
+                    }
+                    Err(_) => warn!("Could not self-test lsm6dso"),
+                }
+                match imu::calibrate_imu(&mut lsm6dso, &mut Delay, rate, &mounting).await {
+                    Ok(calibration) => {
+                        info!(
+                            "Calibrated lsm6dso, accel offset {} m/s^2, gyro bias {} rad/s",
+                            calibration.accelerometer, calibration.gyroscope
+                        );
+                        correction = ImuCorrection::new(mounting, Some(&calibration));
+                        bias = Some(calibration);
+                    }
+                    Err(_) => {
+                        warn!("Could not calibrate lsm6dso, estimating the gyro bias from scratch")
+                    }
+                }
+            }
+            None => (),
+            Some(calibration) => {
+                if lsm6dso
+                    .set_accelerometer_offset(calibration.accelerometer)
+                    .await
+                    .is_err()
+                {
+                    warn!("Could not restore the lsm6dso accel offset");
+                }
+            }
+        }
+
+        let started = match imu::start_imu_fifo(&mut lsm6dso, rate).await {
+            Ok(()) if int1.is_some() => imu::route_imu_watermark(&mut lsm6dso).await,
+            started => started,
         };
+        let started = match started {
+            Ok(()) => imu::start_imu_events(&mut lsm6dso).await,
+            err => err,
+        };
+        let started = match started {
+            Ok(()) => imu::start_imu_clock(&mut lsm6dso).await,
+            Err(err) => Err(err),
+        };
+        let mut clock = match started {
+            Ok(clock) => clock,
+            Err(err) => {
+                warn!("Could not start the lsm6dso FIFO, timestamp and event detection!");
+                let backoff = health.init_failed(Sensor::Imu, &err);
+                wait_retry(Reader::Imu, config, backoff).await;
+                continue;
+            }
+        };
+        health.init_ok(Sensor::Imu);
+        info!("Batching IMU samples at {}", rate);
 
-        accel_bits[0..2].copy_from_slice(&(((accel.0 * 1000.0) as i16).to_be_bytes()));
-        accel_bits[2..4].copy_from_slice(&(((accel.1 * 1000.0) as i16).to_be_bytes()));
-        accel_bits[4..].copy_from_slice(&(((accel.2 * 1000.0) as i16).to_be_bytes()));
+        let mut aligner = ImuAligner::new(rate);
+        let mut readings = [ImuReading::default(); IMU_READINGS];
+        while let Next::Read = wait_drain(config, int1.as_deref_mut()).await {
+            // every drain, so the sample times follow the LSM6DSO clock drifting against ours
+            let now = || Instant::now().as_micros();
+            if imu::sync_imu_clock(&mut lsm6dso, &mut clock, now)
+                .await
+                .is_err()
+            {
+                warn!("Could not read lsm6dso timestamp, timing from the last one");
+            }
 
-        trace!("Sending accel: x {}, y {}, z {}", accel.0, accel.1, accel.2);
-        let accel_frame =
-            Frame::new_data(IMU_SEND_MSG_ID, &accel_bits).expect("Could not create frame");
+            // a late drain finds more than one buffer of readings, keep going until it's empty
+            let mut failed = false;
+            loop {
+                let (count, overrun) =
+                    match imu::drain_imu(&mut lsm6dso, &mut aligner, &mut readings).await {
+                        Ok(drained) => drained,
+                        Err(err) => {
+                            warn!("Could not read lsm6dso FIFO");
+                            failed = health.reading_failed(Sensor::Imu, &err);
+                            break;
+                        }
+                    };
+                health.reading_ok(Sensor::Imu);
+                if overrun {
+                    warn!("lsm6dso FIFO overran, IMU samples were lost");
+                }
 
-        gyro_bits[0..2].copy_from_slice(&(((gyro.0 * 1000.0) as i16).to_be_bytes()));
-        gyro_bits[2..4].copy_from_slice(&(((gyro.1 * 1000.0) as i16).to_be_bytes()));
-        gyro_bits[4..].copy_from_slice(&(((gyro.2 * 1000.0) as i16).to_be_bytes()));
+                for reading in &readings[..count] {
+                    let reading = correction.apply(reading);
+                    let (accel, gyro) = (reading.accel, reading.gyro);
+                    trace!(
+                        "Sending IMU at {} ms: accel x {}, y {}, z {}, gyro x {}, y {}, z {}",
+                        clock.time_ms(reading.time),
+                        accel.0,
+                        accel.1,
+                        accel.2,
+                        gyro.0,
+                        gyro.1,
+                        gyro.2
+                    );
+                    let (accel_message, gyro_message) = reading.messages(&clock);
+                    can_send.send(message_frame(&accel_message)).await;
+                    can_send.send(message_frame(&gyro_message)).await;
+                }
 
-        trace!("Sending gyro: x {}, y {}, z {}", gyro.0, gyro.1, gyro.2);
-        let gyro_frame =
-            Frame::new_data(GYRO_SEND_MSG_ID, &gyro_bits).expect("Could not create frame");
+                if count < readings.len() {
+                    break;
+                }
+            }
+            if failed {
+                break;
+            }
 
-        can_send.send(accel_frame).await;
-        can_send.send(gyro_frame).await;
+            match imu::read_imu_events(&mut lsm6dso, &clock).await {
+                Ok(events) => {
+                    for event in events.into_iter().flatten() {
+                        info!("IMU event {} on axes {:04b}", event.event, event.axes);
+                        can_send.send(message_frame(&event)).await;
+                    }
+                }
+                Err(err) => {
+                    warn!("Could not read lsm6dso events");
+                    if health.reading_failed(Sensor::Imu, &err) {
+                        break;
+                    }
+                }
+            }
+        }
+        info!("Re-initializing lsm6dso");
     }
 }
 
-const TOF_REFRESH_TIME: Duration = Duration::from_millis(500);
-const TOF_SEND_MSG_ID: StandardId = StandardId::new(0x607).expect("Could not parse ID");
-
+/// Read the VL6180X ranging continuously, one period per refresh time. With its GPIO1 the bus is
+/// only touched once GPIO1 flags a new sample, without it the interrupt status is polled.
 #[embassy_executor::task]
 pub async fn tof_reader(
     i2c: &'static SharedI2c3,
+    mut gpio1: Option<ExtiInput<'static>>,
     can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    config: &'static MsbConfig,
+    health: &'static HealthRegistry,
 ) {
-    let i2c_dev = I2cDevice::new(i2c);
-    let Ok(mut vl6180x) = vl6180x_ner::VL6180X::new(i2c_dev).await else {
-        warn!("Could not initialize lsm6dso!");
-        return;
-    };
-
+    let cmd = config.commands.get(Reader::Tof);
     loop {
-        let Ok(rng) = vl6180x.poll_range_mm_single_blocking().await else {
-            warn!("Failed to get measurement!");
-            continue;
+        let rate = tof::continuous_rate(config.refresh_times().tof);
+        let (period, every) = rate;
+        let i2c_dev = I2cDevice::new(i2c);
+        let mut vl6180x = match tof::start_tof_continuous(i2c_dev, period).await {
+            Ok(vl6180x) => vl6180x,
+            Err(err) => {
+                warn!("Could not start vl6180x continuous ranging!");
+                let backoff = health.init_failed(Sensor::Tof, &err);
+                wait_retry(Reader::Tof, config, backoff).await;
+                continue;
+            }
         };
-        let range_bits = rng.to_be_bytes();
-        trace!("Sending TOF range: {}", rng);
-        can_send
-            .send(unwrap!(Frame::new_data(TOF_SEND_MSG_ID, &range_bits)))
-            .await;
+        health.init_ok(Sensor::Tof);
+
+        let timeout = Duration::from_millis(3 * period as u64);
+        let mut samples = 0;
+        let mut dump = false;
+        loop {
+            let sample = async {
+                match gpio1.as_mut() {
+                    Some(gpio1) => with_timeout(timeout, tof::wait_tof(&mut vl6180x, gpio1))
+                        .await
+                        .unwrap_or(Err(vl6180x_ner::Error::Timeout)),
+                    None => tof::poll_tof(&mut vl6180x, &mut Delay).await,
+                }
+            };
+            let sample = match select(sample, cmd.wait()).await {
+                Either::First(sample) => sample,
+                Either::Second(ReaderCommand::Dump) => {
+                    dump = true;
+                    continue;
+                }
+                // a new refresh time may need a different period, which means starting over
+                Either::Second(ReaderCommand::RefreshTimeChanged) => {
+                    if tof::continuous_rate(config.refresh_times().tof) == rate {
+                        continue;
+                    }
+                    break;
+                }
+                Either::Second(ReaderCommand::Reinit | ReaderCommand::CaptureChanged) => break,
+            };
+            // no sample for three periods means GPIO1 is not wired up, or the VL6180X reset and
+            // stopped ranging
+            let (measurement, msg) = match sample {
+                Ok(reading) => reading,
+                Err(err) => {
+                    warn!("Failed to get measurement!");
+                    if health.reading_failed(Sensor::Tof, &err) {
+                        break;
+                    }
+                    continue;
+                }
+            };
+            // no target or too much ambient light is about the scene, the sample is still sent
+            // with its quality, but a failed VCSEL or PLL check is about the sensor
+            if measurement.quality() == RangeQuality::SensorFault {
+                warn!("vl6180x failed a VCSEL or PLL check");
+                if health.reading_failed(Sensor::Tof, &SensorFault::Other) {
+                    break;
+                }
+            } else {
+                health.reading_ok(Sensor::Tof);
+            }
 
-        Timer::after(TOF_REFRESH_TIME).await;
+            // refresh times past the longest period send every so many samples
+            samples += 1;
+            if samples < every && !dump {
+                continue;
+            }
+            samples = 0;
+            dump = false;
+            trace!(
+                "Sending TOF range: {} quality {}",
+                measurement.range_mm,
+                msg.quality
+            );
+            can_send.send(message_frame(&msg)).await;
+        }
+        if vl6180x.stop_range_continuous_mode().await.is_err() {
+            warn!("Could not stop vl6180x continuous ranging");
+        }
+        info!("Re-initializing vl6180x");
     }
 }
 
-const ADC_REFRESH_TIME: Duration = Duration::from_millis(250);
-const STRAIN_SEND_MSG_ID: StandardId = StandardId::new(0x606).expect("Could not parse ID");
-const SHOCKPOT_SEND_MSG_ID: StandardId = StandardId::new(0x605).expect("Could not parse ID");
+/// Samples averaged per ADC reading, a whole number of [`adc::SEQUENCE`]s
+pub const ADC_READ_SIZE: usize = 32 * adc::SEQUENCE.len();
+
+/// Samples averaged per shock pot sample while capturing, short enough to finish well within
+/// one capture period
+const CAPTURE_READ_SIZE: usize = 4 * adc::SEQUENCE.len();
 
 #[embassy_executor::task]
 pub async fn adc1_reader(
     mut adc1: RingBufferedAdc<'static, ADC1>,
     can_send: Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    config: &'static MsbConfig,
 ) {
-    let mut measurements: [u16; 60] = [0u16; 120 / 2];
-    let mut strain_bits: [u8; 4] = [0; 4];
+    loop {
+        match config.capture_mode() {
+            CaptureMode::Off => adc_averaged(&mut adc1, &can_send, config).await,
+            mode => adc_capture(&mut adc1, &can_send, config, mode).await,
+        }
+        info!("ADC capture mode is now {}", config.capture_mode());
+    }
+}
 
+/// Averaged readings at the refresh time, until the capture mode changes
+async fn adc_averaged(
+    adc1: &mut RingBufferedAdc<'static, ADC1>,
+    can_send: &Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    config: &'static MsbConfig,
+) {
     loop {
-        match adc1.read(&mut measurements).await {
-            Ok(_) => {
-                adc1.teardown_adc();
-                trace!("Sending strain + shockpot: {}", measurements);
-                // TODO transform measurements
-                strain_bits[0..2].copy_from_slice(&measurements[1].to_be_bytes());
-                strain_bits[2..4].copy_from_slice(&measurements[2].to_be_bytes());
-                can_send
-                    .send(unwrap!(Frame::new_data(
-                        STRAIN_SEND_MSG_ID,
-                        &measurements[0].to_be_bytes()
-                    )))
-                    .await;
-                can_send
-                    .send(unwrap!(Frame::new_data(SHOCKPOT_SEND_MSG_ID, &strain_bits)))
-                    .await;
-            }
-            Err(_) => {
-                warn!("DMA overrun");
-                continue;
+        if let Some(counts) = read_counts::<ADC_READ_SIZE>(adc1).await {
+            send_counts(&counts, can_send, config).await;
+        }
+        // the ADC has nothing to re-initialize, so a re-init only starts over
+        if let Next::Reinit = wait_refresh(Reader::Adc, config).await {
+            return;
+        }
+    }
+}
+
+/// Sample the shock pot at the capture rate, still sending the averaged readings at the refresh
+/// time, until the capture mode changes
+async fn adc_capture(
+    adc1: &mut RingBufferedAdc<'static, ADC1>,
+    can_send: &Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    config: &'static MsbConfig,
+    mode: CaptureMode,
+) {
+    let cmd = config.commands.get(Reader::Adc);
+    let mut capture = ShockCapture::new();
+    let mut ticker = Ticker::every(Duration::from_hz(CAPTURE_RATE_HZ as u64));
+    let summary_window = Duration::from_millis(SUMMARY_WINDOW_MS as u64);
+    let mut next_summary = Instant::now() + summary_window;
+    let mut next_reading = Instant::now();
+    let mut last = None;
+
+    loop {
+        match select(ticker.next(), cmd.wait()).await {
+            Either::First(()) | Either::Second(ReaderCommand::RefreshTimeChanged) => (),
+            Either::Second(ReaderCommand::Dump) => next_reading = Instant::now(),
+            Either::Second(ReaderCommand::Reinit | ReaderCommand::CaptureChanged) => return,
+        }
+
+        // a failed read repeats the last sample, so the sample count keeps time
+        if let Some(counts) = read_counts::<CAPTURE_READ_SIZE>(adc1).await {
+            last = Some(counts);
+        }
+        let Some(counts) = last else {
+            continue;
+        };
+
+        let travel = config.adc_calibration().shockpot.apply(counts.shockpot);
+        if let Some(samples) = capture.push(travel) {
+            // dropping is better than stalling the sampling, the sequence shows the gap
+            if mode == CaptureMode::Raw && can_send.try_send(message_frame(&samples)).is_err() {
+                warn!("Dropped shock samples {}", samples.sequence);
             }
         }
-        Timer::after(ADC_REFRESH_TIME).await;
+
+        let now = Instant::now();
+        if mode == CaptureMode::Summary && now >= next_summary {
+            next_summary += summary_window;
+            if let Some((sequence, summary)) = capture.take_summary() {
+                let (stats, histograms) = summary.messages(sequence);
+                trace!(
+                    "Sending shock summary {}: {} to {} mm/s",
+                    sequence,
+                    stats.velocity_min,
+                    stats.velocity_max
+                );
+                can_send.send(message_frame(&stats)).await;
+                for histogram in histograms {
+                    can_send.send(message_frame(&histogram)).await;
+                }
+            }
+        }
+        if now >= next_reading {
+            next_reading = now + config.refresh_time(Reader::Adc);
+            send_counts(&counts, can_send, config).await;
+        }
     }
 }
+
+/// Average one buffer of every channel, `None` on a DMA overrun
+async fn read_counts<const N: usize>(
+    adc1: &mut RingBufferedAdc<'static, ADC1>,
+) -> Option<AdcCounts> {
+    let mut measurements = [0u16; N];
+    let res = adc1.read(&mut measurements).await;
+    // stopping restarts the next read at the first channel of the sequence
+    adc1.teardown_adc();
+    if res.is_err() {
+        warn!("DMA overrun");
+        return None;
+    }
+    Some(unwrap!(AdcCounts::average(&measurements)))
+}
+
+async fn send_counts(
+    counts: &AdcCounts,
+    can_send: &Sender<'static, ThreadModeRawMutex, Frame, 25>,
+    config: &MsbConfig,
+) {
+    let (shockpot, strain) = counts.messages(&config.adc_calibration());
+    trace!(
+        "Sending shockpot: {} mm, strain: {} ue {} ue",
+        shockpot.travel,
+        strain.strain_1,
+        strain.strain_2
+    );
+    can_send.send(message_frame(&shockpot)).await;
+    can_send.send(message_frame(&strain)).await;
+}
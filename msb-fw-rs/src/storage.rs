@@ -0,0 +1,27 @@
+use defmt::{info, warn};
+use embassy_time::{Duration, Timer};
+
+use crate::{MsbConfig, MsbConfigStore};
+
+/// Changes arriving this close together are saved as one record, so console commands typed one
+/// after another don't each use a slot of flash
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Save the settings to flash whenever they change.
+/// The spare bank is erased at boot, so a save never stalls the CPU long enough to trip the
+/// watchdog unless the active bank fills up twice without a reboot.
+#[embassy_executor::task]
+pub async fn config_storage(mut store: MsbConfigStore, config: &'static MsbConfig) {
+    loop {
+        config.changed.wait().await;
+        Timer::after(SAVE_DELAY).await;
+        // anything changed during the delay is included in this save
+        config.changed.reset();
+
+        let settings = config.settings();
+        match store.store(&settings) {
+            Ok(()) => info!("Saved settings {} to {}", settings, store.active_bank()),
+            Err(err) => warn!("Could not save settings: {}", err),
+        }
+    }
+}
@@ -23,6 +23,7 @@ use embassy_stm32::{
     usart::{self},
     Config,
 };
+use ner_can_messages::{wheel::WheelButtons, CanMessage};
 use {defmt_rtt as _, panic_probe as _};
 
 // here are our interrupts.  Embassy is interrupt by default
@@ -38,7 +39,7 @@ bind_interrupts!(struct IrqsUsart {
     USART2 => usart::InterruptHandler<peripherals::USART2>;
 });
 
-const SEND_MSG_ID: StandardId = StandardId::new(0x680).expect("Could not parse ID");
+const SEND_MSG_ID: StandardId = StandardId::new(WheelButtons::DEF.id).expect("Could not parse ID");
 
 // main should be where the peripheral object is used, and then peripherals are init-ed and sent to the threads
 // periph. obj sent to threads should not be mut, they can be edited in threads
@@ -87,16 +88,18 @@ async fn main(_spawner: Spawner) -> ! {
         ])
         .await;
 
-        let but1_val = (button1.get_level() == Level::Low) as u8;
-        let but2_val = (button2.get_level() == Level::Low) as u8;
-        let but3_val = (button3.get_level() == Level::Low) as u8;
-        let but4_val = (button4.get_level() == Level::Low) as u8;
-        let but5_val = (button5.get_level() == Level::Low) as u8;
-        let but6_val = (button6.get_level() == Level::Low) as u8;
+        let buttons = WheelButtons {
+            button_1: button1.get_level() == Level::Low,
+            button_2: button2.get_level() == Level::Low,
+            button_3: button3.get_level() == Level::Low,
+            button_4: button4.get_level() == Level::Low,
+            button_5: button5.get_level() == Level::Low,
+            button_6: button6.get_level() == Level::Low,
+        };
 
         can.write(&unwrap!(Frame::new_data(
             SEND_MSG_ID,
-            &[but1_val, but2_val, but3_val, but4_val, but5_val, but6_val]
+            buttons.encode().as_bytes()
         )))
         .await;
     }